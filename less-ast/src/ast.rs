@@ -1,6 +1,8 @@
 use less_lexer::token::Token;
 use serde::{Deserialize, Serialize};
 
+use crate::source::FileId;
+
 pub type Atom = String;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -15,6 +17,7 @@ pub enum StyleContent {
     AtRule(AtRule),
     DefinedStatement(DefinedStatement),
     MixinCall(MixinCall),
+    Comment(Comment),
 }
 
 
@@ -28,7 +31,7 @@ pub struct QualifiedRule {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AtRule {
-    // todo span
+    pub span: Span,
     pub name: AtKeyword,
     pub prelude: VariableValueList,
     pub block: Option<CurlyBracketsBlock>,
@@ -49,6 +52,7 @@ pub enum DefinedStatement {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MixinDefined {
+    pub span: Span,
     pub name: SimpleSelector,
     pub params: Vec<Param>,
     pub block: CurlyBracketsBlock,
@@ -56,12 +60,14 @@ pub struct MixinDefined {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Param {
+    pub span: Span,
     pub name: AtKeyword,
     pub default_params: Option<VariableValueList>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VariableDefined {
+    pub span: Span,
     pub name: AtKeyword,
     pub value: VariableValueList,
 }
@@ -75,10 +81,13 @@ pub enum VariableDefinedValue {
     Ident(Ident),
     Important(Ident),
     PreservedToken(PreservedToken),
+    // a: yes /* comment */;
+    Comment(Comment),
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MapVariableDefined {
+    pub span: Span,
     pub name: AtKeyword,
     pub props: DeclarationList,
 }
@@ -87,6 +96,7 @@ pub type DeclarationList = Vec<Declaration>;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Declaration {
+    pub span: Span,
     pub name: Atom,
     pub value: DeclarationProps,
 }
@@ -97,6 +107,7 @@ pub type ComponentValueList = Vec<ComponentValue>;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CurlyBracketsBlock {
+    pub span: Span,
     pub content: Vec<CurlyBracketsBlockContent>,
 }
 
@@ -107,6 +118,7 @@ pub enum CurlyBracketsBlockContent {
     DefinedStatement(DefinedStatement),
     DeclarationList(DeclarationList),
     MixinCall(MixinCall),
+    Comment(Comment),
 }
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
@@ -120,6 +132,7 @@ pub enum ComponentValue {
 // .a();
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MixinCall {
+    pub span: Span,
     pub name: SelectorComponentList,
     pub params: Option<VariableValueList>,
 }
@@ -127,6 +140,7 @@ pub struct MixinCall {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct FunctionDefinition {
+    pub span: Span,
     pub name: Ident,
     pub params: Vec<Ident>,
     pub guarded: Option<Express>,
@@ -139,7 +153,7 @@ pub enum Express {
     FunctionExpression(FunctionExpression),
     MixinCall(MixinCall),
     VariableExpression(VariableExpression),
-    ParenthesesExpression(Box<Express>),
+    ParenthesesExpression(ParenthesesExpression),
     StringEscape(StringLiteral),
 }
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -153,6 +167,7 @@ pub enum VariableExpression {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MapVariable {
+    pub span: Span,
     pub property: Ident,
     pub object: Box<Express>,
 }
@@ -166,16 +181,25 @@ pub struct Color {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
 pub struct FunctionExpression {
+    pub span: Span,
     pub name: Ident,
     pub params: VariableValueList,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BinaryExpression {
+    pub span: Span,
     pub left: Box<Express>,
     pub operator: BinaryOperator,
     pub right: Box<Express>,
 }
+/// (1 + 2)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParenthesesExpression {
+    pub span: Span,
+    pub express: Box<Express>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     Plus,
@@ -188,7 +212,8 @@ pub type SelectorComponentList = Vec<Selector>;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Selector {
-    ParentSelector,
+    // &
+    ParentSelector(Span),
     SimpleSelector(SimpleSelector),
     PseudoSelector(PseudoSelector),
 }
@@ -226,6 +251,7 @@ pub enum PreservedToken {
     AtKeyword(AtKeyword),
     String(StringLiteral),
     Number(NumberLiteral),
+    Url(UrlLiteral),
     Token(LexerToken),
 }
 
@@ -235,6 +261,14 @@ pub struct LexerToken {
     pub name: Atom,
 }
 
+/// `/* block */` or `// line`, the value keeps the delimiters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub span: Span,
+    pub value: Atom,
+    pub is_line: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Ident {
     pub span: Span,
@@ -259,21 +293,117 @@ pub struct StringLiteral {
     pub value: Atom,
 }
 
+/**
+ * url(a.png) url("a.png")
+ * value is the url without the surrounding quotes
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlLiteral {
+    pub span: Span,
+    pub value: Atom,
+    pub quote: Option<char>,
+}
+
+/**
+ * byte offsets into the file the node was parsed from,
+ * use `SourceFile::position` to get the line and column
+ */
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Span {
     pub start: usize,
     pub end: usize,
+    pub file: FileId,
 }
 
 impl Span {
     pub fn new(start: usize, end: usize) -> Self {
-        Self { start, end }
+        Self {
+            start,
+            end,
+            file: FileId::default(),
+        }
+    }
+
+    pub fn with_file(self, file: FileId) -> Self {
+        Self { file, ..self }
     }
 
     pub fn merge(&self, other: &Self) -> Self {
         Self {
             start: self.start.min(other.start),
             end: self.end.max(other.end),
+            file: self.file,
+        }
+    }
+}
+
+impl Express {
+    pub fn span(&self) -> &Span {
+        match self {
+            Express::BinaryExpression(binary) => &binary.span,
+            Express::FunctionExpression(function) => &function.span,
+            Express::MixinCall(mixin_call) => &mixin_call.span,
+            Express::VariableExpression(variable) => variable.span(),
+            Express::ParenthesesExpression(parentheses) => &parentheses.span,
+            Express::StringEscape(string) => &string.span,
+        }
+    }
+}
+
+impl VariableExpression {
+    pub fn span(&self) -> &Span {
+        match self {
+            VariableExpression::Variable(variable) => &variable.span,
+            VariableExpression::MapVariable(map_variable) => &map_variable.span,
+            VariableExpression::Color(color) => &color.span,
+            VariableExpression::PreservedToken(token) => token.span(),
+        }
+    }
+}
+
+impl VariableDefinedValue {
+    pub fn span(&self) -> &Span {
+        match self {
+            VariableDefinedValue::Express(express) => express.span(),
+            VariableDefinedValue::Ident(ident) | VariableDefinedValue::Important(ident) => {
+                &ident.span
+            }
+            VariableDefinedValue::PreservedToken(token) => token.span(),
+            VariableDefinedValue::Comment(comment) => &comment.span,
+        }
+    }
+}
+
+impl PreservedToken {
+    pub fn span(&self) -> &Span {
+        match self {
+            PreservedToken::Ident(ident) => &ident.span,
+            PreservedToken::AtKeyword(at_keyword) => &at_keyword.span,
+            PreservedToken::String(string) => &string.span,
+            PreservedToken::Number(number) => &number.span,
+            PreservedToken::Url(url) => &url.span,
+            PreservedToken::Token(token) => &token.span,
+        }
+    }
+}
+
+impl Selector {
+    pub fn span(&self) -> &Span {
+        match self {
+            Selector::ParentSelector(span) => span,
+            Selector::SimpleSelector(simple) => &simple.span,
+            Selector::PseudoSelector(PseudoSelector::PseudoFunction(function)) => &function.span,
+            Selector::PseudoSelector(PseudoSelector::PseudoElement(element)) => &element.span,
+        }
+    }
+}
+
+impl DefinedStatement {
+    pub fn span(&self) -> &Span {
+        match self {
+            DefinedStatement::VariableDefined(variable) => &variable.span,
+            DefinedStatement::MapVariableDefined(map) => &map.span,
+            DefinedStatement::MixinDefined(mixin) => &mixin.span,
         }
     }
 }
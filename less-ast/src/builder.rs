@@ -0,0 +1,653 @@
+/*!
+ * builds `less_ast` nodes from strings instead of by hand, every span is `Span::default()`
+ *
+ * ```
+ * use less_ast::builder::StylesheetBuilder;
+ *
+ * let sheet = StylesheetBuilder::new()
+ *     .variable("@brand", "#0af")
+ *     .rule(".btn, .link", |rule| {
+ *         rule.decl("color", "@brand")
+ *             .decl("margin", "0 auto")
+ *             .nest("&:hover", |rule| rule.decl("color", "darken(@brand, 10%)"))
+ *     })
+ *     .at_rule("@media", "print", |block| block.rule(".btn", |rule| rule.decl("display", "none")))
+ *     .build();
+ * assert_eq!(sheet.content.len(), 3);
+ * ```
+ *
+ * selectors and values are read by a small splitter, not by `less_parser`, it gives the same
+ * nodes as the parser for the syntax listed on `selector_list` and `value` (tests/builder.rs
+ * checks that) but leaves out the `" "` the parser keeps before `{`, after `,` and between
+ * some values. anything else, like operators in `@a * 2`, `(...)`, `~"..."` or `1e3`,
+ * comes out as plain idents, build the `Express` and pass the value list for those
+ */
+
+use crate::ast::*;
+
+/// top level content of a stylesheet
+#[derive(Debug, Clone, Default)]
+pub struct StylesheetBuilder {
+    content: Vec<StyleContent>,
+}
+
+impl StylesheetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `@name: value;`, the `@` is optional
+    pub fn variable(mut self, name: &str, value: impl IntoValue) -> Self {
+        self.content
+            .push(StyleContent::DefinedStatement(variable(name, value)));
+        self
+    }
+
+    /// `selectors { ... }`
+    pub fn rule(
+        mut self,
+        selectors: &str,
+        block: impl FnOnce(BlockBuilder) -> BlockBuilder,
+    ) -> Self {
+        self.content
+            .push(StyleContent::QualifiedRule(rule(selectors, block)));
+        self
+    }
+
+    /// `@name prelude { ... }`
+    pub fn at_rule(
+        mut self,
+        name: &str,
+        prelude: impl IntoValue,
+        block: impl FnOnce(BlockBuilder) -> BlockBuilder,
+    ) -> Self {
+        let block = Some(block(BlockBuilder::new()).build());
+        self.content
+            .push(StyleContent::AtRule(at_rule(name, prelude, block)));
+        self
+    }
+
+    /// `@name prelude;`, e.g. `.at_statement("@import", "\"theme\"")`
+    pub fn at_statement(mut self, name: &str, prelude: impl IntoValue) -> Self {
+        self.content
+            .push(StyleContent::AtRule(at_rule(name, prelude, None)));
+        self
+    }
+
+    /// `.mixin(args);`
+    pub fn mixin_call(mut self, name: &str, args: impl IntoValue) -> Self {
+        self.content
+            .push(StyleContent::MixinCall(mixin_call(name, args)));
+        self
+    }
+
+    /// `/* text */`
+    pub fn comment(mut self, text: &str) -> Self {
+        self.content.push(StyleContent::Comment(comment(text)));
+        self
+    }
+
+    pub fn build(self) -> Stylesheets {
+        Stylesheets {
+            span: Span::default(),
+            content: self.content,
+        }
+    }
+}
+
+/// the content of a `{ ... }` block
+#[derive(Debug, Clone, Default)]
+pub struct BlockBuilder {
+    content: Vec<CurlyBracketsBlockContent>,
+}
+
+impl BlockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `name: value;`, declarations next to each other share a `DeclarationList` as they do when parsed
+    pub fn decl(mut self, name: &str, value: impl IntoValue) -> Self {
+        let declaration = Declaration {
+            span: Span::default(),
+            name: name.to_string(),
+            value: value.into_value(),
+        };
+        match self.content.last_mut() {
+            Some(CurlyBracketsBlockContent::DeclarationList(list)) => list.push(declaration),
+            _ => self
+                .content
+                .push(CurlyBracketsBlockContent::DeclarationList(vec![
+                    declaration,
+                ])),
+        }
+        self
+    }
+
+    /// `@name: value;`, the `@` is optional
+    pub fn variable(mut self, name: &str, value: impl IntoValue) -> Self {
+        self.content
+            .push(CurlyBracketsBlockContent::DefinedStatement(variable(
+                name, value,
+            )));
+        self
+    }
+
+    /// a nested rule, `&` is the parent selector
+    pub fn nest(
+        mut self,
+        selectors: &str,
+        block: impl FnOnce(BlockBuilder) -> BlockBuilder,
+    ) -> Self {
+        self.content
+            .push(CurlyBracketsBlockContent::QualifiedRule(rule(
+                selectors, block,
+            )));
+        self
+    }
+
+    /// `nest` under the name used at the top level
+    pub fn rule(self, selectors: &str, block: impl FnOnce(BlockBuilder) -> BlockBuilder) -> Self {
+        self.nest(selectors, block)
+    }
+
+    /// `@name prelude { ... }`
+    pub fn at_rule(
+        mut self,
+        name: &str,
+        prelude: impl IntoValue,
+        block: impl FnOnce(BlockBuilder) -> BlockBuilder,
+    ) -> Self {
+        let block = Some(block(BlockBuilder::new()).build());
+        self.content.push(CurlyBracketsBlockContent::AtRule(at_rule(
+            name, prelude, block,
+        )));
+        self
+    }
+
+    /// `.mixin(args);`
+    pub fn mixin_call(mut self, name: &str, args: impl IntoValue) -> Self {
+        self.content
+            .push(CurlyBracketsBlockContent::MixinCall(mixin_call(name, args)));
+        self
+    }
+
+    /// `/* text */`
+    pub fn comment(mut self, text: &str) -> Self {
+        self.content
+            .push(CurlyBracketsBlockContent::Comment(comment(text)));
+        self
+    }
+
+    pub fn build(self) -> CurlyBracketsBlock {
+        CurlyBracketsBlock {
+            span: Span::default(),
+            content: self.content,
+        }
+    }
+}
+
+/// a value given to the builder, strings are split with `value`
+pub trait IntoValue {
+    fn into_value(self) -> VariableValueList;
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> VariableValueList {
+        value(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> VariableValueList {
+        value(&self)
+    }
+}
+
+impl IntoValue for &String {
+    fn into_value(self) -> VariableValueList {
+        value(self)
+    }
+}
+
+impl IntoValue for VariableValueList {
+    fn into_value(self) -> VariableValueList {
+        self
+    }
+}
+
+impl IntoValue for VariableDefinedValue {
+    fn into_value(self) -> VariableValueList {
+        vec![self]
+    }
+}
+
+fn at_keyword(name: &str) -> AtKeyword {
+    AtKeyword {
+        span: Span::default(),
+        name: match name.starts_with('@') {
+            true => name.to_string(),
+            false => format!("@{}", name),
+        },
+    }
+}
+
+fn variable(name: &str, value: impl IntoValue) -> DefinedStatement {
+    DefinedStatement::VariableDefined(Box::new(VariableDefined {
+        span: Span::default(),
+        name: at_keyword(name),
+        value: value.into_value(),
+    }))
+}
+
+fn rule(selectors: &str, block: impl FnOnce(BlockBuilder) -> BlockBuilder) -> QualifiedRule {
+    QualifiedRule {
+        span: Span::default(),
+        prelude: selector_list(selectors),
+        block: Box::new(block(BlockBuilder::new()).build()),
+    }
+}
+
+fn at_rule(name: &str, prelude: impl IntoValue, block: Option<CurlyBracketsBlock>) -> AtRule {
+    AtRule {
+        span: Span::default(),
+        name: at_keyword(name),
+        prelude: prelude.into_value(),
+        block,
+    }
+}
+
+fn mixin_call(name: &str, args: impl IntoValue) -> MixinCall {
+    MixinCall {
+        span: Span::default(),
+        name: selector_list(name).into_iter().next().unwrap_or_default(),
+        params: Some(args.into_value()),
+    }
+}
+
+fn comment(text: &str) -> Comment {
+    Comment {
+        span: Span::default(),
+        value: format!("/* {} */", text),
+        is_line: false,
+    }
+}
+
+/**
+ * `text` split at the top level on `separators`, quotes, parentheses and brackets
+ * are kept together, a separator is returned as its own part
+ */
+fn split(text: &str, separators: &[char]) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    for ch in text.chars() {
+        match (quote, ch) {
+            (Some(open), _) if ch == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth = depth.saturating_sub(1),
+            (None, _) if depth == 0 && separators.contains(&ch) => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+                parts.push(ch.to_string());
+                continue;
+            }
+            _ => {}
+        }
+        part.push(ch);
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+/**
+ * `a > .b:hover, &-c` as the parser reads a selector list, the list is split on top level `,`
+ * and each selector, trimmed, into
+ * - a run of whitespace as one `" "`
+ * - `&` as a `ParentSelector`
+ * - `>`, `+` and `~` on their own
+ * - `:name` and `::name` as a `PseudoElement`, `:name(...)` as a `PseudoFunction` whose
+ *   arguments are read as a selector list again, so `:nth-child(2n+1)` splits at the `+`
+ *   where the parser keeps `+1`
+ * - `[...]` as one simple selector
+ * - any other char with the name chars (alphanumeric, `-`, `_`, non ascii) after it,
+ *   so `.a`, `#b`, `c` and `*` are simple selectors
+ */
+pub fn selector_list(text: &str) -> SelectorList {
+    split(text, &[','])
+        .iter()
+        .filter(|part| *part != ",")
+        .map(|part| selectors(part.trim()))
+        .filter(|selectors| !selectors.is_empty())
+        .collect()
+}
+
+fn selectors(text: &str) -> SelectorComponentList {
+    let simple = |name: &str| {
+        Selector::SimpleSelector(SimpleSelector {
+            span: Span::default(),
+            name: name.to_string(),
+        })
+    };
+    let mut result = Vec::new();
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let len = match ch {
+            _ if ch.is_whitespace() => {
+                let len = rest.len() - rest.trim_start().len();
+                result.push(simple(" "));
+                len
+            }
+            '&' => {
+                result.push(Selector::ParentSelector(Span::default()));
+                1
+            }
+            '>' | '+' | '~' => {
+                result.push(simple(&rest[..1]));
+                1
+            }
+            ':' => {
+                let colons = if rest.starts_with("::") { 2 } else { 1 };
+                let len = colons + name_len(&rest[colons..]);
+                if rest[len..].starts_with('(') {
+                    let close = len + group_len(&rest[len..]);
+                    let args = rest[len + 1..close].trim_end_matches(')');
+                    result.push(Selector::PseudoSelector(PseudoSelector::PseudoFunction(
+                        PseudoFunction {
+                            span: Span::default(),
+                            name: rest[..close].to_string(),
+                            params: selector_list(args),
+                        },
+                    )));
+                    close
+                } else {
+                    result.push(Selector::PseudoSelector(PseudoSelector::PseudoElement(
+                        PseudoElement {
+                            span: Span::default(),
+                            name: rest[..len].to_string(),
+                        },
+                    )));
+                    len
+                }
+            }
+            '[' => {
+                let len = group_len(rest);
+                result.push(simple(&rest[..len]));
+                len
+            }
+            _ => {
+                let prefix = ch.len_utf8();
+                let len = prefix + name_len(&rest[prefix..]);
+                result.push(simple(&rest[..len]));
+                len
+            }
+        };
+        rest = &rest[len..];
+    }
+    result
+}
+
+// length of the ident at the start of `text`
+fn name_len(text: &str) -> usize {
+    text.find(|ch: char| !(ch.is_alphanumeric() || ch == '-' || ch == '_' || !ch.is_ascii()))
+        .unwrap_or(text.len())
+}
+
+// length of the `(...)` or `[...]` at the start of `text`, to its end when it isn't closed
+fn group_len(text: &str) -> usize {
+    let mut depth = 0;
+    for (index, ch) in text.char_indices() {
+        match ch {
+            '(' | '[' => depth += 1,
+            ')' | ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/**
+ * `1px solid darken(@color, 10%), "a" !important` as the parser reads a value, the text is
+ * split on top level whitespace and `,`, quotes and brackets are kept together, and each word is
+ * - `,` a `Token`
+ * - `!name` an `Important`
+ * - `"..."` or `'...'` a `String`, with the quotes
+ * - `#...` a `Color` and `@name` a `Variable`
+ * - digits and `.` with an optional leading `+` or `-` and a unit of letters or `%`, a `Number`
+ * - `url(...)` a `Url`, unquoted, and `name(...)` a `FunctionExpression` whose arguments are
+ *   read as a value again
+ * - anything else an `Ident`, operators and parentheses included
+ */
+pub fn value(text: &str) -> VariableValueList {
+    split(text, &[' ', '\t', '\n', ','])
+        .iter()
+        .filter(|part| !part.trim().is_empty())
+        .map(|part| word(part))
+        .collect()
+}
+
+fn word(word: &str) -> VariableDefinedValue {
+    let express = |express: VariableExpression| {
+        VariableDefinedValue::Express(Express::VariableExpression(express))
+    };
+    let ident = |name: &str| Ident {
+        span: Span::default(),
+        name: name.to_string(),
+    };
+    if word == "," {
+        return VariableDefinedValue::PreservedToken(PreservedToken::Token(LexerToken {
+            span: Span::default(),
+            name: ",".to_string(),
+        }));
+    }
+    if let Some(important) = word.strip_prefix('!') {
+        return VariableDefinedValue::Important(ident(important.trim()));
+    }
+    if word.starts_with(['"', '\'']) {
+        return VariableDefinedValue::PreservedToken(PreservedToken::String(StringLiteral {
+            span: Span::default(),
+            value: word.to_string(),
+        }));
+    }
+    if word.starts_with('#') {
+        return express(VariableExpression::Color(Color {
+            span: Span::default(),
+            value: word.to_string(),
+        }));
+    }
+    if word.starts_with('@') {
+        return express(VariableExpression::Variable(at_keyword(word)));
+    }
+    if let Some(number) = number(word) {
+        return express(VariableExpression::PreservedToken(PreservedToken::Number(
+            number,
+        )));
+    }
+    if let Some((name, args)) = word
+        .strip_suffix(')')
+        .and_then(|word| word.split_once('('))
+        .filter(|(name, _)| !name.is_empty() && name_len(name) == name.len())
+    {
+        if name.eq_ignore_ascii_case("url") {
+            let quote = args
+                .trim()
+                .chars()
+                .next()
+                .filter(|ch| *ch == '"' || *ch == '\'');
+            let url = args.trim();
+            let url = match quote {
+                Some(quote) => url.trim_matches(quote),
+                None => url,
+            };
+            return VariableDefinedValue::PreservedToken(PreservedToken::Url(UrlLiteral {
+                span: Span::default(),
+                value: url.to_string(),
+                quote,
+            }));
+        }
+        return VariableDefinedValue::Express(Express::FunctionExpression(FunctionExpression {
+            span: Span::default(),
+            name: ident(name),
+            params: value(args),
+        }));
+    }
+    VariableDefinedValue::PreservedToken(PreservedToken::Ident(ident(word)))
+}
+
+// `-1.5px`, `10%`, `.5`
+fn number(word: &str) -> Option<NumberLiteral> {
+    let digits = word
+        .char_indices()
+        .find(|(index, ch)| {
+            !(ch.is_ascii_digit() || *ch == '.' || (*index == 0 && "+-".contains(*ch)))
+        })
+        .map_or(word.len(), |(index, _)| index);
+    let (number, unit) = word.split_at(digits);
+    if !unit.chars().all(|ch| ch.is_alphabetic() || ch == '%') {
+        return None;
+    }
+    Some(NumberLiteral {
+        span: Span::default(),
+        value: number.parse().ok()?,
+        unit: (!unit.is_empty()).then(|| unit.to_string()),
+    })
+}
+
+#[test]
+fn selector_lists() {
+    let simple = |name: &str| {
+        Selector::SimpleSelector(SimpleSelector {
+            span: Span::default(),
+            name: name.to_string(),
+        })
+    };
+    assert_eq!(
+        selector_list(".btn,  a > b.c:hover"),
+        vec![
+            vec![simple(".btn")],
+            vec![
+                simple("a"),
+                simple(" "),
+                simple(">"),
+                simple(" "),
+                simple("b"),
+                simple(".c"),
+                Selector::PseudoSelector(PseudoSelector::PseudoElement(PseudoElement {
+                    span: Span::default(),
+                    name: ":hover".to_string(),
+                })),
+            ],
+        ]
+    );
+    assert_eq!(
+        selector_list("&-x:not(.y, .z)"),
+        vec![vec![
+            Selector::ParentSelector(Span::default()),
+            simple("-x"),
+            Selector::PseudoSelector(PseudoSelector::PseudoFunction(PseudoFunction {
+                span: Span::default(),
+                name: ":not(.y, .z)".to_string(),
+                params: vec![vec![simple(".y")], vec![simple(".z")]],
+            })),
+        ]]
+    );
+}
+
+#[test]
+fn value_lists() {
+    let value = value("-1.5px solid darken(@c, 10%), url('a.png') \"b c\" !important");
+    let names: Vec<_> = value
+        .iter()
+        .map(|value| match value {
+            VariableDefinedValue::Express(Express::VariableExpression(
+                VariableExpression::PreservedToken(PreservedToken::Number(number)),
+            )) => format!(
+                "number {}{}",
+                number.value,
+                number.unit.as_deref().unwrap_or("")
+            ),
+            VariableDefinedValue::Express(Express::FunctionExpression(function)) => {
+                format!("function {} {}", function.name.name, function.params.len())
+            }
+            VariableDefinedValue::PreservedToken(PreservedToken::Ident(ident)) => {
+                format!("ident {}", ident.name)
+            }
+            VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) => {
+                format!("token {}", token.name)
+            }
+            VariableDefinedValue::PreservedToken(PreservedToken::Url(url)) => {
+                format!("url {} {:?}", url.value, url.quote)
+            }
+            VariableDefinedValue::PreservedToken(PreservedToken::String(string)) => {
+                format!("string {}", string.value)
+            }
+            VariableDefinedValue::Important(ident) => format!("important {}", ident.name),
+            value => format!("{:?}", value),
+        })
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "number -1.5px",
+            "ident solid",
+            "function darken 3",
+            "token ,",
+            "url a.png Some('\\'')",
+            "string \"b c\"",
+            "important important",
+        ]
+    );
+}
+
+#[test]
+fn builder() {
+    let sheet = StylesheetBuilder::new()
+        .variable("brand", "#0af")
+        .rule(".btn", |rule| {
+            rule.decl("color", "@brand")
+                .decl("margin", "0 auto")
+                .nest("&:hover", |rule| rule.decl("color", "red"))
+                .decl("padding", "0")
+        })
+        .build();
+    let [StyleContent::DefinedStatement(DefinedStatement::VariableDefined(variable)), StyleContent::QualifiedRule(rule)] =
+        sheet.content.as_slice()
+    else {
+        panic!("{:?}", sheet);
+    };
+    assert_eq!(variable.name.name, "@brand");
+    let lists: Vec<_> = rule
+        .block
+        .content
+        .iter()
+        .map(|content| match content {
+            CurlyBracketsBlockContent::DeclarationList(list) => list.len(),
+            _ => 0,
+        })
+        .collect();
+    assert_eq!(lists, [2, 0, 1]);
+}
+
+#[test]
+fn unsupported_values() {
+    let ident = |name: &str| {
+        VariableDefinedValue::PreservedToken(PreservedToken::Ident(Ident {
+            span: Span::default(),
+            name: name.to_string(),
+        }))
+    };
+    assert_eq!(value("(@a * 2)"), [ident("(@a * 2)")]);
+    assert_eq!(value("~\"x\""), [ident("~\"x\"")]);
+    assert_eq!(value("@a * 2")[1], ident("*"));
+}
@@ -1,3 +1,5 @@
 pub mod ast;
 pub mod builder;
+pub mod source;
+pub mod splice;
 pub mod visitor;
\ No newline at end of file
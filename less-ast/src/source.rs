@@ -0,0 +1,197 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast::Span;
+
+/// index of a file in `SourceFiles`, the entry file is always `FileId(0)`
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub struct FileId(pub u32);
+
+/**
+ * line is 1-based, columns are 0-based
+ * `column` counts utf-8 bytes, `utf16_column` counts utf-16 code units as source maps do
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub utf16_column: usize,
+}
+
+/// a source file and the byte offset of every line start
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub id: FileId,
+    pub name: String,
+    pub src: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(id: FileId, name: impl Into<String>, src: impl Into<String>) -> Self {
+        let src = src.into();
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(pos, _)| pos + 1))
+            .collect();
+        Self {
+            id,
+            name: name.into(),
+            src,
+            line_starts,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// the 0-based line index of a byte offset
+    fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        }
+    }
+
+    /// offsets past the end are clamped, offsets inside a char point at the char start
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.src.len());
+        while !self.src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.line_index(offset);
+        let prefix = &self.src[self.line_starts[line]..offset];
+        Position {
+            line: line + 1,
+            column: prefix.len(),
+            utf16_column: prefix.encode_utf16().count(),
+        }
+    }
+
+    /// the text of a 1-based line without the line break
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.src.len());
+        let text = &self.src[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+}
+
+/**
+ * every file taking part in a compilation, imports are added as they are inlined
+ * the files are shared between clones, the @imports pulling them in are not: entry points
+ * sharing a parsed file each keep their own import chain
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SourceFiles {
+    files: Vec<Arc<SourceFile>>,
+    // the @import that pulled a file in, the entry file has none
+    imported_from: BTreeMap<FileId, Span>,
+}
+
+impl SourceFiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, src: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+        self.files.push(Arc::new(SourceFile::new(id, name, src)));
+        id
+    }
+
+    pub fn add_import(
+        &mut self,
+        name: impl Into<String>,
+        src: impl Into<String>,
+        from: Span,
+    ) -> FileId {
+        let id = self.add(name, src);
+        self.set_imported_from(id, from);
+        id
+    }
+
+    /// `id` was pulled in by the @import at `from`, replacing any import recorded before
+    pub fn set_imported_from(&mut self, id: FileId, from: Span) {
+        self.imported_from.insert(id, from);
+    }
+
+    /// the files importing `id`, nearest first
+    pub fn import_chain(&self, id: FileId) -> Vec<&Span> {
+        let mut chain = Vec::new();
+        let mut current = id;
+        while let Some(from) = self.imported_from.get(&current) {
+            // a file can't import itself, stop instead of looping
+            if chain.len() > self.files.len() {
+                break;
+            }
+            chain.push(from);
+            current = from.file;
+        }
+        chain
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize).map(|file| &**file)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter().map(|file| &**file)
+    }
+}
+
+#[test]
+fn position() {
+    let file = SourceFile::new(FileId(0), "a.less", "a\n汽车: b;\r\n\n🚗c");
+    let at = |line, column, utf16_column| Position {
+        line,
+        column,
+        utf16_column,
+    };
+    assert_eq!(file.line_count(), 4);
+    assert_eq!(file.position(0), at(1, 0, 0));
+    assert_eq!(file.position(1), at(1, 1, 1));
+    assert_eq!(file.position(2), at(2, 0, 0));
+    assert_eq!(file.position(8), at(2, 6, 2));
+    // inside a multi byte char
+    assert_eq!(file.position(4), at(2, 0, 0));
+    assert_eq!(file.position(17), at(4, 0, 0));
+    assert_eq!(file.position(19), at(4, 4, 2));
+    assert_eq!(file.position(100), at(4, 5, 3));
+    assert_eq!(file.line(2), Some("汽车: b;"));
+    assert_eq!(file.line(3), Some(""));
+    assert_eq!(file.line(4), Some("🚗c"));
+    assert_eq!(file.line(5), None);
+    assert_eq!(file.line(0), None);
+}
+
+#[test]
+fn source_files() {
+    let mut files = SourceFiles::new();
+    let main = files.add("main.less", "@import 'a';");
+    let imported = files.add("a.less", ".a {}");
+    assert_eq!(main, FileId(0));
+    assert_eq!(imported, FileId(1));
+    assert_eq!(files.get(imported).unwrap().name, "a.less");
+    assert!(files.get(FileId(2)).is_none());
+
+    let nested = files.add_import("b.less", ".b {}", Span::new(3, 10).with_file(imported));
+    let chain = files.import_chain(nested);
+    assert_eq!(chain.len(), 1);
+    assert_eq!(chain[0].file, imported);
+    assert!(files.import_chain(main).is_empty());
+
+    // a clone shares the files, not the imports
+    let mut other = files.clone();
+    other.set_imported_from(nested, Span::new(0, 5).with_file(main));
+    assert_eq!(other.import_chain(nested)[0].file, main);
+    assert_eq!(files.import_chain(nested)[0].file, imported);
+}
@@ -0,0 +1,94 @@
+use crate::ast::{
+    AtKeyword, Ident, NumberLiteral, PreservedToken, Span, StringLiteral, UrlLiteral,
+};
+
+/**
+ * a rust value that `less_macro::quote_less!` splices in as a whole value, `color: #value;`
+ * numbers become unitless numbers and strings idents, build the token for anything else
+ */
+pub trait Splice {
+    fn splice(&self) -> PreservedToken;
+}
+
+impl<T: Splice + ?Sized> Splice for &T {
+    fn splice(&self) -> PreservedToken {
+        (**self).splice()
+    }
+}
+
+impl Splice for str {
+    fn splice(&self) -> PreservedToken {
+        PreservedToken::Ident(Ident {
+            span: Span::default(),
+            name: self.to_string(),
+        })
+    }
+}
+
+impl Splice for String {
+    fn splice(&self) -> PreservedToken {
+        self.as_str().splice()
+    }
+}
+
+macro_rules! splice_number {
+    ($($ty:ty),*) => {
+        $(impl Splice for $ty {
+            fn splice(&self) -> PreservedToken {
+                PreservedToken::Number(NumberLiteral {
+                    span: Span::default(),
+                    value: *self as f64,
+                    unit: None,
+                })
+            }
+        })*
+    };
+}
+
+splice_number!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl Splice for PreservedToken {
+    fn splice(&self) -> PreservedToken {
+        self.clone()
+    }
+}
+
+macro_rules! splice_token {
+    ($($ty:ident => $variant:ident),*) => {
+        $(impl Splice for $ty {
+            fn splice(&self) -> PreservedToken {
+                PreservedToken::$variant(self.clone())
+            }
+        })*
+    };
+}
+
+splice_token!(
+    Ident => Ident,
+    AtKeyword => AtKeyword,
+    StringLiteral => String,
+    NumberLiteral => Number,
+    UrlLiteral => Url
+);
+
+#[test]
+fn splice() {
+    assert_eq!(
+        "auto".splice(),
+        PreservedToken::Ident(Ident {
+            span: Span::default(),
+            name: "auto".to_string()
+        })
+    );
+    let number = NumberLiteral {
+        span: Span::default(),
+        value: 2.0,
+        unit: None,
+    };
+    assert_eq!(2.splice(), PreservedToken::Number(number.clone()));
+    let px = NumberLiteral {
+        unit: Some("px".to_string()),
+        ..number
+    };
+    assert_eq!(px.splice(), PreservedToken::Number(px));
+}
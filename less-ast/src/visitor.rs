@@ -1,172 +1,477 @@
-use core::prelude;
-use std::mem;
+/*!
+ * `Visit` walks the ast by reference, `VisitMut` by mutable reference and `Fold` by value
+ * so nodes can be replaced, lists are nodes too so `Fold` removes a node by leaving it
+ * out of its list
+ *
+ * every method defaults to walking the children of its node, call
+ * `node.visit_children_with(self)` (or `visit_mut_children_with`, `fold_children_with`)
+ * in an override to keep walking
+ *
+ * ```
+ * use less_ast::{ast::*, builder::StylesheetBuilder, visitor::*};
+ *
+ * // drops every comment in a block
+ * struct NoComments;
+ * impl Fold for NoComments {
+ *     fn fold_block_content_list(
+ *         &mut self,
+ *         list: Vec<CurlyBracketsBlockContent>,
+ *     ) -> Vec<CurlyBracketsBlockContent> {
+ *         list.into_iter()
+ *             .filter(|content| !matches!(content, CurlyBracketsBlockContent::Comment(_)))
+ *             .map(|content| content.fold_with(self))
+ *             .collect()
+ *     }
+ * }
+ *
+ * let sheet = StylesheetBuilder::new().rule(".a", |rule| rule.comment("x")).build();
+ * let sheet = sheet.fold_with(&mut NoComments);
+ * ```
+ *
+ * the traits come from the `nodes!` table below, structs are destructured and enums matched
+ * without a catch all, so a field or variant added to the ast and not to the table is a
+ * compile error instead of a node that is silently skipped
+ */
 
-use crate::ast::*;
+use crate::{ast::*, source::FileId};
 
-pub trait Visitor {
-    fn visit_stylesheets(&mut self, stylesheets: &mut Stylesheets) {
-        for content in &mut stylesheets.content {
-            self.visit_style_content(content);
-        }
-    }
-    fn visit_style_content(&mut self, content: &mut StyleContent) {
-        match content {
-            StyleContent::AtRule(at_rule) => todo!(),
-            StyleContent::QualifiedRule(qualified_rule) => {
-                self.visit_qualified_rule(qualified_rule)
-            }
-            StyleContent::DefinedStatement(defined_statement) => {
-                self.visit_defined_statement(defined_statement)
-            }
-            StyleContent::MixinCall(mixin_call) => todo!(),
+/// a node `V` can visit
+pub trait VisitWith<V: ?Sized + Visit> {
+    /// calls the method of `V` for this node
+    fn visit_with(&self, visitor: &mut V);
+    /// visits the children of this node
+    fn visit_children_with(&self, visitor: &mut V);
+}
+
+/// a node `V` can visit mutably
+pub trait VisitMutWith<V: ?Sized + VisitMut> {
+    fn visit_mut_with(&mut self, visitor: &mut V);
+    fn visit_mut_children_with(&mut self, visitor: &mut V);
+}
+
+/// a node `F` can fold
+pub trait FoldWith<F: ?Sized + Fold>: Sized {
+    fn fold_with(self, folder: &mut F) -> Self;
+    fn fold_children_with(self, folder: &mut F) -> Self;
+}
+
+macro_rules! nodes {
+    (
+        structs { $($struct:ident { $($field:ident),* } => $s_visit:ident, $s_visit_mut:ident, $s_fold:ident;)* }
+        enums { $($enum:ident { $($variant:ident),* } => $e_visit:ident, $e_visit_mut:ident, $e_fold:ident;)* }
+        lists { $($list:ty => $l_visit:ident, $l_visit_mut:ident, $l_fold:ident;)* }
+    ) => {
+        pub trait Visit {
+            $(fn $s_visit(&mut self, node: &$struct) {
+                node.visit_children_with(self)
+            })*
+            $(fn $e_visit(&mut self, node: &$enum) {
+                node.visit_children_with(self)
+            })*
+            $(fn $l_visit(&mut self, node: &$list) {
+                node.visit_children_with(self)
+            })*
         }
-    }
 
-    fn visit_qualified_rule(&mut self, qualified_rule: &mut QualifiedRule) {
-        for prelude in &mut qualified_rule.prelude {
-            self.visit_prelude(prelude);
+        pub trait VisitMut {
+            $(fn $s_visit_mut(&mut self, node: &mut $struct) {
+                node.visit_mut_children_with(self)
+            })*
+            $(fn $e_visit_mut(&mut self, node: &mut $enum) {
+                node.visit_mut_children_with(self)
+            })*
+            $(fn $l_visit_mut(&mut self, node: &mut $list) {
+                node.visit_mut_children_with(self)
+            })*
         }
-        let block = &mut *qualified_rule.block;
-        self.visit_block(block);
-    }
 
-    fn visit_block(&mut self, block: &mut CurlyBracketsBlock) {
-        for content in &mut block.content {
-            self.visit_curly_brackets_block_content(content);
+        pub trait Fold {
+            $(fn $s_fold(&mut self, node: $struct) -> $struct {
+                node.fold_children_with(self)
+            })*
+            $(fn $e_fold(&mut self, node: $enum) -> $enum {
+                node.fold_children_with(self)
+            })*
+            $(fn $l_fold(&mut self, node: $list) -> $list {
+                node.fold_children_with(self)
+            })*
         }
-    }
 
-    fn visit_curly_brackets_block_content(&mut self, content: &mut CurlyBracketsBlockContent) {
-        match content {
-            CurlyBracketsBlockContent::QualifiedRule(qualified_rule) => {
-                self.visit_qualified_rule(qualified_rule);
+        $(
+            impl<V: ?Sized + Visit> VisitWith<V> for $struct {
+                fn visit_with(&self, visitor: &mut V) {
+                    visitor.$s_visit(self)
+                }
+                fn visit_children_with(&self, visitor: &mut V) {
+                    let $struct { $($field),* } = self;
+                    $($field.visit_with(visitor);)*
+                }
+            }
+
+            impl<V: ?Sized + VisitMut> VisitMutWith<V> for $struct {
+                fn visit_mut_with(&mut self, visitor: &mut V) {
+                    visitor.$s_visit_mut(self)
+                }
+                fn visit_mut_children_with(&mut self, visitor: &mut V) {
+                    let $struct { $($field),* } = self;
+                    $($field.visit_mut_with(visitor);)*
+                }
             }
-            CurlyBracketsBlockContent::DeclarationList(declaration) => {
-                self.visit_declaration_list(declaration);
+
+            impl<F: ?Sized + Fold> FoldWith<F> for $struct {
+                fn fold_with(self, folder: &mut F) -> Self {
+                    folder.$s_fold(self)
+                }
+                fn fold_children_with(self, folder: &mut F) -> Self {
+                    let $struct { $($field),* } = self;
+                    $struct { $($field: $field.fold_with(folder)),* }
+                }
             }
-            CurlyBracketsBlockContent::AtRule(at_rule) => {
-                self.visit_at_rule(at_rule);
+        )*
+
+        $(
+            impl<V: ?Sized + Visit> VisitWith<V> for $enum {
+                fn visit_with(&self, visitor: &mut V) {
+                    visitor.$e_visit(self)
+                }
+                fn visit_children_with(&self, visitor: &mut V) {
+                    match self {
+                        $($enum::$variant(node) => node.visit_with(visitor),)*
+                    }
+                }
             }
-            CurlyBracketsBlockContent::DefinedStatement(defined_statement) => {
-                self.visit_defined_statement(defined_statement);
+
+            impl<V: ?Sized + VisitMut> VisitMutWith<V> for $enum {
+                fn visit_mut_with(&mut self, visitor: &mut V) {
+                    visitor.$e_visit_mut(self)
+                }
+                fn visit_mut_children_with(&mut self, visitor: &mut V) {
+                    match self {
+                        $($enum::$variant(node) => node.visit_mut_with(visitor),)*
+                    }
+                }
             }
-            CurlyBracketsBlockContent::MixinCall(mixin_call) => {
-                todo!();
+
+            impl<F: ?Sized + Fold> FoldWith<F> for $enum {
+                fn fold_with(self, folder: &mut F) -> Self {
+                    folder.$e_fold(self)
+                }
+                fn fold_children_with(self, folder: &mut F) -> Self {
+                    match self {
+                        $($enum::$variant(node) => $enum::$variant(node.fold_with(folder)),)*
+                    }
+                }
             }
-        }
+        )*
+
+        $(
+            impl<V: ?Sized + Visit> VisitWith<V> for $list {
+                fn visit_with(&self, visitor: &mut V) {
+                    visitor.$l_visit(self)
+                }
+                fn visit_children_with(&self, visitor: &mut V) {
+                    for node in self {
+                        node.visit_with(visitor);
+                    }
+                }
+            }
+
+            impl<V: ?Sized + VisitMut> VisitMutWith<V> for $list {
+                fn visit_mut_with(&mut self, visitor: &mut V) {
+                    visitor.$l_visit_mut(self)
+                }
+                fn visit_mut_children_with(&mut self, visitor: &mut V) {
+                    for node in self {
+                        node.visit_mut_with(visitor);
+                    }
+                }
+            }
+
+            impl<F: ?Sized + Fold> FoldWith<F> for $list {
+                fn fold_with(self, folder: &mut F) -> Self {
+                    folder.$l_fold(self)
+                }
+                fn fold_children_with(self, folder: &mut F) -> Self {
+                    self.into_iter().map(|node| node.fold_with(folder)).collect()
+                }
+            }
+        )*
+    };
+}
+
+nodes! {
+    structs {
+        Stylesheets { span, content } => visit_stylesheets, visit_mut_stylesheets, fold_stylesheets;
+        QualifiedRule { span, prelude, block } => visit_qualified_rule, visit_mut_qualified_rule, fold_qualified_rule;
+        AtRule { span, name, prelude, block } => visit_at_rule, visit_mut_at_rule, fold_at_rule;
+        MixinDefined { span, name, params, block } => visit_mixin_defined, visit_mut_mixin_defined, fold_mixin_defined;
+        Param { span, name, default_params } => visit_param, visit_mut_param, fold_param;
+        VariableDefined { span, name, value } => visit_variable_defined, visit_mut_variable_defined, fold_variable_defined;
+        MapVariableDefined { span, name, props } => visit_map_variable_defined, visit_mut_map_variable_defined, fold_map_variable_defined;
+        Declaration { span, name, value } => visit_declaration, visit_mut_declaration, fold_declaration;
+        CurlyBracketsBlock { span, content } => visit_block, visit_mut_block, fold_block;
+        MixinCall { span, name, params } => visit_mixin_call, visit_mut_mixin_call, fold_mixin_call;
+        FunctionDefinition { span, name, params, guarded, content } => visit_function_definition, visit_mut_function_definition, fold_function_definition;
+        MapVariable { span, property, object } => visit_map_variable, visit_mut_map_variable, fold_map_variable;
+        Color { span, value } => visit_color, visit_mut_color, fold_color;
+        FunctionExpression { span, name, params } => visit_function_expression, visit_mut_function_expression, fold_function_expression;
+        BinaryExpression { span, left, operator, right } => visit_binary_expression, visit_mut_binary_expression, fold_binary_expression;
+        ParenthesesExpression { span, express } => visit_parentheses_expression, visit_mut_parentheses_expression, fold_parentheses_expression;
+        SimpleSelector { span, name } => visit_simple_selector, visit_mut_simple_selector, fold_simple_selector;
+        PseudoFunction { span, name, params } => visit_pseudo_function, visit_mut_pseudo_function, fold_pseudo_function;
+        PseudoElement { span, name } => visit_pseudo_element, visit_mut_pseudo_element, fold_pseudo_element;
+        LexerToken { span, name } => visit_lexer_token, visit_mut_lexer_token, fold_lexer_token;
+        Comment { span, value, is_line } => visit_comment, visit_mut_comment, fold_comment;
+        Ident { span, name } => visit_ident, visit_mut_ident, fold_ident;
+        AtKeyword { span, name } => visit_at_keyword, visit_mut_at_keyword, fold_at_keyword;
+        NumberLiteral { span, value, unit } => visit_number_literal, visit_mut_number_literal, fold_number_literal;
+        StringLiteral { span, value } => visit_string_literal, visit_mut_string_literal, fold_string_literal;
+        UrlLiteral { span, value, quote } => visit_url_literal, visit_mut_url_literal, fold_url_literal;
+        Span { start, end, file } => visit_span, visit_mut_span, fold_span;
     }
-    fn visit_declaration_list(&mut self, declaration_list: &mut DeclarationList) {
-        for declaration in declaration_list {
-            self.visit_declaration(declaration);
-        }
+    enums {
+        StyleContent { QualifiedRule, AtRule, DefinedStatement, MixinCall, Comment } => visit_style_content, visit_mut_style_content, fold_style_content;
+        DefinedStatement { VariableDefined, MapVariableDefined, MixinDefined } => visit_defined_statement, visit_mut_defined_statement, fold_defined_statement;
+        VariableDefinedValue { Express, Ident, Important, PreservedToken, Comment } => visit_variable_defined_value, visit_mut_variable_defined_value, fold_variable_defined_value;
+        CurlyBracketsBlockContent { QualifiedRule, AtRule, DefinedStatement, DeclarationList, MixinCall, Comment } => visit_curly_brackets_block_content, visit_mut_curly_brackets_block_content, fold_curly_brackets_block_content;
+        ComponentValue { PreservedToken, SelectorList, Express } => visit_component_value, visit_mut_component_value, fold_component_value;
+        Express { BinaryExpression, FunctionExpression, MixinCall, VariableExpression, ParenthesesExpression, StringEscape } => visit_expression, visit_mut_expression, fold_expression;
+        VariableExpression { Variable, MapVariable, Color, PreservedToken } => visit_variable_expression, visit_mut_variable_expression, fold_variable_expression;
+        Selector { ParentSelector, SimpleSelector, PseudoSelector } => visit_selector, visit_mut_selector, fold_selector;
+        PseudoSelector { PseudoFunction, PseudoElement } => visit_pseudo_selector, visit_mut_pseudo_selector, fold_pseudo_selector;
+        PreservedToken { Ident, AtKeyword, String, Number, Url, Token } => visit_preserved_token, visit_mut_preserved_token, fold_preserved_token;
     }
-    fn visit_declaration(&mut self, declaration: &mut Declaration) {
-        for value in &mut declaration.value {
-            self.visit_variable_defined_value(value);
-        }
+    lists {
+        Vec<StyleContent> => visit_style_content_list, visit_mut_style_content_list, fold_style_content_list;
+        Vec<CurlyBracketsBlockContent> => visit_block_content_list, visit_mut_block_content_list, fold_block_content_list;
+        DeclarationList => visit_declaration_list, visit_mut_declaration_list, fold_declaration_list;
+        VariableValueList => visit_value_list, visit_mut_value_list, fold_value_list;
+        SelectorList => visit_selector_list, visit_mut_selector_list, fold_selector_list;
+        // the selectors of one item of a `SelectorList`, or the name of a mixin call
+        SelectorComponentList => visit_prelude, visit_mut_prelude, fold_prelude;
+        Vec<Param> => visit_param_list, visit_mut_param_list, fold_param_list;
+        Vec<Ident> => visit_ident_list, visit_mut_ident_list, fold_ident_list;
+        Vec<ComponentValue> => visit_component_value_list, visit_mut_component_value_list, fold_component_value_list;
     }
-    fn visit_variable_defined_value(&mut self, declaration_props: &mut VariableDefinedValue) {
-        match declaration_props {
-            VariableDefinedValue::Express(express) => {
-                self.visit_expression(express);
+}
+
+// values inside nodes that are not nodes themselves
+macro_rules! leaves {
+    ($($leaf:ty),*) => {
+        $(
+            impl<V: ?Sized + Visit> VisitWith<V> for $leaf {
+                fn visit_with(&self, _: &mut V) {}
+                fn visit_children_with(&self, _: &mut V) {}
             }
-            _ => {}
-        }
-    }
-    fn visit_expression(&mut self, express: &mut Express) {
-        match express {
-            Express::BinaryExpression(binary_expression) => {
-                self.visit_binary_expression(binary_expression);
+
+            impl<V: ?Sized + VisitMut> VisitMutWith<V> for $leaf {
+                fn visit_mut_with(&mut self, _: &mut V) {}
+                fn visit_mut_children_with(&mut self, _: &mut V) {}
             }
-            Express::FunctionExpression(function_expression) => {
-                self.visit_function_expression(function_expression);
+
+            impl<F: ?Sized + Fold> FoldWith<F> for $leaf {
+                fn fold_with(self, _: &mut F) -> Self {
+                    self
+                }
+                fn fold_children_with(self, _: &mut F) -> Self {
+                    self
+                }
             }
-            _ => {}
-        }
+        )*
+    };
+}
+
+leaves!(Atom, f64, bool, char, usize, FileId, BinaryOperator);
+
+impl<V: ?Sized + Visit, T: VisitWith<V>> VisitWith<V> for Box<T> {
+    fn visit_with(&self, visitor: &mut V) {
+        (**self).visit_with(visitor)
     }
-    fn visit_binary_expression(&mut self, binary_expression: &mut BinaryExpression) {
-        self.visit_expression(&mut *binary_expression.left);
-        self.visit_expression(&mut *binary_expression.right);
+    fn visit_children_with(&self, visitor: &mut V) {
+        (**self).visit_children_with(visitor)
+    }
+}
+
+impl<V: ?Sized + VisitMut, T: VisitMutWith<V>> VisitMutWith<V> for Box<T> {
+    fn visit_mut_with(&mut self, visitor: &mut V) {
+        (**self).visit_mut_with(visitor)
     }
-    fn visit_function_expression(&mut self, function_expression: &mut FunctionExpression) {
-        todo!();
+    fn visit_mut_children_with(&mut self, visitor: &mut V) {
+        (**self).visit_mut_children_with(visitor)
     }
+}
 
-    fn visit_at_rule(&mut self, at_rule: &mut AtRule) {
-        todo!();
+impl<F: ?Sized + Fold, T: FoldWith<F>> FoldWith<F> for Box<T> {
+    fn fold_with(self, folder: &mut F) -> Self {
+        Box::new((*self).fold_with(folder))
+    }
+    fn fold_children_with(self, folder: &mut F) -> Self {
+        Box::new((*self).fold_children_with(folder))
     }
+}
 
-    fn visit_prelude(&mut self, prelude: &mut SelectorComponentList) {
-        for component in prelude {
-            self.visit_selector(component);
+impl<V: ?Sized + Visit, T: VisitWith<V>> VisitWith<V> for Option<T> {
+    fn visit_with(&self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.visit_with(visitor);
         }
     }
-
-    fn visit_selector(&mut self, selector: &mut Selector) {
-        match selector {
-            Selector::ParentSelector => {}
-            Selector::SimpleSelector(simple_selector) => {
-                self.visit_simple_selector(simple_selector);
-            }
-            Selector::PseudoSelector(pseudo_selector) => {
-                self.visit_pseudo_selector(pseudo_selector);
-            }
+    fn visit_children_with(&self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.visit_children_with(visitor);
         }
     }
+}
 
-    fn visit_simple_selector(&mut self, simple_selector: &mut SimpleSelector) {}
-
-    fn visit_pseudo_selector(&mut self, pseudo_selector: &mut PseudoSelector) {
-        todo!();
+impl<V: ?Sized + VisitMut, T: VisitMutWith<V>> VisitMutWith<V> for Option<T> {
+    fn visit_mut_with(&mut self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.visit_mut_with(visitor);
+        }
+    }
+    fn visit_mut_children_with(&mut self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.visit_mut_children_with(visitor);
+        }
     }
-
-    fn visit_defined_statement(&mut self, defined_statement: &mut DefinedStatement) {}
 }
 
-impl AstVisitor {
-    fn get_simple_selector_name(&mut self, simple_selector: &mut SimpleSelector) -> String {
-        mem::take(&mut simple_selector.name)
+impl<F: ?Sized + Fold, T: FoldWith<F>> FoldWith<F> for Option<T> {
+    fn fold_with(self, folder: &mut F) -> Self {
+        self.map(|node| node.fold_with(folder))
+    }
+    fn fold_children_with(self, folder: &mut F) -> Self {
+        self.map(|node| node.fold_children_with(folder))
     }
 }
 
-struct AstVisitor;
-impl Visitor for AstVisitor {
+#[cfg(test)]
+fn stylesheets() -> Stylesheets {
+    use crate::builder::StylesheetBuilder;
 
-    fn visit_selector(&mut self, mut selector: &mut Selector) {
-        if let Selector::SimpleSelector(simple_selector) = selector {
-            let mut old_name = self.get_simple_selector_name(simple_selector);
-            old_name.insert_str(0, "hello");
-            simple_selector.name = "b".to_string();
-            dbg!(old_name, &simple_selector.name);
+    StylesheetBuilder::new()
+        .comment("top")
+        .variable("@size", "10px")
+        .rule("a, .b:not(.c)", |rule| {
+            rule.decl("width", "calc(@size)")
+                .comment("inside")
+                .mixin_call(".mixin", "1px")
+                .at_rule("@media", "print", |block| {
+                    block.rule("&:hover", |rule| rule.decl("color", "red"))
+                })
+        })
+        .build()
+}
+
+#[test]
+fn visit() {
+    #[derive(Default)]
+    struct Count {
+        selectors: usize,
+        declarations: usize,
+        at_keywords: Vec<String>,
+    }
+    impl Visit for Count {
+        fn visit_simple_selector(&mut self, _: &SimpleSelector) {
+            self.selectors += 1;
+        }
+        fn visit_declaration(&mut self, declaration: &Declaration) {
+            self.declarations += 1;
+            declaration.visit_children_with(self);
+        }
+        fn visit_at_keyword(&mut self, at_keyword: &AtKeyword) {
+            self.at_keywords.push(at_keyword.name.clone());
         }
     }
 
-  
+    let mut count = Count::default();
+    stylesheets().visit_with(&mut count);
+    // a, .b, .c inside :not(), .mixin
+    assert_eq!(count.selectors, 4);
+    assert_eq!(count.declarations, 2);
+    assert_eq!(count.at_keywords, ["@size", "@size", "@media"]);
 }
 
 #[test]
 fn quick_test() {
-    let mut visitor = AstVisitor;
-    let mut content = Vec::new();
-
-    let select_list = vec![vec![Selector::SimpleSelector(SimpleSelector {
-        span: Span::new(0, 1),
-        name: "a".to_string(),
-    })]];
-
-    let qualified_rule = QualifiedRule {
-        prelude: select_list,
-        span: Default::default(),
-        block: Box::new(CurlyBracketsBlock { content: vec![] }),
+    struct Rename;
+    impl VisitMut for Rename {
+        fn visit_mut_simple_selector(&mut self, simple_selector: &mut SimpleSelector) {
+            simple_selector.name.insert_str(0, "hello");
+            simple_selector.visit_mut_children_with(self);
+        }
+        fn visit_mut_span(&mut self, span: &mut Span) {
+            *span = Span::new(1, 2);
+        }
+    }
+
+    let mut stylesheets = stylesheets();
+    stylesheets.visit_mut_with(&mut Rename);
+    let StyleContent::QualifiedRule(rule) = &stylesheets.content[2] else {
+        panic!("{:?}", stylesheets);
     };
+    assert_eq!(
+        rule.prelude[0],
+        vec![Selector::SimpleSelector(SimpleSelector {
+            span: Span::new(1, 2),
+            name: "helloa".to_string(),
+        })]
+    );
+    assert_eq!(stylesheets.span, Span::new(1, 2));
+}
 
-    content.push(StyleContent::QualifiedRule(qualified_rule));
+#[test]
+fn fold() {
+    // drops comments and replaces `@size` by its value
+    struct Inline;
+    impl Fold for Inline {
+        fn fold_style_content_list(&mut self, list: Vec<StyleContent>) -> Vec<StyleContent> {
+            list.into_iter()
+                .filter(|content| !matches!(content, StyleContent::Comment(_)))
+                .map(|content| content.fold_with(self))
+                .collect()
+        }
+        fn fold_block_content_list(
+            &mut self,
+            list: Vec<CurlyBracketsBlockContent>,
+        ) -> Vec<CurlyBracketsBlockContent> {
+            list.into_iter()
+                .filter(|content| !matches!(content, CurlyBracketsBlockContent::Comment(_)))
+                .map(|content| content.fold_with(self))
+                .collect()
+        }
+        fn fold_variable_expression(&mut self, node: VariableExpression) -> VariableExpression {
+            match node {
+                VariableExpression::Variable(variable) if variable.name == "@size" => {
+                    VariableExpression::PreservedToken(PreservedToken::Number(NumberLiteral {
+                        span: variable.span,
+                        value: 10.0,
+                        unit: Some("px".to_string()),
+                    }))
+                }
+                node => node.fold_children_with(self),
+            }
+        }
+    }
 
-    let mut stylesheets = Stylesheets {
-        span: Default::default(),
-        content,
+    let folded = stylesheets().fold_with(&mut Inline);
+    assert_eq!(folded.content.len(), 2);
+    let StyleContent::QualifiedRule(rule) = &folded.content[1] else {
+        panic!("{:?}", folded);
     };
-    visitor.visit_stylesheets(&mut stylesheets);
-
-    dbg!(&stylesheets);
+    assert_eq!(rule.block.content.len(), 3);
+    let CurlyBracketsBlockContent::DeclarationList(declarations) = &rule.block.content[0] else {
+        panic!("{:?}", rule);
+    };
+    let VariableDefinedValue::Express(Express::FunctionExpression(calc)) =
+        &declarations[0].value[0]
+    else {
+        panic!("{:?}", declarations);
+    };
+    assert!(matches!(
+        &calc.params[0],
+        VariableDefinedValue::Express(Express::VariableExpression(
+            VariableExpression::PreservedToken(PreservedToken::Number(_))
+        ))
+    ));
 }
@@ -0,0 +1,148 @@
+use less_ast::builder::StylesheetBuilder;
+use less_parser::Parser;
+use serde_json::{json, Value};
+
+/**
+ * the json of a node without spans, and without the whitespace the parser keeps
+ * before `{`, after `,` and between some values, which the builder leaves out
+ */
+fn normalize(node: &impl serde::Serialize) -> Value {
+    fn strip(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.remove("span");
+                if let Some(span) = object.get_mut("ParentSelector") {
+                    *span = Value::Null;
+                }
+                object.values_mut().for_each(strip);
+            }
+            Value::Array(array) => {
+                array.iter_mut().for_each(strip);
+                let space = |value: &Value| {
+                    *value == json!({ "SimpleSelector": { "name": " " } })
+                        || *value == json!({ "PreservedToken": { "Token": { "name": " " } } })
+                };
+                // a selector keeps its inner whitespace, only its ends are trimmed
+                if array.iter().any(|item| {
+                    item.get("SimpleSelector").is_some() || item.get("ParentSelector").is_some()
+                }) {
+                    while array.last().is_some_and(space) {
+                        array.pop();
+                    }
+                    while array.first().is_some_and(space) {
+                        array.remove(0);
+                    }
+                } else {
+                    array.retain(|item| !space(item));
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(node).unwrap();
+    strip(&mut value);
+    value
+}
+
+#[test]
+fn selectors() {
+    let selectors = [
+        ".btn",
+        ".btn, .link",
+        "h1,h2",
+        "a b",
+        ".a  .b",
+        "a > b.c:hover",
+        "a ~ b + c",
+        "#id.cls",
+        "input[type=text]",
+        "&-x",
+        "&:hover",
+        ".a &",
+        "a:not(.y, .z)",
+        "a:nth-child(2n)",
+    ];
+    for selector in selectors {
+        let built = StylesheetBuilder::new()
+            .rule(selector, |rule| rule.decl("a", "b"))
+            .build();
+        let parsed = Parser::new(&format!("{} {{ a: b; }}", selector))
+            .parse()
+            .unwrap();
+        assert_eq!(normalize(&built), normalize(&parsed), "{}", selector);
+    }
+}
+
+#[test]
+fn values() {
+    let values = [
+        "b",
+        "-webkit-box",
+        "1px",
+        "10%",
+        ".5",
+        "+1",
+        "#fff",
+        "@brand",
+        "0 auto",
+        "1px  solid #000",
+        "a, b",
+        "a,b",
+        "\"b c\"",
+        "'x'",
+        "red !important",
+        "url(a.png)",
+        "url('a.png')",
+        "e(\"x\")",
+        "darken(@c, 10%)",
+        "1px solid rgba(0, 0, 0, 0.5)",
+        "f(g(1), 2)",
+    ];
+    for value in values {
+        let built = StylesheetBuilder::new()
+            .variable("@v", value)
+            .rule(".a", |rule| rule.decl("b", value))
+            .build();
+        let parsed = Parser::new(&format!("@v: {}; .a {{ b: {}; }}", value, value))
+            .parse()
+            .unwrap();
+        assert_eq!(normalize(&built), normalize(&parsed), "{}", value);
+    }
+}
+
+#[test]
+fn stylesheet() {
+    let built = StylesheetBuilder::new()
+        .at_statement("@import", "\"theme\"")
+        .comment("colors")
+        .variable("@brand", "#0af")
+        .rule(".btn, .link", |rule| {
+            rule.nest("&:hover", |rule| rule.decl("color", "darken(@brand, 10%)"))
+                .variable("@gap", "0 auto")
+                .decl("color", "@brand")
+                .mixin_call(".rounded", "4px")
+                .rule(".icon", |rule| rule.decl("margin", "@gap"))
+                .at_rule("@media", "print", |block| block.decl("display", "none"))
+        })
+        .at_rule("@media", "print", |block| {
+            block.rule(".btn", |rule| rule.decl("display", "none"))
+        })
+        .build();
+    let parsed = Parser::new(
+        "@import \"theme\";
+        /* colors */
+        @brand: #0af;
+        .btn, .link {
+            &:hover { color: darken(@brand, 10%); }
+            @gap: 0 auto;
+            color: @brand;
+            .rounded(4px);
+            .icon { margin: @gap; }
+            @media print { display: none; }
+        }
+        @media print { .btn { display: none; } }",
+    )
+    .parse()
+    .unwrap();
+    assert_eq!(normalize(&built), normalize(&parsed));
+}
@@ -0,0 +1,176 @@
+use std::fmt;
+
+/// every error and warning has a code that is never renumbered or reused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    // parse errors
+    UnexpectedToken,
+    UnexpectedEof,
+    UnexpectedChar,
+    InvalidColor,
+    InvalidNumber,
+    // evaluation errors
+    UndefinedVariable,
+    RecursiveVariable,
+    UndefinedMixin,
+    InvalidOperation,
+    FunctionArgument,
+    Unsupported,
+    // warnings
+    DeprecatedSyntax,
+    UnknownFunction,
+    DivisionOutsideParens,
+    MissingOptionalImport,
+    // added later, errors and warnings mixed
+    IncompatibleUnits,
+    ImportNotFound,
+    PluginNotFound,
+    PluginFailed,
+}
+
+pub const CODES: &[Code] = &[
+    Code::UnexpectedToken,
+    Code::UnexpectedEof,
+    Code::UnexpectedChar,
+    Code::InvalidColor,
+    Code::InvalidNumber,
+    Code::UndefinedVariable,
+    Code::RecursiveVariable,
+    Code::UndefinedMixin,
+    Code::InvalidOperation,
+    Code::FunctionArgument,
+    Code::Unsupported,
+    Code::DeprecatedSyntax,
+    Code::UnknownFunction,
+    Code::DivisionOutsideParens,
+    Code::MissingOptionalImport,
+    Code::IncompatibleUnits,
+    Code::ImportNotFound,
+    Code::PluginNotFound,
+    Code::PluginFailed,
+];
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::UnexpectedToken => "L0001",
+            Code::UnexpectedEof => "L0002",
+            Code::UnexpectedChar => "L0003",
+            Code::InvalidColor => "L0004",
+            Code::InvalidNumber => "L0005",
+            Code::UndefinedVariable => "L0006",
+            Code::RecursiveVariable => "L0007",
+            Code::UndefinedMixin => "L0008",
+            Code::InvalidOperation => "L0009",
+            Code::FunctionArgument => "L0010",
+            Code::Unsupported => "L0011",
+            Code::DeprecatedSyntax => "L0012",
+            Code::UnknownFunction => "L0013",
+            Code::DivisionOutsideParens => "L0014",
+            Code::MissingOptionalImport => "L0015",
+            Code::IncompatibleUnits => "L0016",
+            Code::ImportNotFound => "L0017",
+            Code::PluginNotFound => "L0018",
+            Code::PluginFailed => "L0019",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Code::UnexpectedToken => "unrecognised input",
+            Code::UnexpectedEof => "the input ended inside a comment, string or block",
+            Code::UnexpectedChar => "a character that can't start a token",
+            Code::InvalidColor => "a `#` not followed by 3 to 8 hex digits",
+            Code::InvalidNumber => "a number that can't be parsed",
+            Code::UndefinedVariable => "a variable used but not defined in scope",
+            Code::RecursiveVariable => "a variable that refers to itself",
+            Code::UndefinedMixin => "a mixin call without a matching definition",
+            Code::InvalidOperation => "an operation on values that can't be combined",
+            Code::FunctionArgument => {
+                "a builtin or registered function called with the wrong arguments"
+            }
+            Code::Unsupported => "an expression the compiler does not support yet",
+            Code::DeprecatedSyntax => "syntax that still works but will be removed",
+            Code::UnknownFunction => "a function that is neither builtin nor css, printed as is",
+            Code::DivisionOutsideParens => "a `/` outside parentheses evaluated as a division",
+            Code::MissingOptionalImport => "an `@import (optional)` of a file that doesn't exist",
+            Code::IncompatibleUnits => {
+                "an operation on numbers with different units, with strict units"
+            }
+            Code::ImportNotFound => "an `@import` of a file that can't be found or read",
+            Code::PluginNotFound => {
+                "an `@plugin` that is neither registered nor a `.wasm` file next to the stylesheet"
+            }
+            Code::PluginFailed => "a `.wasm` plugin that can't be read, instantiated or installed",
+        }
+    }
+
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            Code::DeprecatedSyntax
+                | Code::UnknownFunction
+                | Code::DivisionOutsideParens
+                | Code::MissingOptionalImport
+        )
+    }
+
+    pub fn parse(code: &str) -> Option<Code> {
+        CODES.iter().copied().find(|known| known.as_str() == code)
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<Code> for String {
+    fn from(code: Code) -> Self {
+        code.as_str().to_string()
+    }
+}
+
+/**
+ * codes silenced by comments of a file
+ * // less-disable L0013
+ * /* less-disable L0012, L0014 */
+ */
+pub fn disabled_codes(comment: &str) -> Vec<Code> {
+    let comment = comment
+        .trim_start_matches("//")
+        .trim_start_matches("/*")
+        .trim_end_matches("*/")
+        .trim();
+    let Some(codes) = comment.strip_prefix("less-disable") else {
+        return Vec::new();
+    };
+    codes
+        .split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter_map(Code::parse)
+        .collect()
+}
+
+#[test]
+fn codes_are_unique() {
+    for (index, code) in CODES.iter().enumerate() {
+        assert_eq!(code.as_str(), format!("L{:04}", index + 1));
+        assert_eq!(Code::parse(code.as_str()), Some(*code));
+    }
+    assert_eq!(Code::parse("L9999"), None);
+}
+
+#[test]
+fn disable_directives() {
+    assert_eq!(
+        disabled_codes("// less-disable L0013"),
+        vec![Code::UnknownFunction]
+    );
+    assert_eq!(
+        disabled_codes("/* less-disable L0012, L0014 */"),
+        vec![Code::DeprecatedSyntax, Code::DivisionOutsideParens]
+    );
+    assert!(disabled_codes("/* less-disable */").is_empty());
+    assert!(disabled_codes("/* a comment */").is_empty());
+}
@@ -0,0 +1,474 @@
+use std::fmt::Write;
+
+use less_ast::{
+    ast::Span,
+    source::{SourceFile, SourceFiles},
+};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// a span with a message, the primary label is underlined with `^`, others with `-`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+/// an error or warning with everything needed to print a code frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+            help: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    pub fn primary_span(&self) -> Option<&Span> {
+        self.labels
+            .iter()
+            .find(|label| label.primary)
+            .map(|label| &label.span)
+    }
+
+    pub fn to_json(&self, files: &SourceFiles) -> JsonDiagnostic {
+        let primary = self
+            .primary_span()
+            .and_then(|span| Some((files.get(span.file)?, span)));
+        JsonDiagnostic {
+            severity: self.severity,
+            code: self.code.clone(),
+            message: self.message.clone(),
+            file: primary.map(|(file, _)| file.name.clone()),
+            range: primary.map(|(file, span)| JsonRange {
+                start: JsonPosition::new(file, span.start),
+                end: JsonPosition::new(file, span.end),
+            }),
+            notes: self.notes.clone(),
+            help: self.help.clone(),
+        }
+    }
+}
+
+/**
+ * the json form of a diagnostic, the fields are only ever added to
+ *
+ * {"severity":"error","code":null,"message":"variable @c is undefined","file":"a.less",
+ *  "range":{"start":{"line":2,"column":6,"offset":10},"end":{"line":2,"column":8,"offset":12}},
+ *  "notes":[],"help":[]}
+ */
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonDiagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub file: Option<String>,
+    pub range: Option<JsonRange>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonRange {
+    pub start: JsonPosition,
+    pub end: JsonPosition,
+}
+
+/// line and column are 1-based, the column counts bytes as the text output does
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JsonPosition {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl JsonPosition {
+    fn new(file: &SourceFile, offset: usize) -> Self {
+        let position = file.position(offset);
+        Self {
+            line: position.line,
+            column: position.column + 1,
+            offset,
+        }
+    }
+}
+
+/// one json object per line, the format of `--error-format=json`
+pub fn to_json_lines(diagnostics: &[Diagnostic], files: &SourceFiles) -> String {
+    let mut result = String::new();
+    for diagnostic in diagnostics {
+        result.push_str(&serde_json::to_string(&diagnostic.to_json(files)).unwrap());
+        result.push('\n');
+    }
+    result
+}
+
+// the escape codes are only written by the ansi renderer
+struct Style {
+    error: &'static str,
+    warning: &'static str,
+    gutter: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+const PLAIN: Style = Style {
+    error: "",
+    warning: "",
+    gutter: "",
+    bold: "",
+    reset: "",
+};
+
+const ANSI: Style = Style {
+    error: "\x1b[1;31m",
+    warning: "\x1b[1;33m",
+    gutter: "\x1b[1;34m",
+    bold: "\x1b[1m",
+    reset: "\x1b[0m",
+};
+
+/**
+ * prints diagnostics the way rustc does
+ *
+ * error: variable @c is undefined
+ *  --> a.less:2:6
+ *   |
+ * 2 |   b: @c;
+ *   |      ^^ not defined in this scope
+ *   |
+ *   = note: in file imported from main.less:3
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Renderer {
+    ansi: bool,
+}
+
+impl Renderer {
+    pub fn new(ansi: bool) -> Self {
+        Self { ansi }
+    }
+
+    pub fn plain() -> Self {
+        Self::new(false)
+    }
+
+    pub fn ansi() -> Self {
+        Self::new(true)
+    }
+
+    fn style(&self) -> &'static Style {
+        if self.ansi {
+            &ANSI
+        } else {
+            &PLAIN
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, files: &SourceFiles) -> String {
+        let mut result = String::new();
+        self.write(&mut result, diagnostic, files).unwrap();
+        result
+    }
+
+    fn write(
+        &self,
+        f: &mut String,
+        diagnostic: &Diagnostic,
+        files: &SourceFiles,
+    ) -> std::fmt::Result {
+        let style = self.style();
+        let color = match diagnostic.severity {
+            Severity::Error => style.error,
+            Severity::Warning => style.warning,
+        };
+        let code = match &diagnostic.code {
+            Some(code) => format!("[{}]", code),
+            None => String::new(),
+        };
+        writeln!(
+            f,
+            "{}{}{}{}: {}{}{}",
+            color,
+            diagnostic.severity.as_str(),
+            code,
+            style.reset,
+            style.bold,
+            diagnostic.message,
+            style.reset
+        )?;
+
+        // labels grouped by file, the file of the primary label first
+        let mut labels: Vec<&Label> = diagnostic
+            .labels
+            .iter()
+            .filter(|label| files.get(label.span.file).is_some())
+            .collect();
+        labels.sort_by_key(|label| (!label.primary, label.span.start));
+        let mut groups: Vec<(&SourceFile, Vec<&Label>)> = Vec::new();
+        for label in labels {
+            let file = files.get(label.span.file).unwrap();
+            match groups.iter_mut().find(|(group, _)| group.id == file.id) {
+                Some((_, group)) => group.push(label),
+                None => groups.push((file, vec![label])),
+            }
+        }
+        let width = groups
+            .iter()
+            .flat_map(|(file, labels)| {
+                labels
+                    .iter()
+                    .map(|label| file.position(label.span.start).line)
+            })
+            .max()
+            .unwrap_or(0)
+            .to_string()
+            .len();
+        let gutter = |f: &mut String, line: Option<usize>| -> std::fmt::Result {
+            match line {
+                Some(line) => write!(f, "{}{:>width$} |{}", style.gutter, line, style.reset),
+                None => write!(f, "{}{:width$} |{}", style.gutter, "", style.reset),
+            }
+        };
+
+        for (index, (file, labels)) in groups.iter().enumerate() {
+            let first = file.position(labels[0].span.start);
+            let arrow = if index == 0 { "-->" } else { ":::" };
+            writeln!(
+                f,
+                "{}{:width$}{}{} {}:{}:{}",
+                style.gutter,
+                "",
+                arrow,
+                style.reset,
+                file.name,
+                first.line,
+                first.column + 1
+            )?;
+            gutter(f, None)?;
+            f.push('\n');
+            let mut lines: Vec<usize> = labels
+                .iter()
+                .map(|label| file.position(label.span.start).line)
+                .collect();
+            lines.sort();
+            lines.dedup();
+            for line in lines {
+                let text = file.line(line).unwrap_or("");
+                gutter(f, Some(line))?;
+                writeln!(f, " {}", text)?;
+                for label in labels
+                    .iter()
+                    .filter(|label| file.position(label.span.start).line == line)
+                {
+                    let position = file.position(label.span.start);
+                    let offset = text[..position.column.min(text.len())].chars().count();
+                    // spans over several lines are underlined to the end of the first
+                    let end = label.span.end.min(file.src.len());
+                    let length = file.src[label.span.start.min(end)..end]
+                        .lines()
+                        .next()
+                        .map_or(0, |text| text.chars().count())
+                        .max(1);
+                    let (mark, mark_color) = if label.primary {
+                        ('^', color)
+                    } else {
+                        ('-', style.gutter)
+                    };
+                    gutter(f, None)?;
+                    write!(
+                        f,
+                        " {:offset$}{}{}",
+                        "",
+                        mark_color,
+                        mark.to_string().repeat(length)
+                    )?;
+                    if !label.message.is_empty() {
+                        write!(f, " {}", label.message)?;
+                    }
+                    writeln!(f, "{}", style.reset)?;
+                }
+            }
+        }
+
+        let import_chain = diagnostic
+            .primary_span()
+            .map(|span| files.import_chain(span.file))
+            .unwrap_or_default();
+        let notes = diagnostic.notes.iter().map(|note| ("note", note.clone()));
+        let imports = import_chain.into_iter().filter_map(|span| {
+            let file = files.get(span.file)?;
+            let position = file.position(span.start);
+            let note = format!("in file imported from {}:{}", file.name, position.line);
+            Some(("note", note))
+        });
+        let help = diagnostic.help.iter().map(|help| ("help", help.clone()));
+        let footer: Vec<(&str, String)> = notes.chain(imports).chain(help).collect();
+        if !footer.is_empty() {
+            if !groups.is_empty() {
+                gutter(f, None)?;
+                f.push('\n');
+            }
+            for (kind, message) in footer {
+                writeln!(
+                    f,
+                    "{}{:width$} ={} {}{}:{} {}",
+                    style.gutter, "", style.reset, style.bold, kind, style.reset, message
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn render_plain() {
+    let mut files = SourceFiles::new();
+    let main = files.add("main.less", "@a: @a;\n@import 'b';\n");
+    let imported = files.add_import(
+        "b.less",
+        ".b {\n  c: @a;\n}\n",
+        Span::new(8, 19).with_file(main),
+    );
+    let diagnostic = Diagnostic::error("Recursive variable definition for @a")
+        .with_code("L0007")
+        .with_label(Label::primary(
+            Span::new(10, 12).with_file(imported),
+            "used here",
+        ))
+        .with_label(Label::secondary(
+            Span::new(0, 6).with_file(main),
+            "variable defined here",
+        ))
+        .with_help("a variable can't refer to itself");
+    assert_eq!(
+        Renderer::plain().render(&diagnostic, &files),
+        "\
+error[L0007]: Recursive variable definition for @a
+ --> b.less:2:6
+  |
+2 |   c: @a;
+  |      ^^ used here
+ ::: main.less:1:1
+  |
+1 | @a: @a;
+  | ------ variable defined here
+  |
+  = note: in file imported from main.less:2
+  = help: a variable can't refer to itself
+"
+    );
+}
+
+#[test]
+fn render_ansi() {
+    let mut files = SourceFiles::new();
+    files.add("a.less", "a: b;");
+    let diagnostic =
+        Diagnostic::warning("deprecated").with_label(Label::primary(Span::new(0, 1), ""));
+    let rendered = Renderer::ansi().render(&diagnostic, &files);
+    assert!(rendered.starts_with("\x1b[1;33mwarning\x1b[0m: \x1b[1mdeprecated"));
+    assert!(rendered.contains("\x1b[1;33m^\x1b[0m"));
+    // no labels, no code frame
+    let diagnostic = Diagnostic::error("boom").with_note("a note");
+    assert_eq!(
+        Renderer::plain().render(&diagnostic, &files),
+        "error: boom\n  = note: a note\n"
+    );
+}
+
+#[test]
+fn json_lines() {
+    let mut files = SourceFiles::new();
+    files.add("a.less", ".a {\n  b: @c;\n}\n");
+    let diagnostics = [
+        Diagnostic::error("variable @c is undefined")
+            .with_code("L0006")
+            .with_label(Label::primary(
+                Span::new(10, 12),
+                "not defined in this scope",
+            )),
+        Diagnostic::warning("no location").with_help("a help"),
+    ];
+    assert_eq!(
+        to_json_lines(&diagnostics, &files),
+        r#"{"severity":"error","code":"L0006","message":"variable @c is undefined","file":"a.less","range":{"start":{"line":2,"column":6,"offset":10},"end":{"line":2,"column":8,"offset":12}},"notes":[],"help":[]}
+{"severity":"warning","code":null,"message":"no location","file":null,"range":null,"notes":[],"help":["a help"]}
+"#
+    );
+}
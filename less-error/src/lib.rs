@@ -0,0 +1,148 @@
+pub mod codes;
+pub mod diagnostic;
+
+use std::fmt;
+
+use less_ast::{
+    ast::Span,
+    source::{SourceFile, SourceFiles},
+};
+
+/// the error types less.js reports, printed as `SyntaxError`, `NameError`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // valid syntax that cannot be evaluated: properties in the root, bad units
+    Syntax,
+    // unrecognised input
+    Parse,
+    // undefined variables and mixins
+    Name,
+    // a function called with the wrong arguments
+    Argument,
+    // an @import that cannot be read
+    Import,
+    // mixin matching and everything else going wrong while evaluating
+    Runtime,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorKind::Syntax => "SyntaxError",
+            ErrorKind::Parse => "ParseError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Argument => "ArgumentError",
+            ErrorKind::Import => "FileError",
+            ErrorKind::Runtime => "RuntimeError",
+        };
+        f.write_str(name)
+    }
+}
+
+/// where an error happened, line is 1-based and column 0-based as in less.js
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub filename: String,
+    pub line: usize,
+    pub column: usize,
+    // the line before, the line itself and the line after
+    pub extract: [Option<String>; 3],
+}
+
+impl Location {
+    pub fn new(file: &SourceFile, offset: usize) -> Self {
+        let position = file.position(offset);
+        let line = |line: usize| file.line(line).map(str::to_string);
+        Self {
+            filename: file.name.clone(),
+            line: position.line,
+            column: position.column,
+            extract: [
+                line(position.line - 1),
+                line(position.line),
+                line(position.line + 1),
+            ],
+        }
+    }
+}
+
+/// every error of a compilation, displayed the way less.js prints it:
+///
+/// ```text
+/// NameError: variable @a is undefined in a.less on line 2, column 6:
+/// 1 .a {
+/// 2   b: @a;
+/// 3 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LessError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl LessError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn with_location(mut self, file: &SourceFile, offset: usize) -> Self {
+        self.location = Some(Location::new(file, offset));
+        self
+    }
+
+    /// the location of the span start, unchanged when the file is unknown
+    pub fn with_span(self, files: &SourceFiles, span: &Span) -> Self {
+        match files.get(span.file) {
+            Some(file) => self.with_location(file, span.start),
+            None => self,
+        }
+    }
+}
+
+impl fmt::Display for LessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+        write!(
+            f,
+            " in {} on line {}, column {}:",
+            location.filename,
+            location.line,
+            location.column + 1
+        )?;
+        let lines = [location.line - 1, location.line, location.line + 1];
+        for (line, text) in lines.iter().zip(&location.extract) {
+            if let Some(text) = text {
+                write!(f, "\n{} {}", line, text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LessError {}
+
+#[test]
+fn display() {
+    let file = SourceFile::new(Default::default(), "a.less", ".a {\n  b: @a;\n}\n");
+    let error = LessError::new(ErrorKind::Name, "variable @a is undefined");
+    assert_eq!(error.to_string(), "NameError: variable @a is undefined");
+    assert_eq!(
+        error.with_location(&file, 10).to_string(),
+        "NameError: variable @a is undefined in a.less on line 2, column 6:\n1 .a {\n2   b: @a;\n3 }"
+    );
+
+    let file = SourceFile::new(Default::default(), "b.less", "@@a: 1;");
+    let error = LessError::new(ErrorKind::Parse, "Unrecognised input").with_location(&file, 0);
+    assert_eq!(
+        error.to_string(),
+        "ParseError: Unrecognised input in b.less on line 1, column 1:\n1 @@a: 1;"
+    );
+}
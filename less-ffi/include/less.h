@@ -0,0 +1,88 @@
+/*
+ * the C ABI of less-ffi, link against libless_ffi
+ *
+ * options and results are owned by the caller and freed with less_options_free and
+ * less_free_result, strings are utf-8 and nul terminated, strings returned by a result
+ * live as long as the result
+ *
+ *   LessOptions *options = less_options_new();
+ *   less_options_add_include_path(options, "styles");
+ *   LessResult *result = less_compile_file("styles/main.less", options);
+ *   if (less_result_ok(result)) puts(less_result_css(result));
+ *   else fputs(less_result_diagnostics_text(result), stderr);
+ *   less_free_result(result);
+ *   less_options_free(options);
+ */
+
+#ifndef LESS_H
+#define LESS_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* returned by the option setters */
+#define LESS_OK 0
+/* a NULL pointer or a string that is not utf-8 */
+#define LESS_INVALID_ARGUMENT 1
+/* e.g. an unknown math mode */
+#define LESS_INVALID_VALUE 2
+/* the compiler panicked, a compilation returns it as an error of the result instead */
+#define LESS_INTERNAL_ERROR 3
+
+typedef struct LessOptions LessOptions;
+typedef struct LessResult LessResult;
+
+const char *less_version(void);
+
+LessOptions *less_options_new(void);
+void less_options_free(LessOptions *options);
+int less_options_set_filename(LessOptions *options, const char *filename);
+int less_options_add_include_path(LessOptions *options, const char *path);
+int less_options_set_compress(LessOptions *options, bool compress);
+int less_options_set_strict_units(LessOptions *options, bool strict_units);
+int less_options_set_warnings_as_errors(LessOptions *options, bool warnings_as_errors);
+/* "always", "parens-division" or "parens" */
+int less_options_set_math(LessOptions *options, const char *math);
+/* "off", "local" or "all" */
+int less_options_set_rewrite_urls(LessOptions *options, const char *rewrite_urls);
+int less_options_set_rootpath(LessOptions *options, const char *rootpath);
+int less_options_set_url_args(LessOptions *options, const char *url_args);
+/* value is less source, e.g. "1px solid @color" */
+int less_options_add_global_var(LessOptions *options, const char *name, const char *value);
+int less_options_add_modify_var(LessOptions *options, const char *name, const char *value);
+/* url is NULL for the default annotation */
+int less_options_set_source_map(LessOptions *options, const char *url, bool inline_map, bool include_sources);
+
+/* options is NULL for the defaults, NULL is returned only when source or path is not utf-8 */
+LessResult *less_compile(const char *source, const LessOptions *options);
+LessResult *less_compile_file(const char *path, const LessOptions *options);
+void less_free_result(LessResult *result);
+
+bool less_result_ok(const LessResult *result);
+/* NULL when the compilation failed */
+const char *less_result_css(const LessResult *result);
+/* the json of the source map, NULL without one */
+const char *less_result_map(const LessResult *result);
+size_t less_result_import_count(const LessResult *result);
+const char *less_result_import(const LessResult *result, size_t index);
+
+/* the errors of a failed compilation, the warnings of a successful one */
+size_t less_result_diagnostic_count(const LessResult *result);
+bool less_result_diagnostic_is_error(const LessResult *result, size_t index);
+const char *less_result_diagnostic_message(const LessResult *result, size_t index);
+/* e.g. "L0006", NULL without a code */
+const char *less_result_diagnostic_code(const LessResult *result, size_t index);
+/* a json object, the format of lessc --error-format=json */
+const char *less_result_diagnostic_json(const LessResult *result, size_t index);
+/* every diagnostic as plain code frames, empty when there are none */
+const char *less_result_diagnostics_text(const LessResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
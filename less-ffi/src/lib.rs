@@ -0,0 +1,700 @@
+/*!
+ * a C ABI over `less_to_css::compile`, declared in `include/less.h`
+ *
+ * options and results are opaque pointers owned by the caller and freed with
+ * `less_options_free` and `less_free_result`, every string is utf-8 and nul terminated,
+ * strings returned by a result live as long as the result
+ */
+
+use std::{
+    any::Any,
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use less_error::diagnostic::{Diagnostic, Renderer, Severity};
+use less_to_css::{
+    compile, compile_file,
+    options::{CompileOptions, SourceMapOptions},
+    CompileOutput, Diagnostics,
+};
+
+pub const LESS_OK: c_int = 0;
+// a NULL pointer or a string that is not utf-8
+pub const LESS_INVALID_ARGUMENT: c_int = 1;
+// e.g. an unknown math mode
+pub const LESS_INVALID_VALUE: c_int = 2;
+// the compiler panicked, a compilation returns it as an error of the result instead
+pub const LESS_INTERNAL_ERROR: c_int = 3;
+
+/// `LessOptions` in C
+#[derive(Debug, Clone, Default)]
+pub struct LessOptions(CompileOptions);
+
+// a diagnostic with its strings ready for C
+struct LessDiagnostic {
+    is_error: bool,
+    message: CString,
+    code: Option<CString>,
+    json: CString,
+}
+
+/// `LessResult` in C, everything is converted once so that the accessors only borrow
+pub struct LessResult {
+    css: Option<CString>,
+    map: Option<CString>,
+    imports: Vec<CString>,
+    // the errors of a failed compilation or the warnings of a successful one
+    diagnostics: Vec<LessDiagnostic>,
+    // the diagnostics rendered as plain code frames
+    text: CString,
+}
+
+// strings with a nul inside are cut there, C can't see past it anyway
+fn c_string(string: impl Into<String>) -> CString {
+    let mut bytes = string.into().into_bytes();
+    if let Some(nul) = bytes.iter().position(|byte| *byte == 0) {
+        bytes.truncate(nul);
+    }
+    CString::new(bytes).unwrap()
+}
+
+impl LessResult {
+    fn new(result: Result<CompileOutput, Diagnostics>) -> Self {
+        let (output, diagnostics, files) = match result {
+            Ok(output) => {
+                let warnings = output.warnings.clone();
+                let files = output.files.clone();
+                (Some(output), warnings, files)
+            }
+            Err(error) => (None, error.diagnostics, error.files),
+        };
+        let renderer = Renderer::plain();
+        let text: String = diagnostics
+            .iter()
+            .map(|diagnostic| renderer.render(diagnostic, &files))
+            .collect();
+        let diagnostics = diagnostics
+            .iter()
+            .map(|diagnostic| LessDiagnostic {
+                is_error: diagnostic.severity == Severity::Error,
+                message: c_string(diagnostic.message.as_str()),
+                code: diagnostic.code.as_deref().map(c_string),
+                json: c_string(serde_json::to_string(&diagnostic.to_json(&files)).unwrap()),
+            })
+            .collect();
+        Self {
+            css: output.as_ref().map(|output| c_string(output.css.as_str())),
+            map: output
+                .as_ref()
+                .and_then(|output| output.map.as_ref())
+                .map(|map| c_string(map.to_json())),
+            imports: output
+                .iter()
+                .flat_map(|output| &output.imports)
+                .map(|path| c_string(path.display().to_string()))
+                .collect(),
+            diagnostics,
+            text: c_string(text),
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+    format!(
+        "the compiler panicked: {}",
+        message.unwrap_or("unknown error")
+    )
+}
+
+// a panic must not unwind into C, every export returns `fallback` instead
+fn guard<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+// like `guard`, the panic is the error of the result
+fn guard_result(f: impl FnOnce() -> *mut LessResult) -> *mut LessResult {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let result = LessResult::new(Err(Diagnostics {
+            diagnostics: vec![Diagnostic::error(panic_message(&*payload))],
+            imports: Vec::new(),
+            missing: Vec::new(),
+            files: Default::default(),
+        }));
+        Box::into_raw(Box::new(result))
+    })
+}
+
+/// # Safety
+/// `string` is NULL or a nul terminated string
+unsafe fn read_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok()
+}
+
+fn as_ptr(string: Option<&CString>) -> *const c_char {
+    string.map_or(ptr::null(), |string| string.as_ptr())
+}
+
+/// # Safety
+/// `options` comes from `less_options_new`, `value` is NULL or nul terminated
+unsafe fn set(
+    options: *mut LessOptions,
+    value: *const c_char,
+    apply: impl FnOnce(&mut CompileOptions, &str) -> Result<(), String>,
+) -> c_int {
+    let (Some(options), Some(value)) = (options.as_mut(), read_str(value)) else {
+        return LESS_INVALID_ARGUMENT;
+    };
+    match apply(&mut options.0, value) {
+        Ok(()) => LESS_OK,
+        Err(_) => LESS_INVALID_VALUE,
+    }
+}
+
+/// # Safety
+/// `options` comes from `less_options_new`
+unsafe fn set_flag(options: *mut LessOptions, apply: impl FnOnce(&mut CompileOptions)) -> c_int {
+    match options.as_mut() {
+        Some(options) => {
+            apply(&mut options.0);
+            LESS_OK
+        }
+        None => LESS_INVALID_ARGUMENT,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn less_version() -> *const c_char {
+    guard(ptr::null(), || {
+        concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn less_options_new() -> *mut LessOptions {
+    guard(ptr::null_mut(), || Box::into_raw(Box::default()))
+}
+
+/// # Safety
+/// `options` is NULL or comes from `less_options_new` and is not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn less_options_free(options: *mut LessOptions) {
+    guard((), || {
+        if !options.is_null() {
+            drop(Box::from_raw(options));
+        }
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_filename(
+    options: *mut LessOptions,
+    filename: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, filename, |options, filename| {
+            options.filename = Some(PathBuf::from(filename));
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_add_include_path(
+    options: *mut LessOptions,
+    path: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, path, |options, path| {
+            options.include_paths.push(PathBuf::from(path));
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_compress(
+    options: *mut LessOptions,
+    compress: bool,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set_flag(options, |options| options.options.compress = compress)
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_strict_units(
+    options: *mut LessOptions,
+    strict_units: bool,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set_flag(options, |options| {
+            options.options.strict_units = strict_units
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_warnings_as_errors(
+    options: *mut LessOptions,
+    warnings_as_errors: bool,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set_flag(options, |options| {
+            options.options.warnings_as_errors = warnings_as_errors
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_math(
+    options: *mut LessOptions,
+    math: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, math, |options, math| {
+            options.options.math = math.parse()?;
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_rewrite_urls(
+    options: *mut LessOptions,
+    rewrite_urls: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, rewrite_urls, |options, rewrite_urls| {
+            options.options.rewrite_urls = rewrite_urls.parse()?;
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_rootpath(
+    options: *mut LessOptions,
+    rootpath: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, rootpath, |options, rootpath| {
+            options.options.rootpath = Some(rootpath.to_string());
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_url_args(
+    options: *mut LessOptions,
+    url_args: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        set(options, url_args, |options, url_args| {
+            options.options.url_args = Some(url_args.to_string());
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`, `value` is the less source of the value
+#[no_mangle]
+pub unsafe extern "C" fn less_options_add_global_var(
+    options: *mut LessOptions,
+    name: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        let Some(value) = read_str(value) else {
+            return LESS_INVALID_ARGUMENT;
+        };
+        set(options, name, |options, name| {
+            options
+                .global_vars
+                .insert(name.to_string(), value.to_string());
+            Ok(())
+        })
+    })
+}
+
+/// # Safety
+/// see `set`, `value` is the less source of the value
+#[no_mangle]
+pub unsafe extern "C" fn less_options_add_modify_var(
+    options: *mut LessOptions,
+    name: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        let Some(value) = read_str(value) else {
+            return LESS_INVALID_ARGUMENT;
+        };
+        set(options, name, |options, name| {
+            options
+                .modify_vars
+                .insert(name.to_string(), value.to_string());
+            Ok(())
+        })
+    })
+}
+
+/**
+ * turns the source map on, `url` is NULL for the default annotation
+ * `inline_map` puts the map in the css, `include_sources` puts the less sources in the map
+ *
+ * # Safety
+ * see `set`
+ */
+#[no_mangle]
+pub unsafe extern "C" fn less_options_set_source_map(
+    options: *mut LessOptions,
+    url: *const c_char,
+    inline_map: bool,
+    include_sources: bool,
+) -> c_int {
+    guard(LESS_INTERNAL_ERROR, || {
+        let url = match (url.is_null(), read_str(url)) {
+            (true, _) => None,
+            (false, Some(url)) => Some(url.to_string()),
+            (false, None) => return LESS_INVALID_ARGUMENT,
+        };
+        set_flag(options, |options| {
+            options.options.source_map = Some(SourceMapOptions {
+                url,
+                inline: inline_map,
+                include_sources,
+                ..Default::default()
+            })
+        })
+    })
+}
+
+/// # Safety
+/// `source` is nul terminated, `options` is NULL for the defaults or comes from `less_options_new`
+#[no_mangle]
+pub unsafe extern "C" fn less_compile(
+    source: *const c_char,
+    options: *const LessOptions,
+) -> *mut LessResult {
+    guard_result(|| {
+        let Some(source) = read_str(source) else {
+            return ptr::null_mut();
+        };
+        let options = options.as_ref().map(|options| &options.0);
+        let result = compile(source, options.unwrap_or(&CompileOptions::default()));
+        Box::into_raw(Box::new(LessResult::new(result)))
+    })
+}
+
+/// # Safety
+/// like `less_compile`, `path` is read from the disk
+#[no_mangle]
+pub unsafe extern "C" fn less_compile_file(
+    path: *const c_char,
+    options: *const LessOptions,
+) -> *mut LessResult {
+    guard_result(|| {
+        let Some(path) = read_str(path) else {
+            return ptr::null_mut();
+        };
+        let options = options.as_ref().map(|options| &options.0);
+        let result = compile_file(
+            Path::new(path),
+            options.unwrap_or(&CompileOptions::default()),
+        );
+        Box::into_raw(Box::new(LessResult::new(result)))
+    })
+}
+
+/// # Safety
+/// `result` is NULL or comes from `less_compile` and is not used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn less_free_result(result: *mut LessResult) {
+    guard((), || {
+        if !result.is_null() {
+            drop(Box::from_raw(result));
+        }
+    })
+}
+
+/// # Safety
+/// `result` is NULL or comes from `less_compile`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_ok(result: *const LessResult) -> bool {
+    guard(false, || {
+        result.as_ref().is_some_and(|result| result.css.is_some())
+    })
+}
+
+/// NULL when the compilation failed
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_css(result: *const LessResult) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(result.as_ref().and_then(|result| result.css.as_ref()))
+    })
+}
+
+/// the json of the source map, NULL without one
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_map(result: *const LessResult) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(result.as_ref().and_then(|result| result.map.as_ref()))
+    })
+}
+
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_import_count(result: *const LessResult) -> usize {
+    guard(0, || {
+        result.as_ref().map_or(0, |result| result.imports.len())
+    })
+}
+
+/// NULL when `index` is out of range
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_import(
+    result: *const LessResult,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(result.as_ref().and_then(|result| result.imports.get(index)))
+    })
+}
+
+/// the errors of a failed compilation, the warnings of a successful one
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostic_count(result: *const LessResult) -> usize {
+    guard(0, || {
+        result.as_ref().map_or(0, |result| result.diagnostics.len())
+    })
+}
+
+unsafe fn diagnostic<'a>(result: *const LessResult, index: usize) -> Option<&'a LessDiagnostic> {
+    result.as_ref()?.diagnostics.get(index)
+}
+
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostic_is_error(
+    result: *const LessResult,
+    index: usize,
+) -> bool {
+    guard(false, || {
+        diagnostic(result, index).is_some_and(|diagnostic| diagnostic.is_error)
+    })
+}
+
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostic_message(
+    result: *const LessResult,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(diagnostic(result, index).map(|diagnostic| &diagnostic.message))
+    })
+}
+
+/// e.g. `L0006`, NULL without a code
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostic_code(
+    result: *const LessResult,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(diagnostic(result, index).and_then(|diagnostic| diagnostic.code.as_ref()))
+    })
+}
+
+/// the diagnostic as a json object, the format of `lessc --error-format=json`
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostic_json(
+    result: *const LessResult,
+    index: usize,
+) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(diagnostic(result, index).map(|diagnostic| &diagnostic.json))
+    })
+}
+
+/// every diagnostic as plain code frames, empty when there are none
+///
+/// # Safety
+/// see `less_result_ok`
+#[no_mangle]
+pub unsafe extern "C" fn less_result_diagnostics_text(result: *const LessResult) -> *const c_char {
+    guard(ptr::null(), || {
+        as_ptr(result.as_ref().map(|result| &result.text))
+    })
+}
+
+#[test]
+fn header() {
+    // every export is declared with the same prototype, and nothing else
+    let header = include_str!("../include/less.h");
+    let source = include_str!("lib.rs");
+    let c_type = |rust: &str| -> String {
+        let c_type = match rust.trim() {
+            "" => "void",
+            "c_int" => "int",
+            "bool" => "bool",
+            "usize" => "size_t",
+            "*const c_char" => "const char *",
+            "*mut LessOptions" => "LessOptions *",
+            "*const LessOptions" => "const LessOptions *",
+            "*mut LessResult" => "LessResult *",
+            "*const LessResult" => "const LessResult *",
+            other => panic!("no C type for {}", other),
+        };
+        c_type.to_string()
+    };
+    let declaration = |c_type: String, name: &str| match c_type.ends_with('*') {
+        true => format!("{}{}", c_type, name),
+        false => format!("{} {}", c_type, name),
+    };
+    let mut exported: Vec<String> = source
+        .split("extern \"C\" fn ")
+        .skip(1)
+        .filter_map(|rest| {
+            let signature = rest[..rest.find('{')?]
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let signature = signature.join(" ");
+            let (name, rest) = signature.split_once('(')?;
+            let (params, ret) = rest.rsplit_once(')')?;
+            let params: Vec<String> = params
+                .split(',')
+                .filter(|param| !param.trim().is_empty())
+                .map(|param| {
+                    let (name, rust) = param.split_once(':').unwrap();
+                    declaration(c_type(rust), name.trim())
+                })
+                .collect();
+            let params = match params.is_empty() {
+                true => "void".to_string(),
+                false => params.join(", "),
+            };
+            let ret = c_type(ret.trim().trim_start_matches("->"));
+            Some(format!("{}({});", declaration(ret, name), params))
+        })
+        .collect();
+    let mut declared: Vec<String> = header
+        .lines()
+        .filter(|line| line.ends_with(");") && !line.starts_with([' ', '#', '/']))
+        .map(String::from)
+        .collect();
+    exported.sort();
+    declared.sort();
+    assert_eq!(exported, declared);
+}
+
+#[test]
+fn panics() {
+    let text = |ptr: *const c_char| unsafe { CStr::from_ptr(ptr).to_str().unwrap() };
+    let result = guard_result(|| panic!("boom"));
+    unsafe {
+        assert!(!less_result_ok(result));
+        assert!(less_result_diagnostic_is_error(result, 0));
+        assert_eq!(
+            text(less_result_diagnostic_message(result, 0)),
+            "the compiler panicked: boom"
+        );
+        less_free_result(result);
+    }
+    assert_eq!(guard(LESS_OK, || panic!("{}", 1)), LESS_OK);
+}
+
+#[test]
+fn compile_c() {
+    let text = |ptr: *const c_char| unsafe { CStr::from_ptr(ptr).to_str().unwrap() };
+    unsafe {
+        let options = less_options_new();
+        assert_eq!(less_options_set_compress(options, true), LESS_OK);
+        assert_eq!(
+            less_options_add_global_var(options, c"color".as_ptr(), c"red".as_ptr()),
+            LESS_OK
+        );
+        assert_eq!(
+            less_options_set_math(options, c"sometimes".as_ptr()),
+            LESS_INVALID_VALUE
+        );
+        assert_eq!(
+            less_options_set_filename(ptr::null_mut(), c"a.less".as_ptr()),
+            LESS_INVALID_ARGUMENT
+        );
+
+        let result = less_compile(c".a { color: @color; b: foo(1); }".as_ptr(), options);
+        assert!(less_result_ok(result));
+        assert_eq!(text(less_result_css(result)), ".a{color:red;b:foo(1)}");
+        assert!(less_result_map(result).is_null());
+        assert_eq!(less_result_import_count(result), 0);
+        assert_eq!(less_result_diagnostic_count(result), 1);
+        assert!(!less_result_diagnostic_is_error(result, 0));
+        assert_eq!(text(less_result_diagnostic_code(result, 0)), "L0013");
+        less_free_result(result);
+
+        let result = less_compile(c".a { b: @c; }".as_ptr(), ptr::null());
+        assert!(!less_result_ok(result));
+        assert!(less_result_css(result).is_null());
+        assert!(less_result_diagnostic_is_error(result, 0));
+        assert_eq!(
+            text(less_result_diagnostic_message(result, 0)),
+            "variable @c is undefined"
+        );
+        assert!(text(less_result_diagnostic_json(result, 0)).contains("\"code\":\"L0006\""));
+        assert!(text(less_result_diagnostics_text(result)).starts_with("error[L0006]"));
+        assert!(less_result_diagnostic_message(result, 1).is_null());
+        less_free_result(result);
+        less_options_free(options);
+    }
+}
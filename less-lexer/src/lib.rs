@@ -57,6 +57,9 @@ pub struct Lexer<'source> {
     stack_charts: VecDeque<(CharIndices<'source>, VecDeque<Token>)>,
 
     mode: LexerMode,
+
+    // comments skipped by next/peek, sorted by start, drained by take_comments
+    comments: Vec<Token>,
 }
 
 impl<'source> Lexer<'source> {
@@ -67,10 +70,10 @@ impl<'source> Lexer<'source> {
             chars: source.char_indices(),
             stack_charts: VecDeque::with_capacity(20),
             mode: LexerMode::Normal,
+            comments: Vec::new(),
         }
     }
     pub fn set_mode(&mut self, mode: LexerMode) {
-        dbg!(&mode);
         self.mode = mode;
     }
     pub fn advance(&mut self) -> Option<(usize, char)> {
@@ -224,6 +227,15 @@ impl<'source> Lexer<'source> {
                 '@' => {
                     return self.parse_at_word(pos);
                 }
+                '\\' => {
+                    if Self::is_valid_escape(Some((pos, ch)), self.peek_char()) {
+                        return self.parse_ident_like_token(pos);
+                    }
+                    return Err(LexerError::UnexpectedChar(ch));
+                }
+                'u' | 'U' if self.is_at_unicode_range() => {
+                    return Ok(self.parse_unicode_range(pos));
+                }
                 '#' => {
                     return Ok(Token::new(Kind::Hash, pos, pos + 1));
                 }
@@ -261,7 +273,7 @@ impl<'source> Lexer<'source> {
                         _ => {}
                     }
                     if Self::is_validate_ident(ch, false) {
-                        return self.parse_ident_token(pos);
+                        return self.parse_ident_like_token(pos);
                     }
                     return Err(LexerError::UnexpectedChar(ch));
                 }
@@ -271,7 +283,8 @@ impl<'source> Lexer<'source> {
     }
 
     fn parse_color(&mut self, start: usize) -> Result<Token, LexerError> {
-        let mut end_pos = start + 1;
+        // the color may run to the end of the source, e.g. a value given to --global-var
+        let mut end_pos = self.source.len();
         let max_length = 8;
         let min_length = 3;
         let mut cur_length = 0;
@@ -300,14 +313,14 @@ impl<'source> Lexer<'source> {
             // single line comment
             if char == '/' {
                 let end_pos = self.eat_until_end_line();
-                return Ok(Token::new(Kind::Comment, start, end_pos));
+                return Ok(Token::new(Kind::LineComment, start, end_pos));
             }
             // multi line comment
             if char == '*' {
                 while let Some((_, ch)) = self.cur_char() {
                     if ch == '*' && matches!(self.peek_char(), Some((_, '/'))) {
                         let (end, _) = self.advance().unwrap();
-                        return Ok(Token::new(Kind::Comment, start, end));
+                        return Ok(Token::new(Kind::Comment, start, end + 1));
                     }
                 }
                 return Err(LexerError::UnexpectedEof);
@@ -321,31 +334,53 @@ impl<'source> Lexer<'source> {
         }
         false
     }
+    // css-syntax-3 4.2: any non ascii code point can be part of an ident
     fn is_validate_ident(ch: char, in_ident: bool) -> bool {
         match ch {
-            '_' => return true,
-            ch if ch.is_ascii_alphabetic() => return true,
-            '-' => return true,
-            '\u{00b7}' => return true,
-            '\u{00c0}'..='\u{00d6}' => return true,
-            '\u{00d8}'..='\u{00f6}' => return true,
-            '\u{00f8}'..='\u{03ff}' => return true,
-            '\u{037f}'..='\u{1fff}' => return true,
-            '\u{200c}' | '\u{200d}' | '\u{203f}' | '\u{2040}' => return true,
-            '\u{2070}'..='\u{218f}' => return true,
-            '\u{2c00}'..='\u{2fef}' => return true,
-            '\u{3001}'..='\u{d7ff}' => return true,
-            '\u{f900}'..='\u{fdcf}' => return true,
-            '\u{fdf0}'..='\u{fffd}' => return true,
-            '\u{10000}'.. => return true,
-            '0'..='9' if in_ident => return true,
-            _ => return false,
+            '_' | '-' => true,
+            ch if ch.is_ascii_alphabetic() => true,
+            ch if !ch.is_ascii() => true,
+            '0'..='9' => in_ident,
+            _ => false,
+        }
+    }
+
+    // a backslash followed by anything but a newline starts an escape: \31 0 \:hover \@
+    fn is_valid_escape(first: Option<(usize, char)>, second: Option<(usize, char)>) -> bool {
+        matches!(first, Some((_, '\\'))) && !matches!(second, None | Some((_, '\n')))
+    }
+
+    fn is_at_escape(&mut self) -> bool {
+        let first = self.peek_char();
+        let second = self.peek_nth_char(1);
+        Self::is_valid_escape(first, second)
+    }
+
+    // consume the escape after the backslash, the source spelling is kept in the token
+    fn eat_escape(&mut self) {
+        let Some((_, ch)) = self.advance() else {
+            return;
+        };
+        if !ch.is_ascii_hexdigit() {
+            return;
+        }
+        for _ in 1..6 {
+            match self.peek_char() {
+                Some((_, ch)) if ch.is_ascii_hexdigit() => {
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+        // a single whitespace after the hex digits belongs to the escape
+        if matches!(self.peek_char(), Some((_, ch)) if ch.is_whitespace()) {
+            self.advance();
         }
     }
 
     fn is_at_ident_token(&mut self) -> (bool, usize) {
         if let Some((pos, ch)) = self.peek_char() {
-            if Self::is_validate_ident(ch, false) {
+            if Self::is_validate_ident(ch, false) || self.is_at_escape() {
                 return (true, pos);
             }
         }
@@ -442,10 +477,17 @@ impl<'source> Lexer<'source> {
     }
 
     fn parse_ident_token(&mut self, start: usize) -> Result<Token, LexerError> {
+        if matches!(self.source[start..].chars().next(), Some('\\')) {
+            self.eat_escape();
+        }
         while let Some((end, ch)) = self.peek_char() {
             if Self::is_validate_ident(ch, true) {
                 self.advance();
                 continue;
+            } else if self.is_at_escape() {
+                self.advance();
+                self.eat_escape();
+                continue;
             } else {
                 return Ok(Token::new(Kind::Ident, start, end));
             }
@@ -453,6 +495,78 @@ impl<'source> Lexer<'source> {
         Ok(Token::new(Kind::Ident, start, self.source.len()))
     }
 
+    // url(a/b.png?x=1) url(data:image/png;base64,...)
+    // quoted urls and url(@var) are left to the parser as a function call
+    fn parse_ident_like_token(&mut self, start: usize) -> Result<Token, LexerError> {
+        let token = self.parse_ident_token(start)?;
+        if !self.source[token.start..token.end].eq_ignore_ascii_case("url")
+            || !matches!(self.peek_char(), Some((_, '(')))
+        {
+            return Ok(token);
+        }
+        let mut lookahead = self.chars.clone();
+        lookahead.next();
+        if let Some((_, ch)) = lookahead.find(|(_, ch)| !ch.is_whitespace()) {
+            if matches!(ch, '"' | '\'' | '@') {
+                return Ok(token);
+            }
+        }
+        self.advance();
+        while let Some((pos, ch)) = self.cur_char() {
+            match ch {
+                '\\' => {
+                    self.advance();
+                }
+                ')' => return Ok(Token::new(Kind::Url, start, pos + 1)),
+                _ => {}
+            }
+        }
+        Err(LexerError::UnexpectedEof)
+    }
+
+    // u+ followed by a hex digit or ?: U+0025-00FF u+4??
+    fn is_at_unicode_range(&mut self) -> bool {
+        matches!(self.peek_char(), Some((_, '+')))
+            && matches!(self.peek_nth_char(1), Some((_, ch)) if ch.is_ascii_hexdigit() || ch == '?')
+    }
+
+    fn parse_unicode_range(&mut self, start: usize) -> Token {
+        self.advance();
+        let mut length = 0;
+        let mut has_question_mark = false;
+        while let Some((_, ch)) = self.peek_char() {
+            if length == 6 {
+                break;
+            }
+            if ch == '?' {
+                has_question_mark = true;
+            } else if !ch.is_ascii_hexdigit() || has_question_mark {
+                break;
+            }
+            self.advance();
+            length += 1;
+        }
+        if !has_question_mark
+            && matches!(self.peek_char(), Some((_, '-')))
+            && matches!(self.peek_nth_char(1), Some((_, ch)) if ch.is_ascii_hexdigit())
+        {
+            self.advance();
+            for _ in 0..6 {
+                match self.peek_char() {
+                    Some((_, ch)) if ch.is_ascii_hexdigit() => {
+                        self.advance();
+                    }
+                    _ => break,
+                }
+            }
+        }
+        let end = self
+            .peek_char()
+            .map(|(pos, _)| pos)
+            .unwrap_or(self.source.len());
+        Token::new(Kind::UnicodeRange, start, end)
+    }
+
     fn parse_at_word(&mut self, start: usize) -> Result<Token, LexerError> {
         let (is_at_ident, ident_start) = self.is_at_ident_token();
         if is_at_ident {
@@ -468,7 +582,8 @@ impl<'source> Lexer<'source> {
         }
         loop {
             let token = self.get_token()?;
-            if token.kind == Kind::Comment {
+            if token.kind.is_comment() {
+                self.stash_comment(token);
                 continue;
             } else {
                 return Ok(token);
@@ -490,7 +605,8 @@ impl<'source> Lexer<'source> {
                 }
                 _ => {}
             }
-            if token.kind == Kind::Comment {
+            if token.kind.is_comment() {
+                self.stash_comment(token);
                 continue;
             } else {
                 self.token_stash.push_back(token);
@@ -521,7 +637,8 @@ impl<'source> Lexer<'source> {
                     }
                     _ => {}
                 }
-                if token.kind == Kind::Comment {
+                if token.kind.is_comment() {
+                    self.stash_comment(token);
                     continue;
                 } else {
                     self.token_stash.push_back(token);
@@ -533,6 +650,38 @@ impl<'source> Lexer<'source> {
         return Ok(self.token_stash.get(n).unwrap());
     }
 
+    // a restored lexer reads the same comment again
+    fn stash_comment(&mut self, token: Token) {
+        if let Err(index) = self
+            .comments
+            .binary_search_by_key(&token.start, |comment| comment.start)
+        {
+            self.comments.insert(index, token);
+        }
+    }
+
+    /**
+     * the comments in `from..to`, comments ending before `to` are dropped either way,
+     * they were inside a node that does not keep them
+     */
+    pub fn take_comments(&mut self, from: usize, to: usize) -> Vec<Token> {
+        let count = self
+            .comments
+            .iter()
+            .take_while(|comment| comment.end <= to)
+            .count();
+        self.comments
+            .drain(..count)
+            .filter(|comment| comment.start >= from)
+            .collect()
+    }
+
+    pub fn has_comments(&self, from: usize, to: usize) -> bool {
+        self.comments
+            .iter()
+            .any(|comment| comment.start >= from && comment.end <= to)
+    }
+
     pub fn expect(kind: Kind) {}
 
     fn debug_token(&self, token: &Token) {
@@ -548,12 +697,28 @@ fn comment() {
 //a
 /* a"#;
     let mut lex = Lexer::new(code);
-    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Comment, 1, 5)));
-    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Comment, 6, 18)));
-    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Comment, 20, 23)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::LineComment, 1, 5)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Comment, 6, 19)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::LineComment, 20, 23)));
     assert_eq!(lex.get_token(), Err(LexerError::UnexpectedEof))
 }
 
+#[test]
+fn take_comments() {
+    let code = "a /* b */ c // d\n/* e */ f";
+    let mut lex = Lexer::new(code);
+    while lex.next().map(|token| token.kind != Kind::EOF) == Ok(true) {}
+    assert_eq!(lex.take_comments(3, 10), vec![]);
+    assert_eq!(
+        lex.take_comments(11, 26),
+        vec![
+            Token::new(Kind::LineComment, 12, 16),
+            Token::new(Kind::Comment, 17, 24)
+        ]
+    );
+    assert_eq!(lex.take_comments(0, code.len()), vec![]);
+}
+
 #[test]
 fn number() {
     let code = r#"
@@ -673,6 +838,63 @@ fn string() {
     assert_eq!(lex.get_token(), Ok(Token::new(Kind::EOF, 47, 47)));
 }
 
+#[test]
+fn url() {
+    let code = r#"
+url(a/b.png?x=1)
+url(data:image/png;base64,iVBOR\)w0K)
+URL( "a.png" )
+url(@a)
+url(a
+"#;
+    let mut lex = Lexer::new(code);
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Url, 1, 17)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Url, 18, 55)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 56, 59)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::LeftParen, 59, 60)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Whitespace, 60, 61)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::String, 61, 68)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Whitespace, 68, 69)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::RightParen, 69, 70)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 71, 74)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::LeftParen, 74, 75)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::AtKeyword, 75, 77)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::RightParen, 77, 78)));
+    assert_eq!(lex.get_token(), Err(LexerError::UnexpectedEof));
+}
+
+#[test]
+fn escape() {
+    let code = r#"
+\31 0
+a\:hover
+\@b
+\62\6c\6f \63 e
+-\31 x
+\
+"#;
+    let mut lex = Lexer::new(code);
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 1, 6)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 7, 15)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 16, 19)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 20, 35)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Ident, 36, 42)));
+    assert_eq!(lex.get_token(), Err(LexerError::UnexpectedChar('\\')));
+}
+
+#[test]
+fn unicode_range() {
+    let code = r#"
+U+0025-00FF
+u+4??
+U+1F600
+"#;
+    let mut lex = Lexer::new(code);
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::UnicodeRange, 1, 12)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::UnicodeRange, 13, 18)));
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::UnicodeRange, 19, 26)));
+}
+
 #[test]
 fn peek_nth() {
     let code = r#"
@@ -706,3 +928,10 @@ fn quick_test() {
         dbg!(token);
     }
 }
+
+#[test]
+fn color_at_end() {
+    let mut lex = Lexer::new("111");
+    lex.set_mode(LexerMode::Color);
+    assert_eq!(lex.get_token(), Ok(Token::new(Kind::Color, 0, 3)));
+}
@@ -15,8 +15,13 @@ pub enum Kind {
     AtKeyword,
     String,
     Number,
+    // /* */
     Comment,
+    // //
+    LineComment,
     Color,
+    Url,
+    UnicodeRange,
 
     LeftParen,    // (
     RightParen,   // )
@@ -57,6 +62,12 @@ impl Token {
     }
 }
 
+impl Kind {
+    pub fn is_comment(&self) -> bool {
+        matches!(self, Kind::Comment | Kind::LineComment)
+    }
+}
+
 impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         return self.kind.fmt(f);
@@ -95,12 +106,15 @@ impl std::fmt::Display for Kind {
             Kind::RightBrace => write!(f, "RightBrace"),
             Kind::Whitespace => write!(f, "Whitespace"),
             Kind::Comment => write!(f, "Comment"),
+            Kind::LineComment => write!(f, "LineComment"),
             Kind::EOF => write!(f, "EOF"),
             Kind::Bang => write!(f, "Bang"),
             Kind::Hash => write!(f, "Hash"),
             Kind::Dot => write!(f, "Dot"),
             Kind::Percent => write!(f, "Percent"),
             Kind::Color => write!(f, "Color"),
+            Kind::Url => write!(f, "Url"),
+            Kind::UnicodeRange => write!(f, "UnicodeRange"),
         }
     }
 }
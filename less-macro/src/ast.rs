@@ -0,0 +1,413 @@
+/*!
+ * less_ast nodes written back as the rust expressions that build them
+ *
+ * every node is serialized into `Node`, serde's data model keeps the type and field
+ * names so the constructors don't have to be listed here and can't fall behind the ast
+ */
+
+use std::fmt;
+
+use proc_macro2::{Ident, Literal, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use serde::{ser, Serialize};
+
+pub const PLACEHOLDER: &str = "__less_splice_";
+
+pub enum Node {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    None,
+    Some(Box<Node>),
+    Unit,
+    Seq(Vec<Node>),
+    Struct(&'static str, Vec<(&'static str, Node)>),
+    // newtype structs too
+    TupleStruct(&'static str, Vec<Node>),
+    UnitVariant(&'static str, &'static str),
+    // newtype variants too
+    TupleVariant(&'static str, &'static str, Vec<Node>),
+    StructVariant(&'static str, &'static str, Vec<(&'static str, Node)>),
+}
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self(message.to_string())
+    }
+}
+
+pub fn to_node(value: &impl Serialize) -> Result<Node, Error> {
+    value.serialize(Serializer)
+}
+
+struct Serializer;
+
+pub struct Items(Vec<Node>);
+pub struct Fields(Vec<(&'static str, Node)>);
+pub struct Named<T>(&'static str, T);
+pub struct Variant<T>(&'static str, &'static str, T);
+
+impl ser::Serializer for Serializer {
+    type Ok = Node;
+    type Error = Error;
+    type SerializeSeq = Items;
+    type SerializeTuple = Items;
+    type SerializeTupleStruct = Named<Items>;
+    type SerializeTupleVariant = Variant<Items>;
+    type SerializeMap = ser::Impossible<Node, Error>;
+    type SerializeStruct = Named<Fields>;
+    type SerializeStructVariant = Variant<Fields>;
+
+    fn serialize_bool(self, v: bool) -> Result<Node, Error> {
+        Ok(Node::Bool(v))
+    }
+    fn serialize_i8(self, v: i8) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_i16(self, v: i16) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_i32(self, v: i32) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_i64(self, v: i64) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_u8(self, v: u8) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_u16(self, v: u16) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_u32(self, v: u32) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_u64(self, v: u64) -> Result<Node, Error> {
+        Ok(Node::Int(v.into()))
+    }
+    fn serialize_f32(self, v: f32) -> Result<Node, Error> {
+        Ok(Node::Float(v.into()))
+    }
+    fn serialize_f64(self, v: f64) -> Result<Node, Error> {
+        Ok(Node::Float(v))
+    }
+    fn serialize_char(self, v: char) -> Result<Node, Error> {
+        Ok(Node::Str(v.to_string()))
+    }
+    fn serialize_str(self, v: &str) -> Result<Node, Error> {
+        Ok(Node::Str(v.to_string()))
+    }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Node, Error> {
+        Err(ser::Error::custom("bytes are not part of the ast"))
+    }
+    fn serialize_none(self) -> Result<Node, Error> {
+        Ok(Node::None)
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node, Error> {
+        Ok(Node::Some(Box::new(value.serialize(self)?)))
+    }
+    fn serialize_unit(self) -> Result<Node, Error> {
+        Ok(Node::Unit)
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Node, Error> {
+        Ok(Node::Struct(name, Vec::new()))
+    }
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Node, Error> {
+        Ok(Node::UnitVariant(name, variant))
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        Ok(Node::TupleStruct(name, vec![value.serialize(self)?]))
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, Error> {
+        Ok(Node::TupleVariant(
+            name,
+            variant,
+            vec![value.serialize(self)?],
+        ))
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Items, Error> {
+        Ok(Items(Vec::with_capacity(len.unwrap_or_default())))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Items, Error> {
+        Ok(Items(Vec::with_capacity(len)))
+    }
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Named<Items>, Error> {
+        Ok(Named(name, Items(Vec::with_capacity(len))))
+    }
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Variant<Items>, Error> {
+        Ok(Variant(name, variant, Items(Vec::with_capacity(len))))
+    }
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(ser::Error::custom("maps are not part of the ast"))
+    }
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Named<Fields>, Error> {
+        Ok(Named(name, Fields(Vec::with_capacity(len))))
+    }
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Variant<Fields>, Error> {
+        Ok(Variant(name, variant, Fields(Vec::with_capacity(len))))
+    }
+}
+
+impl Items {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Items {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTuple for Items {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::Seq(self.0))
+    }
+}
+
+impl ser::SerializeTupleStruct for Named<Items> {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.1.push(value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::TupleStruct(self.0, self.1 .0))
+    }
+}
+
+impl ser::SerializeTupleVariant for Variant<Items> {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.2.push(value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::TupleVariant(self.0, self.1, self.2 .0))
+    }
+}
+
+impl Fields {
+    fn push<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.0.push((key, value.serialize(Serializer)?));
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Named<Fields> {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.1.push(key, value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::Struct(self.0, self.1 .0))
+    }
+}
+
+impl ser::SerializeStructVariant for Variant<Fields> {
+    type Ok = Node;
+    type Error = Error;
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.2.push(key, value)
+    }
+    fn end(self) -> Result<Node, Error> {
+        Ok(Node::StructVariant(self.0, self.1, self.2 .0))
+    }
+}
+
+// the path of an ast type
+fn path(name: &str) -> TokenStream {
+    let name = format_ident!("{}", name);
+    match name.to_string().as_str() {
+        "FileId" => quote!(::less_ast::source::FileId),
+        _ => quote!(::less_ast::ast::#name),
+    }
+}
+
+/**
+ * `text` with every placeholder replaced by the spliced value it stands for,
+ * None when there is none
+ */
+fn interpolate(text: &str, splices: &[Ident]) -> Option<TokenStream> {
+    let mut format = String::new();
+    let mut args = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER) {
+        let digits = &rest[start + PLACEHOLDER.len()..];
+        let len = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        let Some(splice) = digits[..len]
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| splices.get(i))
+        else {
+            break;
+        };
+        format.push_str(&rest[..start].replace('{', "{{").replace('}', "}}"));
+        format.push_str("{}");
+        args.push(splice);
+        rest = &digits[len..];
+    }
+    if args.is_empty() {
+        return None;
+    }
+    format.push_str(&rest.replace('{', "{{").replace('}', "}}"));
+    Some(quote!(::std::format!(#format, #(#args),*)))
+}
+
+// the splice a node that is nothing but a placeholder ident stands for
+fn whole_splice<'a>(node: &Node, splices: &'a [Ident]) -> Option<&'a Ident> {
+    let Node::Struct("Ident", fields) = node else {
+        return None;
+    };
+    let name = fields.iter().find_map(|(key, value)| match (key, value) {
+        (&"name", Node::Str(name)) => Some(name),
+        _ => None,
+    })?;
+    let index = name.strip_prefix(PLACEHOLDER)?.parse::<usize>().ok()?;
+    splices.get(index)
+}
+
+impl Node {
+    /**
+     * the expression building this node, spans become `Span::default()`
+     *
+     * struct fields and variant payloads go through `From::from` which is the identity
+     * for most of them and boxes the `Box<T>` ones
+     */
+    pub fn to_tokens(&self, splices: &[Ident]) -> TokenStream {
+        let convert = |node: &Node| match node {
+            Node::Struct(..)
+            | Node::TupleStruct(..)
+            | Node::UnitVariant(..)
+            | Node::TupleVariant(..)
+            | Node::StructVariant(..) => {
+                let tokens = node.to_tokens(splices);
+                quote!(::core::convert::From::from(#tokens))
+            }
+            _ => node.to_tokens(splices),
+        };
+        let fields = |fields: &[(&str, Node)]| {
+            let (keys, values): (Vec<_>, Vec<_>) = fields
+                .iter()
+                .map(|(key, value)| (format_ident!("{}", key), convert(value)))
+                .unzip();
+            quote!({ #(#keys: #values),* })
+        };
+        match self {
+            Node::Bool(value) => quote!(#value),
+            Node::Int(value) => Literal::i128_unsuffixed(*value).into_token_stream(),
+            Node::Float(value) => Literal::f64_suffixed(*value).into_token_stream(),
+            Node::Str(value) => interpolate(value, splices)
+                .unwrap_or_else(|| quote!(::std::string::String::from(#value))),
+            Node::None => quote!(::core::option::Option::None),
+            Node::Some(value) => {
+                let value = value.to_tokens(splices);
+                quote!(::core::option::Option::Some(#value))
+            }
+            Node::Unit => quote!(()),
+            Node::Seq(items) => {
+                let items = items.iter().map(|item| item.to_tokens(splices));
+                quote!(::std::vec![#(#items),*])
+            }
+            Node::Struct("Span", _) => quote!(::less_ast::ast::Span::default()),
+            Node::Struct(name, items) => {
+                let (path, fields) = (path(name), fields(items));
+                quote!(#path #fields)
+            }
+            Node::TupleStruct(name, items) => {
+                let (path, items) = (path(name), items.iter().map(convert));
+                quote!(#path(#(#items),*))
+            }
+            Node::UnitVariant(name, variant) => {
+                let (path, variant) = (path(name), format_ident!("{}", variant));
+                quote!(#path::#variant)
+            }
+            Node::TupleVariant(name, variant, items) => {
+                // `color: #value;`
+                let splice = match (*name, *variant, items.as_slice()) {
+                    ("VariableDefinedValue" | "PreservedToken", "Ident", [ident]) => {
+                        whole_splice(ident, splices)
+                    }
+                    _ => None,
+                };
+                if let Some(splice) = splice {
+                    let token = quote!(::less_ast::splice::Splice::splice(&#splice));
+                    return match *name {
+                        "PreservedToken" => token,
+                        _ => quote!(::less_ast::ast::VariableDefinedValue::PreservedToken(#token)),
+                    };
+                }
+                let (path, variant) = (path(name), format_ident!("{}", variant));
+                let items = items.iter().map(convert);
+                quote!(#path::#variant(#(#items),*))
+            }
+            Node::StructVariant(name, variant, items) => {
+                let (path, variant) = (path(name), format_ident!("{}", variant));
+                let fields = fields(items);
+                quote!(#path::#variant #fields)
+            }
+        }
+    }
+}
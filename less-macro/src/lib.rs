@@ -0,0 +1,303 @@
+/*!
+ * less compiled to css while rust compiles
+ *
+ * ```ignore
+ * static THEME: &str = include_less!("theme.less");
+ * static BUTTON: &str = less! {
+ *     @color: #0af;
+ *     .button { color: @color; .icon { width: (10px * 2); } }
+ * };
+ * ```
+ *
+ * both take `compress` as a last argument, `include_less!("theme.less", compress)`
+ * and `less!(compress; .a { b: c; })`, errors are compile errors
+ *
+ * `quote_less!` builds the `less_ast` nodes of the less instead, see its docs
+ */
+
+mod ast;
+
+use std::path::{Path, PathBuf};
+
+use less_error::diagnostic::Renderer;
+use less_parser::Parser;
+use less_to_css::{compile, compile_file, options::CompileOptions, CompileOutput, Diagnostics};
+use proc_macro::{Delimiter, Spacing, Span, TokenStream, TokenTree};
+use quote::{quote, quote_spanned};
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, LitStr, Token,
+};
+
+// "theme.less" or "theme.less", compress
+struct IncludeLess {
+    path: LitStr,
+    compress: bool,
+}
+
+fn parse_compress(input: ParseStream) -> syn::Result<bool> {
+    let flag: Ident = input.parse()?;
+    if flag != "compress" {
+        return Err(syn::Error::new(flag.span(), "expected `compress`"));
+    }
+    Ok(true)
+}
+
+impl Parse for IncludeLess {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut compress = false;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            compress = parse_compress(input)?;
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self { path, compress })
+    }
+}
+
+// the file the macro is called from, None when the compiler does not say
+fn calling_file() -> Option<PathBuf> {
+    Span::call_site().local_file()
+}
+
+fn options(filename: Option<PathBuf>, compress: bool) -> CompileOptions {
+    let mut options = CompileOptions {
+        filename,
+        ..Default::default()
+    };
+    options.options.compress = compress;
+    options
+}
+
+/**
+ * the css as a string literal, every file read is also passed to `include_bytes!`
+ * so that cargo rebuilds when one of them changes
+ */
+fn expand(output: CompileOutput, read: impl IntoIterator<Item = PathBuf>) -> TokenStream {
+    let css = output.css;
+    let read = read
+        .into_iter()
+        .chain(output.imports)
+        .filter_map(|path| path.canonicalize().ok())
+        .map(|path| path.display().to_string());
+    quote! {
+        {
+            #(const _: &[u8] = include_bytes!(#read);)*
+            #css
+        }
+    }
+    .into()
+}
+
+fn error(span: proc_macro2::Span, message: &str) -> TokenStream {
+    quote_spanned!(span=> compile_error!(#message)).into()
+}
+
+fn render(diagnostics: &Diagnostics) -> String {
+    let renderer = Renderer::plain();
+    diagnostics
+        .diagnostics
+        .iter()
+        .map(|diagnostic| renderer.render(diagnostic, &diagnostics.files))
+        .collect()
+}
+
+/// compiles a less file into a `&'static str` of css, the path is relative to the calling file
+#[proc_macro]
+pub fn include_less(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as IncludeLess);
+    let relative = input.path.value();
+    let directory = calling_file()
+        .and_then(|file| file.parent().map(Path::to_path_buf))
+        .or_else(|| std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from))
+        .unwrap_or_default();
+    let path = directory.join(&relative);
+    if !path.is_file() {
+        let message = format!("couldn't read `{}`: file not found", path.display());
+        return error(input.path.span(), &message);
+    }
+    match compile_file(&path, &options(None, input.compress)) {
+        Ok(output) => expand(output, [path]),
+        Err(diagnostics) => error(input.path.span(), &render(&diagnostics)),
+    }
+}
+
+// the less source of the tokens, spacing kept from the token positions
+#[derive(Default)]
+struct Source {
+    text: String,
+    // where each token starts in `text`
+    tokens: Vec<(usize, Span)>,
+    // line and column after the last token
+    end: Option<(usize, usize)>,
+    // the `#var` idents in order, None when `#` is plain less
+    splices: Option<Vec<proc_macro::Ident>>,
+}
+
+impl Source {
+    fn push(&mut self, text: &str, span: Span) {
+        self.push_between(text, span, span);
+    }
+
+    // text standing for the tokens from `first` to `last`
+    fn push_between(&mut self, text: &str, first: Span, last: Span) {
+        let (start, end) = (first.start(), last.end());
+        if let Some((line, column)) = self.end {
+            if start.line() > line {
+                self.text.push('\n');
+            } else if start.column() > column {
+                self.text.push(' ');
+            }
+        }
+        self.tokens.push((self.text.len(), first));
+        self.text.push_str(text);
+        self.end = Some((end.line(), end.column()));
+    }
+
+    fn extend(&mut self, stream: TokenStream) {
+        let mut tokens = stream.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push(open, group.span_open());
+                    self.extend(group.stream());
+                    self.push(close, group.span_close());
+                }
+                TokenTree::Punct(hash) if hash.as_char() == '#' && self.splices.is_some() => {
+                    match tokens.peek() {
+                        // `##fff` is the less `#fff`
+                        Some(TokenTree::Punct(next))
+                            if next.as_char() == '#' && hash.spacing() == Spacing::Joint =>
+                        {
+                            self.push_between("#", hash.span(), next.span());
+                            tokens.next();
+                        }
+                        Some(TokenTree::Ident(ident)) => {
+                            let splices = self.splices.as_mut().unwrap();
+                            let placeholder = format!("{}{}", ast::PLACEHOLDER, splices.len());
+                            splices.push(ident.clone());
+                            let span = ident.span();
+                            self.push_between(&placeholder, hash.span(), span);
+                            tokens.next();
+                        }
+                        _ => self.push("#", hash.span()),
+                    }
+                }
+                token => self.push(&token.to_string(), token.span()),
+            }
+        }
+    }
+
+    // the token an offset of `text` falls in
+    fn span_at(&self, offset: usize) -> Option<Span> {
+        let index = self.tokens.partition_point(|(start, _)| *start <= offset);
+        index.checked_sub(1).map(|index| self.tokens[index].1)
+    }
+}
+
+/// compiles the less written inside it into a `&'static str` of css, errors point at the tokens
+#[proc_macro]
+pub fn less(input: TokenStream) -> TokenStream {
+    let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+    // less!(compress; ...)
+    let compress = matches!(
+        tokens.as_slice(),
+        [TokenTree::Ident(ident), TokenTree::Punct(punct), ..]
+            if ident.to_string() == "compress" && punct.as_char() == ';'
+    );
+    if compress {
+        tokens.drain(..2);
+    }
+    let mut source = Source::default();
+    source.extend(tokens.into_iter().collect());
+
+    match compile(&source.text, &options(calling_file(), compress)) {
+        Ok(output) => expand(output, []),
+        Err(diagnostics) => {
+            // the first file is the macro input, its errors point at the tokens instead of a code frame
+            let entry = diagnostics.files.iter().next().map(|file| file.id);
+            let first = diagnostics.diagnostics.first();
+            let span = first
+                .and_then(|diagnostic| diagnostic.primary_span())
+                .filter(|span| Some(span.file) == entry)
+                .and_then(|span| source.span_at(span.start));
+            match (first, span) {
+                (Some(diagnostic), Some(span)) => {
+                    let mut message = diagnostic.message.clone();
+                    for help in &diagnostic.help {
+                        message.push_str(&format!("\nhelp: {}", help));
+                    }
+                    error(span.into(), &message)
+                }
+                _ => error(Span::call_site().into(), &render(&diagnostics)),
+            }
+        }
+    }
+}
+
+/**
+ * the `less_ast` nodes of the less written inside it, built at runtime from constructor
+ * expressions with `Span::default()` spans, the crate using it depends on `less_ast`
+ *
+ * ```ignore
+ * let name = "primary";
+ * let color = "red";
+ * let sheet: Stylesheets = quote_less! {
+ *     .btn-#name { #property: #color; border: 1px solid ##fff; }
+ * };
+ * let value: VariableValueList = quote_less!(value; 1px solid #color);
+ * ```
+ *
+ * `#var` splices a rust variable, a whole value goes through `less_ast::splice::Splice`,
+ * anywhere else in a selector, name or value it is formatted with `Display`,
+ * `##` is a literal `#` so ids and colors starting with a letter are written `##header`
+ */
+#[proc_macro]
+pub fn quote_less(input: TokenStream) -> TokenStream {
+    let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+    // quote_less!(value; ...)
+    let value = matches!(
+        tokens.as_slice(),
+        [TokenTree::Ident(ident), TokenTree::Punct(punct), ..]
+            if ident.to_string() == "value" && punct.as_char() == ';'
+    );
+    if value {
+        tokens.drain(..2);
+    }
+    let mut source = Source {
+        splices: Some(Vec::new()),
+        ..Default::default()
+    };
+    source.extend(tokens.into_iter().collect());
+
+    let mut parser = Parser::new(&source.text);
+    let node = if value {
+        parser.parse_value().map(|value| ast::to_node(&value))
+    } else {
+        parser.parse().map(|stylesheets| ast::to_node(&stylesheets))
+    };
+    let splices: Vec<proc_macro2::Ident> = source
+        .splices
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|ident| proc_macro2::Ident::new(&ident.to_string(), ident.span().into()))
+        .collect();
+    match node {
+        Ok(Ok(node)) => node.to_tokens(&splices).into(),
+        Ok(Err(err)) => error(Span::call_site().into(), &err.to_string()),
+        Err(err) => {
+            let span = err
+                .offset()
+                .and_then(|offset| source.span_at(offset))
+                .unwrap_or_else(Span::call_site);
+            error(span.into(), &err.to_string())
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use less_ast::ast::{
+    NumberLiteral, PreservedToken, Stylesheets, VariableDefinedValue, VariableValueList,
+};
+use less_macro::{include_less, less, quote_less};
+use less_parser::Parser;
+use serde_json::Value;
+
+#[test]
+fn include() {
+    const THEME: &str = include_less!("styles/theme.less");
+    assert_eq!(
+        THEME,
+        ".button {\n  color: #0af;\n}\n.button .icon {\n  width: 20px;\n}\n"
+    );
+    assert_eq!(
+        include_less!("styles/theme.less", compress),
+        ".button{color:#0af}.button .icon{width:20px}"
+    );
+}
+
+#[test]
+fn inline() {
+    let css = less! {
+        @import "styles/_vars";
+        @size: 10px;
+        #header .nav > li:first-child {
+            color: @color;
+            border: 1px solid #1e90ff;
+            margin: -5px 0 (@size * 1.5);
+            -webkit-box-sizing: border-box;
+        }
+    };
+    assert_eq!(
+        css,
+        "#header .nav > li:first-child {\n  color: #0af;\n  border: 1px solid #1e90ff;\n  margin: -5px 0 15px;\n  -webkit-box-sizing: border-box;\n}\n"
+    );
+    assert_eq!(less!(compress; .a { b: c; }), ".a{b:c}");
+}
+
+// the json of a node with every span left out
+fn without_spans(node: &impl serde::Serialize) -> Value {
+    fn strip(value: &mut Value) {
+        match value {
+            Value::Object(object) => {
+                object.remove("span");
+                object.values_mut().for_each(strip);
+            }
+            Value::Array(array) => array.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(node).unwrap();
+    strip(&mut value);
+    value
+}
+
+#[test]
+fn quote() {
+    let sheet: Stylesheets = quote_less! {
+        @size: 10px;
+        ##header .nav > li:first-child, a:not(.b) {
+            color: @color;
+            margin: -5px 0 (@size * 1.5);
+            width: percentage(0.5);
+            .mixin();
+        }
+        @media screen { .a { b: "c"; } }
+    };
+    let source = "@size: 10px;\n#header .nav > li:first-child, a:not(.b) {\n    color: @color;\n    margin: -5px 0 (@size * 1.5);\n    width: percentage(0.5);\n    .mixin();\n}\n@media screen { .a { b: \"c\"; } }\n";
+    let parsed = Parser::new(source).parse().unwrap();
+    assert_eq!(without_spans(&sheet), without_spans(&parsed));
+    assert!(sheet.span == Default::default());
+}
+
+#[test]
+fn quote_splices() {
+    let name = "primary";
+    let property = "color";
+    let color = "red";
+    let style = String::from("dashed");
+    let sheet: Stylesheets = quote_less! {
+        .btn-#name { #property: #color; border: 1px #style ##fff; }
+    };
+    let parsed = Parser::new(".btn-primary { color: red; border: 1px dashed #fff; }")
+        .parse()
+        .unwrap();
+    assert_eq!(without_spans(&sheet), without_spans(&parsed));
+
+    let width = 2;
+    let value: VariableValueList = quote_less!(value; #width solid);
+    assert_eq!(
+        value[0],
+        VariableDefinedValue::PreservedToken(PreservedToken::Number(NumberLiteral {
+            span: Default::default(),
+            value: 2.0,
+            unit: None,
+        }))
+    );
+}
@@ -1,15 +1,22 @@
 use less_ast::ast::{
-    AtKeyword, AtRule, Atom, BinaryExpression, BinaryOperator, Color, ComponentValue,
+    AtKeyword, AtRule, Atom, BinaryExpression, BinaryOperator, Color, Comment, ComponentValue,
     ComponentValueList, CurlyBracketsBlock, CurlyBracketsBlockContent, Declaration,
     DeclarationList, DefinedStatement, Express, FunctionExpression, Ident, LexerToken, MapVariable,
-    MapVariableDefined, MixinCall, MixinDefined, NumberLiteral, Param, PreservedToken,
-    PseudoElement, PseudoFunction, PseudoSelector, QualifiedRule, Selector, SelectorComponentList,
-    SelectorList, SimpleSelector, Span, StringLiteral, StyleContent, Stylesheets, VariableDefined,
-    VariableDefinedValue, VariableExpression, VariableValueList,
+    MapVariableDefined, MixinCall, MixinDefined, NumberLiteral, Param, ParenthesesExpression,
+    PreservedToken, PseudoElement, PseudoFunction, PseudoSelector, QualifiedRule, Selector,
+    SelectorComponentList, SelectorList, SimpleSelector, Span, StringLiteral, StyleContent,
+    Stylesheets, UrlLiteral, VariableDefined, VariableDefinedValue, VariableExpression,
+    VariableValueList,
+};
+use less_ast::source::{FileId, SourceFile};
+use less_error::{
+    codes::Code,
+    diagnostic::{Diagnostic, Label},
+    ErrorKind, LessError,
 };
 use less_lexer::{
     token::{self, Kind, Token},
-    Lexer, LexerMode,
+    Lexer, LexerError, LexerMode,
 };
 use less_test_data::{read_test_file, test_main_less_feature};
 use log::{debug, error, info, trace};
@@ -20,19 +27,29 @@ use thiserror::Error;
 pub struct Parser<'source> {
     lexer: Lexer<'source>,
     source: &'source str,
+    // every span is tagged with the file it was parsed from
+    file: FileId,
+    // end of the last consumed token that is not whitespace
+    last_end: usize,
 }
 
 impl<'source> Parser<'source> {
     pub fn new(source: &'source str) -> Self {
+        Self::with_file(source, FileId::default())
+    }
+
+    pub fn with_file(source: &'source str, file: FileId) -> Self {
         Self {
             lexer: Lexer::new(source),
             source,
+            file,
+            last_end: 0,
         }
     }
 }
 
 #[derive(Debug, Error)]
-enum ParserError {
+pub enum ParserError {
     #[error("Unexpected token {0}")]
     UnexpectedToken(Token),
     #[error("Lexer error: {0}")]
@@ -41,11 +58,84 @@ enum ParserError {
     ParseNUmberError(#[from] std::num::ParseFloatError),
 }
 
+impl ParserError {
+    // byte offset the error points at
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            ParserError::UnexpectedToken(token) => Some(token.start),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> Code {
+        match self {
+            ParserError::UnexpectedToken(_) => Code::UnexpectedToken,
+            ParserError::LexerError(LexerError::UnexpectedEof) => Code::UnexpectedEof,
+            ParserError::LexerError(LexerError::UnexpectedToken(_)) => Code::UnexpectedToken,
+            ParserError::LexerError(LexerError::UnexpectedChar(_)) => Code::UnexpectedChar,
+            ParserError::LexerError(LexerError::ParserColorError) => Code::InvalidColor,
+            ParserError::ParseNUmberError(_) => Code::InvalidNumber,
+        }
+    }
+
+    // errors without an offset happened at the end of the input
+    pub fn to_less_error(&self, file: &SourceFile) -> LessError {
+        let message = match self {
+            ParserError::UnexpectedToken(token) if token.kind == Kind::RightBrace => {
+                "Unrecognised input. Possibly missing opening '{'".to_string()
+            }
+            ParserError::UnexpectedToken(token) if token.kind == Kind::EOF => {
+                "Unrecognised input. Possibly missing something".to_string()
+            }
+            ParserError::UnexpectedToken(_) => "Unrecognised input".to_string(),
+            _ => self.to_string(),
+        };
+        let offset = self.offset().unwrap_or(file.src.len());
+        LessError::new(ErrorKind::Parse, message).with_location(file, offset)
+    }
+
+    pub fn to_diagnostic(&self, file: &SourceFile) -> Diagnostic {
+        let end = Span::new(file.src.len(), file.src.len()).with_file(file.id);
+        let diagnostic = match self {
+            ParserError::UnexpectedToken(token) => {
+                let span = Span::new(token.start, token.end).with_file(file.id);
+                let diagnostic = Diagnostic::error("Unrecognised input")
+                    .with_label(Label::primary(span, format!("unexpected {}", token.kind)));
+                match token.kind {
+                    Kind::RightBrace => diagnostic.with_help("possibly missing opening '{'"),
+                    Kind::EOF => diagnostic.with_help("possibly missing something"),
+                    _ => diagnostic,
+                }
+            }
+            ParserError::LexerError(LexerError::UnexpectedEof) => {
+                Diagnostic::error(self.to_string())
+                    .with_label(Label::primary(end, "unexpected end of file"))
+                    .with_help("check for an unclosed comment, string or block")
+            }
+            _ => Diagnostic::error(self.to_string()).with_label(Label::primary(end, "")),
+        };
+        diagnostic.with_code(self.code())
+    }
+}
+
 impl<'source> Parser<'source> {
     pub fn next_token(&mut self) -> Result<Token, ParserError> {
         let token = self.lexer.next()?;
+        if token.kind != Kind::Whitespace {
+            self.last_end = token.end;
+        }
         Ok(token)
     }
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(start, end).with_file(self.file)
+    }
+    pub fn token_span(&self, token: &Token) -> Span {
+        self.span(token.start, token.end)
+    }
+    // from `start` to the end of the last consumed token
+    fn span_from(&self, start: usize) -> Span {
+        self.span(start, self.last_end.max(start))
+    }
     pub fn skip_whitespace(&mut self) {
         while let Ok(token) = self.peek_token() {
             if token.kind == Kind::Whitespace {
@@ -55,6 +145,32 @@ impl<'source> Parser<'source> {
             }
         }
     }
+    // the comments between the last consumed token and the next one
+    fn take_comments(&mut self) -> Vec<Comment> {
+        let Ok(next) = self.peek_token() else {
+            return Vec::new();
+        };
+        let to = next.start;
+        self.lexer
+            .take_comments(self.last_end, to)
+            .into_iter()
+            .map(|token| Comment {
+                span: self.token_span(&token),
+                value: self.get_atom(&token),
+                is_line: token.kind == Kind::LineComment,
+            })
+            .collect()
+    }
+    fn is_at_comment(&mut self) -> bool {
+        self.skip_whitespace();
+        match self.peek_token() {
+            Ok(next) => {
+                let to = next.start;
+                self.lexer.has_comments(self.last_end, to)
+            }
+            Err(_) => false,
+        }
+    }
     pub fn peek_token(&mut self) -> Result<&Token, ParserError> {
         Ok(self.lexer.peek()?)
     }
@@ -99,10 +215,23 @@ impl<'source> Parser<'source> {
         Ok(self.source[token.start..token.end].parse::<f64>()?)
     }
 
+    /// a value on its own, e.g. `1px solid @color` given to `--global-var`
+    pub fn parse_value(&mut self) -> Result<VariableValueList, ParserError> {
+        self.skip_whitespace();
+        let value = self.parse_value_list()?;
+        self.skip_whitespace();
+        self.expect(Kind::EOF)?;
+        Ok(value)
+    }
+
     pub fn parse(&mut self) -> Result<Stylesheets, ParserError> {
         let mut content = Vec::new();
 
-        while let Ok(token) = self.peek_token() {
+        loop {
+            content.extend(self.take_comments().into_iter().map(StyleContent::Comment));
+            let Ok(token) = self.peek_token() else {
+                break;
+            };
             match token.kind {
                 Kind::AtKeyword => {
                     if self.is_at_defined_statement() {
@@ -110,8 +239,6 @@ impl<'source> Parser<'source> {
                         content.push(StyleContent::DefinedStatement(statement));
                     } else {
                         let rule = self.parse_at_rule()?;
-                        dbg!(&rule);
-
                         content.push(StyleContent::AtRule(rule));
                     }
                 }
@@ -140,22 +267,66 @@ impl<'source> Parser<'source> {
             }
         }
         Ok(Stylesheets {
-            span: Span::new(0, self.source.len()),
+            span: self.span(0, self.source.len()),
             content,
         })
     }
 
     fn parse_at_rule(&mut self) -> Result<AtRule, ParserError> {
         let name = self.parse_at_keyword()?;
-        let prelude = self.parse_value_list()?;
+        let mut prelude = self.parse_value_list()?;
+        // @media (min-width: 1px), @import (optional) "a"; `(` is already kept as a token
+        while matches!(self.peek_token(), Ok(token) if token.kind == Kind::RightParen) {
+            let token = self.next_token()?;
+            prelude.push(VariableDefinedValue::PreservedToken(PreservedToken::Token(
+                LexerToken {
+                    name: self.get_atom(&token),
+                    span: self.token_span(&token),
+                },
+            )));
+            prelude.extend(self.parse_value_list()?);
+        }
+        // @keyframes a /* b */ { } moves the comment into the block, other comments are dropped
+        let is_blank = |value: &VariableDefinedValue| match value {
+            VariableDefinedValue::Comment(_) => true,
+            VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) => {
+                token.name.trim().is_empty()
+            }
+            _ => false,
+        };
+        let trailing_start = prelude
+            .iter()
+            .rposition(|value| !is_blank(value))
+            .map_or(0, |index| index + 1);
+        let trailing_comments: Vec<Comment> = prelude
+            .drain(trailing_start..)
+            .filter_map(|value| match value {
+                VariableDefinedValue::Comment(comment) => Some(comment),
+                _ => None,
+            })
+            .collect();
+        prelude.retain(|value| !matches!(value, VariableDefinedValue::Comment(_)));
         let block = if self.is_at_semicolon() {
             self.expect(Kind::Semicolon)?; // skip ;
             None
         } else {
-            Some(self.parse_block()?)
+            // @media print { }
+            let left_brace = self.expect_skit_whitespace(Kind::LeftBrace)?;
+            if left_brace.kind != Kind::LeftBrace {
+                return Err(ParserError::UnexpectedToken(left_brace));
+            }
+            let mut block = self.parse_block()?;
+            let end = self.expect(Kind::RightBrace)?.end;
+            block.span = self.span(left_brace.start, end);
+            let comments = trailing_comments
+                .into_iter()
+                .map(CurlyBracketsBlockContent::Comment);
+            block.content.splice(0..0, comments);
+            Some(block)
         };
 
         return Ok(AtRule {
+            span: self.span_from(name.span.start),
             name,
             prelude,
             block,
@@ -209,13 +380,14 @@ impl<'source> Parser<'source> {
         let start = self.peek_token()?.start;
 
         let prelude = self.parse_prelude()?;
-        self.expect(Kind::LeftBrace)?;
-        let block = self.parse_block()?;
+        let left_brace = self.expect(Kind::LeftBrace)?;
+        let mut block = self.parse_block()?;
         let end = self.expect(Kind::RightBrace)?.end;
+        block.span = self.span(left_brace.start, end);
         Ok(QualifiedRule {
             prelude,
             block: Box::new(block),
-            span: Span::new(start, end),
+            span: self.span(start, end),
         })
     }
     fn is_at_mixin_name(&mut self) -> bool {
@@ -227,7 +399,7 @@ impl<'source> Parser<'source> {
         let end = self.parse_ident()?;
         Ok(SimpleSelector {
             name: self.get_atom_by_span(start.start, end.span.end),
-            span: Span::new(start.start, end.span.end),
+            span: self.span(start.start, end.span.end),
         })
     }
     fn try_parse_mixin_defined(&mut self) -> Result<MixinDefined, ParserError> {
@@ -238,10 +410,12 @@ impl<'source> Parser<'source> {
         self.skip_whitespace();
         self.expect(Kind::RightParen)?;
         self.skip_whitespace();
-        self.expect(Kind::LeftBrace)?;
-        let block = self.parse_block()?;
-        self.expect(Kind::RightBrace)?;
+        let left_brace = self.expect(Kind::LeftBrace)?;
+        let mut block = self.parse_block()?;
+        let end = self.expect(Kind::RightBrace)?.end;
+        block.span = self.span(left_brace.start, end);
         Ok(MixinDefined {
+            span: self.span(name.span.start, end),
             name,
             params,
             block,
@@ -263,6 +437,7 @@ impl<'source> Parser<'source> {
             };
 
             params.push(Param {
+                span: self.span_from(name.span.start),
                 name,
                 default_params,
             })
@@ -316,12 +491,38 @@ impl<'source> Parser<'source> {
             && !self.is_at_right_parent()
             && !self.is_at_comma()
         {
+            // u+b is lexed as a unicode range, in a selector it is `u`, `+` and `b`
+            if self.is_at_unicode_range() {
+                prelude.extend(self.parse_unicode_range_selector()?);
+                continue;
+            }
             let component = self.parse_selector_component()?;
             prelude.push(component);
         }
         Ok(prelude)
     }
 
+    fn parse_unicode_range_selector(&mut self) -> Result<Vec<Selector>, ParserError> {
+        let token = self.expect(Kind::UnicodeRange)?;
+        let plus = token.start + 1;
+        let mut end = token.end;
+        // u+bold is lexed as u+b and old
+        if self.is_at_element() {
+            end = self.parse_element()?.end;
+        }
+        let simple = |parser: &Self, start, end| {
+            Selector::SimpleSelector(SimpleSelector {
+                name: parser.get_atom_by_span(start, end),
+                span: parser.span(start, end),
+            })
+        };
+        Ok(vec![
+            simple(self, token.start, plus),
+            simple(self, plus, plus + 1),
+            simple(self, plus + 1, end),
+        ])
+    }
+
     fn is_at_hash(&mut self) -> bool {
         if let Ok(token) = self.peek_token() {
             return matches!(token.kind, Kind::Hash);
@@ -400,15 +601,46 @@ impl<'source> Parser<'source> {
             || self.is_at_hash()
             || self.is_at_ident()
             || self.is_at_number()
-            || self.is_at_dot();
+            || self.is_at_dot()
+            || self.is_at_left_square_bracket()
+            || self.is_at_unicode_range();
+    }
+
+    fn is_at_left_square_bracket(&mut self) -> bool {
+        if let Ok(token) = self.peek_token() {
+            return matches!(token.kind, Kind::LeftBracket);
+        }
+        false
+    }
+
+    // [ng\:cloak] [type="text"], kept as written
+    fn parse_attribute_selector(&mut self) -> Result<Selector, ParserError> {
+        let start_token = self.expect(Kind::LeftBracket)?;
+        loop {
+            let token = self.next_token()?;
+            match token.kind {
+                Kind::RightBracket => {
+                    return Ok(Selector::SimpleSelector(SimpleSelector {
+                        name: self.get_atom_by_span(start_token.start, token.end),
+                        span: self.span(start_token.start, token.end),
+                    }));
+                }
+                Kind::EOF | Kind::LeftBrace | Kind::RightBrace | Kind::Semicolon => {
+                    return Err(ParserError::UnexpectedToken(token));
+                }
+                _ => {}
+            }
+        }
     }
 
     // const re = /^[#.](?:[\w-]|\\(?:[A-Fa-f0-9]{1,6} ?|[^A-Fa-f0-9]))+/;
     fn parse_selector_component(&mut self) -> Result<Selector, ParserError> {
         trace!("parse_selector_component");
         if self.is_at_ampersand() {
-            self.expect(Kind::Ampersand)?;
-            return Ok(Selector::ParentSelector);
+            let ampersand = self.expect(Kind::Ampersand)?;
+            return Ok(Selector::ParentSelector(self.token_span(&ampersand)));
+        } else if self.is_at_left_square_bracket() {
+            return self.parse_attribute_selector();
         } else if self.is_at_colon() {
             trace!("parse_selector_component");
             let start_token = self.expect(Kind::Colon)?;
@@ -421,7 +653,7 @@ impl<'source> Parser<'source> {
                 return Ok(Selector::PseudoSelector(PseudoSelector::PseudoFunction(
                     PseudoFunction {
                         name: self.get_atom_by_span(start_token.start, end_token.end),
-                        span: Span::new(start_token.start, end_token.end),
+                        span: self.span(start_token.start, end_token.end),
                         params,
                     },
                 )));
@@ -430,7 +662,7 @@ impl<'source> Parser<'source> {
             return Ok(Selector::PseudoSelector(PseudoSelector::PseudoElement(
                 PseudoElement {
                     name: self.get_atom_by_span(start_token.start, end_token.end),
-                    span: Span::new(start_token.start, end_token.end),
+                    span: self.span(start_token.start, end_token.end),
                 },
             )));
         } else if self.is_at_hash() {
@@ -439,7 +671,7 @@ impl<'source> Parser<'source> {
             let end_token = self.parse_element()?;
             return Ok(Selector::SimpleSelector(SimpleSelector {
                 name: self.get_atom_by_span(start_token.start, end_token.end),
-                span: Span::new(start_token.start, end_token.end),
+                span: self.span(start_token.start, end_token.end),
             }));
         } else if self.is_at_dot() {
             trace!("parse_element");
@@ -447,7 +679,7 @@ impl<'source> Parser<'source> {
             let end_token = self.parse_element()?;
             return Ok(Selector::SimpleSelector(SimpleSelector {
                 name: self.get_atom_by_span(start_token.start, end_token.end),
-                span: Span::new(start_token.start, end_token.end),
+                span: self.span(start_token.start, end_token.end),
             }));
         } else if self.is_at_ident() || self.is_at_number() {
             trace!("parse_element");
@@ -458,7 +690,7 @@ impl<'source> Parser<'source> {
             }
             return Ok(Selector::SimpleSelector(SimpleSelector {
                 name: self.get_atom_by_span(start_token.start, end_pos),
-                span: Span::new(start_token.start, end_pos),
+                span: self.span(start_token.start, end_pos),
             }));
         } else {
             trace!("unexpected token");
@@ -486,7 +718,7 @@ impl<'source> Parser<'source> {
             let token = self.next_token()?;
             return Ok(Selector::SimpleSelector(SimpleSelector {
                 name: self.get_atom(&token),
-                span: token.into(),
+                span: self.token_span(&token),
             }));
         }
         trace!("unexpected token");
@@ -516,6 +748,7 @@ impl<'source> Parser<'source> {
                 self.expect_skit_whitespace(Kind::Colon)?;
                 self.skip_whitespace();
                 let value = self.parse_value_list()?;
+                let span = self.span_from(name.start);
 
                 // the next token should have three result
                 // ;} or } or ; Ident
@@ -523,9 +756,14 @@ impl<'source> Parser<'source> {
                     self.expect(Kind::Semicolon)?;
                 }
                 declaration_list.push(Declaration {
+                    span,
                     name: self.get_atom(&name),
                     value,
                 });
+                // a: b; /* c */ the comment is parsed by the block
+                if self.is_at_comment() {
+                    break;
+                }
                 let is_at_right_curly_bracket = self.is_at_right_curly_bracket();
                 if is_at_right_curly_bracket {
                     break;
@@ -538,9 +776,16 @@ impl<'source> Parser<'source> {
         return Ok(declaration_list);
     }
 
+    // the span covers the content, callers that consume the braces widen it
     fn parse_block(&mut self) -> Result<CurlyBracketsBlock, ParserError> {
+        let start = self.peek_token()?.start;
         let mut content: Vec<CurlyBracketsBlockContent> = Vec::new();
-        while let Ok(token) = self.peek_token() {
+        loop {
+            let comments = self.take_comments();
+            content.extend(comments.into_iter().map(CurlyBracketsBlockContent::Comment));
+            let Ok(token) = self.peek_token() else {
+                break;
+            };
             trace!("parse_block: {:?}", token);
             match token.kind {
                 Kind::AtKeyword => {
@@ -554,7 +799,10 @@ impl<'source> Parser<'source> {
                 }
                 Kind::Ident => {
                     let declaration = self.try_parse_declaration()?;
-                    content.push(CurlyBracketsBlockContent::DeclarationList(declaration));
+                    if !declaration.is_empty() {
+                        content.push(CurlyBracketsBlockContent::DeclarationList(declaration));
+                        continue;
+                    }
                     self.lexer.start();
                     if let Ok(mixin_defined) = self.try_parse_mixin_defined() {
                         content.push(CurlyBracketsBlockContent::DefinedStatement(
@@ -588,15 +836,12 @@ impl<'source> Parser<'source> {
                         }
                         self.lexer.start();
                         if let Ok(mixin_call) = self.try_parse_mixin_call() {
-                            dbg!(&mixin_call);
                             self.expect(Kind::Semicolon);
                             content.push(CurlyBracketsBlockContent::MixinCall(mixin_call));
                             continue;
                         } else {
                             self.lexer.restore();
                         }
-                        dbg!("rule");
-
                         let rule = self.parse_rule()?;
                         content.push(CurlyBracketsBlockContent::QualifiedRule(rule));
                         continue;
@@ -606,28 +851,31 @@ impl<'source> Parser<'source> {
                 }
             }
         }
-        Ok(CurlyBracketsBlock { content })
+        Ok(CurlyBracketsBlock {
+            span: self.span_from(start),
+            content,
+        })
     }
 
     fn parse_ident(&mut self) -> Result<Ident, ParserError> {
         let ident = self.expect(Kind::Ident)?;
         Ok(Ident {
             name: self.get_atom(&ident),
-            span: ident.into(),
+            span: self.token_span(&ident),
         })
     }
     fn parse_at_keyword(&mut self) -> Result<AtKeyword, ParserError> {
         let ident = self.expect(Kind::AtKeyword)?;
         Ok(AtKeyword {
             name: self.get_atom(&ident),
-            span: ident.into(),
+            span: self.token_span(&ident),
         })
     }
     fn parse_string_literal(&mut self) -> Result<StringLiteral, ParserError> {
         let string = self.expect(Kind::String)?;
         Ok(StringLiteral {
             value: self.get_atom(&string),
-            span: string.into(),
+            span: self.token_span(&string),
         })
     }
 
@@ -657,13 +905,13 @@ impl<'source> Parser<'source> {
             };
             return Ok(NumberLiteral {
                 value: self.get_float_number(&number)?,
-                span: number.into(),
+                span: self.span(number.start, unit.end),
                 unit: Some(self.get_atom(&unit)),
             });
         }
         Ok(NumberLiteral {
             value: self.get_float_number(&number)?,
-            span: number.into(),
+            span: self.token_span(&number),
             unit: None,
         })
     }
@@ -673,13 +921,13 @@ impl<'source> Parser<'source> {
     }
     fn parse_value_defined(&mut self, name: Token) -> Result<VariableDefined, ParserError> {
         let value = self.parse_value_list()?;
-        dbg!(&value);
-        dbg!(self.peek_token());
+        let span = self.span_from(name.start);
         self.expect(Kind::Semicolon)?;
         Ok(VariableDefined {
+            span,
             name: AtKeyword {
                 name: self.get_atom(&name),
-                span: name.into(),
+                span: self.token_span(&name),
             },
             value,
         })
@@ -703,6 +951,9 @@ impl<'source> Parser<'source> {
         while self.is_at_value_defined_value() {
             let value = self.parse_value_list_item()?;
             values.push(value);
+            // a: yes /* comment */;
+            let comments = self.take_comments();
+            values.extend(comments.into_iter().map(VariableDefinedValue::Comment));
             if self.is_at_semicolon() {
                 break;
             }
@@ -735,8 +986,56 @@ impl<'source> Parser<'source> {
         false
     }
 
+    fn is_at_url(&mut self) -> bool {
+        if let Ok(token) = self.peek_token() {
+            return matches!(token.kind, Kind::Url);
+        }
+        false
+    }
+
+    // U+0025-00FF is kept as a plain token
+    fn is_at_unicode_range(&mut self) -> bool {
+        if let Ok(token) = self.peek_token() {
+            return matches!(token.kind, Kind::UnicodeRange);
+        }
+        false
+    }
+
+    // url(a.png)
+    fn parse_url_literal(&mut self) -> Result<UrlLiteral, ParserError> {
+        let url = self.expect(Kind::Url)?;
+        // strip `url(` and `)`
+        let value = self.get_source_sub_str(url.start + 4, url.end - 1).trim();
+        Ok(UrlLiteral {
+            value: value.to_string(),
+            quote: None,
+            span: self.token_span(&url),
+        })
+    }
+
+    // url("a.png")
+    fn try_parse_quoted_url(&mut self) -> Result<UrlLiteral, ParserError> {
+        let name = self.expect(Kind::Ident)?;
+        if !self.get_atom(&name).eq_ignore_ascii_case("url") {
+            return Err(ParserError::UnexpectedToken(name));
+        }
+        self.expect(Kind::LeftParen)?;
+        self.skip_whitespace();
+        let string = self.expect(Kind::String)?;
+        self.skip_whitespace();
+        let end = self.expect(Kind::RightParen)?;
+        let quote = self.source[string.start..].chars().next();
+        Ok(UrlLiteral {
+            value: self.get_atom_by_span(string.start + 1, string.end - 1),
+            quote,
+            span: self.span(name.start, end.end),
+        })
+    }
+
     fn is_at_value_defined_value(&mut self) -> bool {
         return self.is_at_ident()
+            || self.is_at_url()
+            || self.is_at_unicode_range()
             || self.is_at_at_keyword()
             || self.is_at_string()
             || self.is_at_number()
@@ -748,10 +1047,22 @@ impl<'source> Parser<'source> {
             || self.is_at_slash()
             || self.is_at_left_parent()
             || self.is_at_tilde()
+            || self.is_at_format_function()
             || self.is_at_selector_component()
             || self.is_at_bang()
             || self.is_at_equal();
     }
+    // %("%d", 1)
+    fn is_at_format_function(&mut self) -> bool {
+        if let Ok(token) = self.peek_token() {
+            if matches!(token.kind, Kind::Percent) {
+                if let Ok(token) = self.peek_nth_token(1) {
+                    return matches!(token.kind, Kind::LeftParen);
+                }
+            }
+        }
+        false
+    }
     fn is_at_equal(&mut self) -> bool {
         if let Ok(token) = self.peek_token() {
             return matches!(token.kind, Kind::Equals);
@@ -800,7 +1111,21 @@ impl<'source> Parser<'source> {
             self.expect(Kind::Bang)?;
             let ident = self.parse_ident()?;
             return Ok(VariableDefinedValue::Important(ident));
+        } else if self.is_at_url() {
+            let url = self.parse_url_literal()?;
+            return Ok(VariableDefinedValue::PreservedToken(PreservedToken::Url(
+                url,
+            )));
         } else if self.is_at_ident() {
+            // url("a.png")
+            self.lexer.start();
+            if let Ok(url) = self.try_parse_quoted_url() {
+                return Ok(VariableDefinedValue::PreservedToken(PreservedToken::Url(
+                    url,
+                )));
+            } else {
+                self.lexer.restore();
+            }
             // a()
             if let Ok(token) = self.peek_nth_token(1) {
                 if matches!(token.kind, Kind::LeftParen) {
@@ -813,6 +1138,15 @@ impl<'source> Parser<'source> {
             return Ok(VariableDefinedValue::PreservedToken(PreservedToken::Ident(
                 ident,
             )));
+        } else if self.is_at_tilde() || self.is_at_format_function() {
+            // ~"string" %("%d", 1)
+            self.lexer.start();
+            let express = self.try_parse_express();
+            if let Ok(express) = express {
+                return Ok(VariableDefinedValue::Express(express));
+            } else {
+                self.lexer.restore();
+            }
         } else if self.is_at_dot() || self.is_at_hash() {
             // may be mixin
             self.lexer.start();
@@ -860,7 +1194,7 @@ impl<'source> Parser<'source> {
         return Ok(VariableDefinedValue::PreservedToken(PreservedToken::Token(
             LexerToken {
                 name: self.get_atom(&token),
-                span: token.into(),
+                span: self.token_span(&token),
             },
         )));
     }
@@ -873,19 +1207,23 @@ impl<'source> Parser<'source> {
                 Kind::Plus => {
                     self.expect(Kind::Plus)?;
                     self.skip_whitespace();
+                    let right = self.try_parse_term()?;
                     cur = Express::BinaryExpression(BinaryExpression {
+                        span: cur.span().merge(right.span()),
                         left: Box::new(cur),
                         operator: BinaryOperator::Plus,
-                        right: Box::new(self.try_parse_term()?),
+                        right: Box::new(right),
                     });
                 }
                 Kind::Minus => {
                     self.expect(Kind::Minus)?;
                     self.skip_whitespace();
+                    let right = self.try_parse_term()?;
                     cur = Express::BinaryExpression(BinaryExpression {
+                        span: cur.span().merge(right.span()),
                         left: Box::new(cur),
                         operator: BinaryOperator::Minus,
-                        right: Box::new(self.try_parse_term()?),
+                        right: Box::new(right),
                     });
                 }
                 _ => break,
@@ -901,19 +1239,23 @@ impl<'source> Parser<'source> {
                 Kind::Asterisk => {
                     self.expect(Kind::Asterisk)?;
                     self.skip_whitespace();
+                    let right = self.try_parse_factory()?;
                     cur = Express::BinaryExpression(BinaryExpression {
+                        span: cur.span().merge(right.span()),
                         left: Box::new(cur),
-                        operator: BinaryOperator::Plus,
-                        right: Box::new(self.try_parse_factory()?),
+                        operator: BinaryOperator::Mul,
+                        right: Box::new(right),
                     });
                 }
                 Kind::Slash => {
                     self.expect(Kind::Slash)?;
                     self.skip_whitespace();
+                    let right = self.try_parse_factory()?;
                     cur = Express::BinaryExpression(BinaryExpression {
+                        span: cur.span().merge(right.span()),
                         left: Box::new(cur),
-                        operator: BinaryOperator::Minus,
-                        right: Box::new(self.try_parse_factory()?),
+                        operator: BinaryOperator::Div,
+                        right: Box::new(right),
                     });
                 }
 
@@ -934,20 +1276,28 @@ impl<'source> Parser<'source> {
         return self.is_at_selector_component();
     }
     fn try_parse_mixin_call(&mut self) -> Result<MixinCall, ParserError> {
+        let start = self.peek_token()?.start;
         let name = self.parse_prelude_component()?;
         if self.is_at_left_parent() {
             self.expect(Kind::LeftParen)?;
             self.skip_whitespace();
             let express = self.parse_value_list()?;
             self.expect(Kind::RightParen)?;
+            let span = self.span_from(start);
             self.skip_whitespace();
             return Ok(MixinCall {
+                span,
                 name,
                 params: Some(express),
             });
         }
+        let span = self.span_from(start);
         self.expect(Kind::Semicolon)?;
-        return Ok(MixinCall { name, params: None });
+        return Ok(MixinCall {
+            span,
+            name,
+            params: None,
+        });
     }
 
     fn is_at_color(&mut self) -> bool {
@@ -979,16 +1329,19 @@ impl<'source> Parser<'source> {
             }
             Kind::AtKeyword => {
                 let keyword = self.parse_at_keyword()?;
+                let keyword_start = keyword.span.start;
                 // @x[][][]
                 if self.is_at_left_bracket() {
                     self.expect(Kind::LeftBracket)?;
                     self.skip_whitespace();
                     let object = Express::VariableExpression(VariableExpression::Variable(keyword));
+                    let property = self.parse_ident()?;
+                    let end = self.expect(Kind::RightBracket)?.end;
                     let map_variable = MapVariable {
+                        span: self.span(keyword_start, end),
                         object: Box::new(object),
-                        property: self.parse_ident()?,
+                        property,
                     };
-                    self.expect(Kind::RightBracket)?;
                     return Ok(Express::VariableExpression(
                         VariableExpression::MapVariable(map_variable),
                     ));
@@ -997,14 +1350,23 @@ impl<'source> Parser<'source> {
                     keyword,
                 )));
             }
-            Kind::Ident => {
-                let name = self.parse_ident()?;
+            Kind::Ident | Kind::Percent => {
+                let name = if self.is_at_format_function() {
+                    let percent = self.expect(Kind::Percent)?;
+                    Ident {
+                        name: self.get_atom(&percent),
+                        span: self.token_span(&percent),
+                    }
+                } else {
+                    self.parse_ident()?
+                };
                 self.expect(Kind::LeftParen)?;
                 self.skip_whitespace();
                 let express = self.parse_value_list()?;
-                self.expect(Kind::RightParen)?;
+                let end = self.expect(Kind::RightParen)?.end;
                 self.skip_whitespace();
                 return Ok(Express::FunctionExpression(FunctionExpression {
+                    span: self.span(name.span.start, end),
                     name,
                     params: express,
                 }));
@@ -1017,7 +1379,7 @@ impl<'source> Parser<'source> {
                         return Ok(Express::VariableExpression(VariableExpression::Color(
                             Color {
                                 value: self.get_atom_by_span(start.start, end.end),
-                                span: Span::new(start.start, end.end),
+                                span: self.span(start.start, end.end),
                             },
                         )));
                     }
@@ -1034,13 +1396,16 @@ impl<'source> Parser<'source> {
                 return Ok(Express::StringEscape(express));
             }
             Kind::LeftParen => {
-                self.expect(Kind::LeftParen)?;
+                let start = self.expect(Kind::LeftParen)?.start;
                 self.skip_whitespace();
 
                 let express = self.try_parse_express()?;
                 self.skip_whitespace();
-                self.expect(Kind::RightParen)?;
-                return Ok(Express::ParenthesesExpression(Box::new(express)));
+                let end = self.expect(Kind::RightParen)?.end;
+                return Ok(Express::ParenthesesExpression(ParenthesesExpression {
+                    span: self.span(start, end),
+                    express: Box::new(express),
+                }));
             }
             _ => {
                 return Err(ParserError::UnexpectedToken(self.next_token()?));
@@ -1225,3 +1590,102 @@ fn test_main_less() {
         }
     })
 }
+
+#[test]
+fn node_spans() {
+    let code = ".a {\n  width: (1 + 2) * 3;\n  & .b { c: d }\n}\n@media print;\n";
+    let file = FileId(3);
+    let ast = Parser::with_file(code, file).parse().unwrap();
+    let slice = |span: &Span| {
+        assert_eq!(span.file, file);
+        &code[span.start..span.end]
+    };
+
+    let StyleContent::QualifiedRule(rule) = &ast.content[0] else {
+        panic!("expected a rule");
+    };
+    assert!(slice(&rule.span).starts_with(".a {"));
+    assert!(slice(&rule.block.span).starts_with("{\n  width"));
+    let CurlyBracketsBlockContent::DeclarationList(declarations) = &rule.block.content[0] else {
+        panic!("expected declarations");
+    };
+    assert_eq!(slice(&declarations[0].span), "width: (1 + 2) * 3");
+    let VariableDefinedValue::Express(express) = &declarations[0].value[0] else {
+        panic!("expected an expression");
+    };
+    assert_eq!(slice(express.span()), "(1 + 2) * 3");
+    let Express::BinaryExpression(binary) = express else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(slice(binary.left.span()), "(1 + 2)");
+    let CurlyBracketsBlockContent::QualifiedRule(nested) = &rule.block.content[1] else {
+        panic!("expected a nested rule");
+    };
+    assert_eq!(slice(nested.prelude[0][0].span()), "&");
+    assert_eq!(slice(&nested.span), "& .b { c: d }");
+
+    let StyleContent::AtRule(at_rule) = &ast.content[1] else {
+        panic!("expected an at-rule");
+    };
+    assert_eq!(slice(&at_rule.span), "@media print;");
+}
+
+#[test]
+fn comments() {
+    let code = "/* a */\n.b /* c */ {\n  d: e /* f */; // g\n  h: i;\n}\n";
+    let ast = Parser::new(code).parse().unwrap();
+    let comment = |content: &Comment| (content.value.clone(), content.is_line);
+
+    let StyleContent::Comment(header) = &ast.content[0] else {
+        panic!("expected a comment");
+    };
+    assert_eq!(comment(header), ("/* a */".to_string(), false));
+    assert_eq!(&code[header.span.start..header.span.end], "/* a */");
+    let StyleContent::QualifiedRule(rule) = &ast.content[1] else {
+        panic!("expected a rule");
+    };
+    let CurlyBracketsBlockContent::DeclarationList(declarations) = &rule.block.content[0] else {
+        panic!("expected declarations");
+    };
+    let VariableDefinedValue::Comment(trailing) = declarations[0].value.last().unwrap() else {
+        panic!("expected a comment in the value");
+    };
+    assert_eq!(comment(trailing), ("/* f */".to_string(), false));
+    let CurlyBracketsBlockContent::Comment(line) = &rule.block.content[1] else {
+        panic!("expected a line comment");
+    };
+    assert_eq!(comment(line), ("// g".to_string(), true));
+    assert!(matches!(
+        &rule.block.content[2],
+        CurlyBracketsBlockContent::DeclarationList(declarations) if declarations[0].name == "h"
+    ));
+}
+
+#[test]
+fn parse_value() {
+    let value = Parser::new(" 1px solid @color ").parse_value().unwrap();
+    assert!(!value.is_empty());
+    assert!(Parser::new("(@a * 2)").parse_value().is_ok());
+    assert!(matches!(
+        Parser::new("red; .a { b: c }").parse_value(),
+        Err(ParserError::UnexpectedToken(_))
+    ));
+}
+
+#[test]
+fn unicode_range_selector() {
+    let code = "u+b { color: red; }\nu+bold { unicode-range: U+0025-00FF; }\n";
+    let ast = Parser::new(code).parse().unwrap();
+    let names = |index: usize| {
+        let StyleContent::QualifiedRule(rule) = &ast.content[index] else {
+            panic!("expected a rule");
+        };
+        rule.prelude[0]
+            .iter()
+            .map(|selector| code[selector.span().start..selector.span().end].trim())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(0), ["u", "+", "b"]);
+    assert_eq!(names(1), ["u", "+", "bold"]);
+}
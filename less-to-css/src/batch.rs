@@ -0,0 +1,355 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use less_ast::{ast::Stylesheets, source::SourceFiles};
+use less_error::diagnostic::Diagnostic;
+use thiserror::Error;
+
+use crate::{
+    import::{Disk, ImportCache, ImportError, Importer},
+    options::CompileOptions,
+    plugin, render_from,
+};
+
+#[derive(Debug, Error)]
+pub enum BatchError {
+    #[error("invalid pattern {0}: {1}")]
+    Pattern(String, glob::PatternError),
+    #[error("{0}: {1}")]
+    Io(PathBuf, io::Error),
+}
+
+/**
+ * compiles every entry point under `input` into `output`, keeping the directory layout
+ * `input` is a directory, searched recursively for `.less` files, or a glob pattern
+ * partials, files whose name starts with `_`, are only compiled when imported
+ */
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    pub input: String,
+    pub output: PathBuf,
+    pub options: CompileOptions,
+    // the number of cores when not set
+    pub threads: Option<usize>,
+}
+
+/// how one entry point went, `result` holds its warnings or the error that stopped it
+#[derive(Debug)]
+pub struct Compiled {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub duration: Duration,
+    pub result: Result<Vec<Diagnostic>, Box<Diagnostic>>,
+    // every file read, with the import chains of this entry, the spans of `result` point into them
+    pub files: SourceFiles,
+}
+
+#[derive(Debug)]
+pub struct Summary {
+    // sorted by input path
+    pub compiled: Vec<Compiled>,
+    pub duration: Duration,
+    pub threads: usize,
+}
+
+impl Summary {
+    pub fn failed(&self) -> usize {
+        self.compiled
+            .iter()
+            .filter(|compiled| compiled.result.is_err())
+            .count()
+    }
+}
+
+// `_mixins.less`
+fn is_partial(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('_'))
+}
+
+// the directories before the first wildcard, `src/**/*.less` -> `src`
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
+fn find_less_files(directory: &Path, files: &mut Vec<PathBuf>) -> Result<(), BatchError> {
+    let error = |error| BatchError::Io(directory.to_path_buf(), error);
+    for entry in fs::read_dir(directory).map_err(error)? {
+        let path = entry.map_err(error)?.path();
+        if path.is_dir() {
+            find_less_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "less")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// the entry points of `input` and the directory their output paths are relative to
+pub fn entries(input: &str) -> Result<(PathBuf, Vec<PathBuf>), BatchError> {
+    let (base, mut files) = if Path::new(input).is_dir() {
+        let mut files = Vec::new();
+        find_less_files(Path::new(input), &mut files)?;
+        (PathBuf::from(input), files)
+    } else {
+        let paths =
+            glob::glob(input).map_err(|error| BatchError::Pattern(input.to_string(), error))?;
+        let files = paths
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect();
+        (glob_base(input), files)
+    };
+    files.retain(|path| !is_partial(path));
+    files.sort();
+    Ok((base, files))
+}
+
+// runs `f` on every item on `threads` threads, the results keep the order of `items`
+fn parallel<T: Sync, R: Send>(items: &[T], threads: usize, f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..threads.min(items.len()) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(index) else {
+                    break;
+                };
+                let result = f(item);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+// an entry point once its imports are inlined
+struct Loaded {
+    input: PathBuf,
+    output: PathBuf,
+    stylesheets: Result<Stylesheets, ImportError>,
+    files: SourceFiles,
+    duration: Duration,
+}
+
+/**
+ * parses every entry in parallel, files imported by several entries are parsed once,
+ * then evaluates and writes them in parallel
+ * stops early only when the entries can't be listed, a failing entry is reported in the summary
+ */
+pub fn compile(batch: &Batch) -> Result<Summary, BatchError> {
+    let start = Instant::now();
+    let threads = batch.threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(usize::from)
+            .unwrap_or(1)
+    });
+    let threads = threads.max(1);
+    let (base, inputs) = entries(&batch.input)?;
+
+    let cache = Arc::new(ImportCache::default());
+    let loaded = parallel(&inputs, threads, |input| {
+        let start = Instant::now();
+        let relative = input.strip_prefix(&base).unwrap_or(input);
+        let mut importer = Importer::with_cache(batch.options.include_paths.clone(), cache.clone())
+            .with_plugins(batch.options.plugins.clone());
+        let stylesheets = importer
+            .load_file(input)
+            .and_then(|stylesheets| importer.apply_variables(stylesheets, &batch.options))
+            .map(|mut stylesheets| {
+                plugin::pre_eval(&batch.options.plugins, &mut stylesheets);
+                stylesheets
+            });
+        Loaded {
+            input: input.clone(),
+            output: batch.output.join(relative).with_extension("css"),
+            stylesheets,
+            // urls and messages follow the imports of this entry, not of the one parsing a file first
+            files: importer.into_files(),
+            duration: start.elapsed(),
+        }
+    });
+
+    let compiled = parallel(&loaded, threads, |loaded| {
+        let start = Instant::now();
+        let files = &loaded.files;
+        let result = match &loaded.stylesheets {
+            Ok(stylesheets) => render_entry(stylesheets, files, &batch.options, &loaded.output),
+            Err(error) => Err(Box::new(error.to_diagnostic(files))),
+        };
+        Compiled {
+            input: loaded.input.clone(),
+            output: loaded.output.clone(),
+            duration: loaded.duration + start.elapsed(),
+            result,
+            files: files.clone(),
+        }
+    });
+    Ok(Summary {
+        compiled,
+        duration: start.elapsed(),
+        threads,
+    })
+}
+
+// writes the css, and the map next to it unless it is inline
+fn render_entry(
+    stylesheets: &Stylesheets,
+    files: &SourceFiles,
+    compile_options: &CompileOptions,
+    output: &Path,
+) -> Result<Vec<Diagnostic>, Box<Diagnostic>> {
+    let mut options = compile_options.clone();
+    let map_path = output.with_extension("css.map");
+    if let Some(source_map) = &mut options.options.source_map {
+        let name = |path: &Path| path.file_name().map(|name| name.to_string_lossy().into());
+        source_map.filename = Some(map_path.display().to_string());
+        source_map.output_filename = name(output);
+        if source_map.url.is_none() {
+            source_map.url = name(&map_path);
+        }
+    }
+    let result = render_from(stylesheets, files, &options, Arc::new(Disk))
+        .map_err(|error| Box::new(error.to_diagnostic()))?;
+    let write = |path: &Path, contents: &str| {
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(path, contents))
+            .map_err(|error| Box::new(Diagnostic::error(format!("{}: {}", path.display(), error))))
+    };
+    write(output, &result.css)?;
+    let inline = options.options.source_map.map(|map| map.inline);
+    if let (Some(map), Some(false)) = (&result.map, inline) {
+        write(&map_path, &map.to_json())?;
+    }
+    Ok(result.warnings)
+}
+
+#[test]
+fn batch() {
+    let directory = std::env::temp_dir().join(format!("less-batch-{}", std::process::id()));
+    let input = directory.join("src");
+    let file = |name: &str, source: &str| {
+        let path = input.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    };
+    file("_vars.less", "@color: red;\n");
+    file("a.less", "@import '_vars';\na { color: @color; }\n");
+    file(
+        "pages/b.less",
+        "@import '../_vars';\nb { color: @color; }\n",
+    );
+    file("pages/c.less", "c { color: @missing; }\n");
+    file("pages/notes.txt", "");
+
+    let (base, found) = entries(input.to_str().unwrap()).unwrap();
+    assert_eq!(base, input);
+    assert_eq!(
+        found,
+        [
+            input.join("a.less"),
+            input.join("pages/b.less"),
+            input.join("pages/c.less")
+        ]
+    );
+    let pattern = format!("{}/pages/*.less", input.display());
+    assert_eq!(entries(&pattern).unwrap().1.len(), 2);
+    assert_eq!(glob_base("src/**/*.less"), PathBuf::from("src"));
+
+    let summary = compile(&Batch {
+        input: input.display().to_string(),
+        output: directory.join("out"),
+        threads: Some(2),
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(summary.compiled.len(), 3);
+    assert_eq!(summary.failed(), 1);
+    assert_eq!(
+        summary.compiled[2].result.as_ref().unwrap_err().message,
+        "variable @missing is undefined"
+    );
+    // _vars.less is parsed once for both entries
+    assert_eq!(
+        summary.compiled[1]
+            .files
+            .iter()
+            .filter(|file| file.name.ends_with("_vars.less"))
+            .count(),
+        1
+    );
+    let css = |name: &str| fs::read_to_string(directory.join("out").join(name)).unwrap();
+    assert!(css("a.css").contains("color: red"));
+    assert!(css("pages/b.css").contains("color: red"));
+    assert!(!directory.join("out/_vars.css").exists());
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn shared_partial_urls() {
+    use crate::options::RewriteUrls;
+
+    let directory = std::env::temp_dir().join(format!("less-batch-urls-{}", std::process::id()));
+    let input = directory.join("src");
+    let file = |name: &str, source: &str| {
+        let path = input.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    };
+    file("shared/_img.less", ".img { background: url(img.png); }\n");
+    file("a.less", "@import 'shared/_img';\n");
+    file("b/c/y.less", "@import '../../shared/_img';\n");
+
+    let mut options = CompileOptions::default();
+    options.options.rewrite_urls = RewriteUrls::All;
+    // one thread, a.less parses the partial and y.less gets it from the cache
+    let summary = compile(&Batch {
+        input: input.display().to_string(),
+        output: directory.join("out"),
+        options,
+        threads: Some(1),
+    })
+    .unwrap();
+    assert_eq!(summary.failed(), 0);
+    let css = |name: &str| fs::read_to_string(directory.join("out").join(name)).unwrap();
+    assert!(css("a.css").contains("url(shared/img.png)"));
+    assert!(css("b/c/y.css").contains("url(../../shared/img.png)"));
+
+    // each entry sees the partial imported from itself
+    for compiled in &summary.compiled {
+        let files = &compiled.files;
+        let partial = files
+            .iter()
+            .find(|file| file.name.ends_with("_img.less"))
+            .unwrap();
+        let entry = files.import_chain(partial.id)[0].file;
+        assert_eq!(Path::new(&files.get(entry).unwrap().name), compiled.input);
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
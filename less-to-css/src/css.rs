@@ -0,0 +1,81 @@
+use less_ast::ast::{Atom, Span};
+
+use crate::value::Value;
+
+/// the evaluated stylesheet, nothing less specific is left in it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stylesheet {
+    pub nodes: Vec<Node>,
+}
+
+impl Stylesheet {
+    /// calls `visit` on every node, a rule or at-rule before the nodes in its block
+    pub fn walk_mut(&mut self, mut visit: impl FnMut(&mut Node)) {
+        walk_mut(&mut self.nodes, &mut visit);
+    }
+}
+
+fn walk_mut(nodes: &mut [Node], visit: &mut dyn FnMut(&mut Node)) {
+    for node in nodes {
+        visit(node);
+        match node {
+            Node::Rule(rule) => walk_mut(&mut rule.declarations, visit),
+            Node::AtRule(AtRule {
+                block: Some(block), ..
+            }) => walk_mut(block, visit),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Rule(Rule),
+    AtRule(AtRule),
+    // only inside an at-rule block: @font-face { src: url(a.ttf) }
+    Declaration(Declaration),
+    Comment(Comment),
+}
+
+/// .a,
+/// .b {
+///   color: red;
+/// }
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub span: Span,
+    pub selectors: Vec<String>,
+    // declarations and the comments between them
+    pub declarations: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub span: Span,
+    pub name: Atom,
+    pub value: Value,
+    pub important: bool,
+}
+
+/// @media screen { } @charset "utf-8";
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtRule {
+    pub span: Span,
+    pub name: Atom,
+    pub prelude: Option<Value>,
+    pub block: Option<Vec<Node>>,
+}
+
+/// a block comment, line comments never reach the css
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub span: Span,
+    pub value: Atom,
+}
+
+impl Comment {
+    // stripped when compressing, except `/*! */`
+    pub fn is_silent(&self, compress: bool) -> bool {
+        compress && !self.value.starts_with("/*!")
+    }
+}
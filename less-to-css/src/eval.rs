@@ -0,0 +1,1102 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use less_ast::ast::{
+    AtRule, Atom, BinaryExpression, BinaryOperator, Comment, CurlyBracketsBlockContent,
+    Declaration, DefinedStatement, Express, FunctionExpression, MixinCall, MixinDefined,
+    PreservedToken, QualifiedRule, Selector, SelectorComponentList, SelectorList, Span,
+    StyleContent, Stylesheets, UrlLiteral, VariableDefinedValue, VariableExpression,
+    VariableValueList,
+};
+use less_ast::source::{FileId, SourceFiles};
+use less_error::{
+    codes::{disabled_codes, Code},
+    diagnostic::{Diagnostic, Label, Severity},
+    ErrorKind, LessError,
+};
+use thiserror::Error;
+
+use crate::{
+    css::{self, Node, Rule, Stylesheet},
+    functions::{get_function, is_css_function, CustomFunction, FunctionError, Functions},
+    import::{Disk, FileSystem},
+    options::{Math, Options},
+    url::{file_rootpath, relative_dir, rewrite_import, rewrite_url},
+    value::{Color, Function, List, Number, Quoted, Separator, Url, Value},
+    wasm::{FuelBudget, WasmPlugin},
+};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EvalError {
+    #[error("variable {0} is undefined")]
+    UndefinedVariable(Atom, Span),
+    #[error("Recursive variable definition for {0}")]
+    // the use and the definition
+    RecursiveVariable(Atom, Span, Span),
+    #[error("{0} is undefined")]
+    UndefinedMixin(Atom, Span),
+    #[error("Operation on an invalid type")]
+    InvalidOperation,
+    #[error("Incompatible units. Change the units or use the unit function. Bad units: '{0}' and '{1}'.")]
+    IncompatibleUnits(Atom, Atom, Span),
+    #[error("Error evaluating function `{0}`: {1}")]
+    Function(Atom, FunctionError, Span),
+    #[error("plugin {0} is not registered")]
+    PluginNotFound(Atom, Span),
+    #[error("plugin {0} failed: {1}")]
+    PluginFailed(Atom, String, Span),
+    #[error("unsupported expression")]
+    Unsupported,
+    // a warning promoted with `Options::warnings_as_errors`
+    #[error("{}", .1.message)]
+    Warning(Code, Box<Diagnostic>),
+}
+
+impl EvalError {
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            EvalError::UndefinedVariable(_, span)
+            | EvalError::RecursiveVariable(_, span, _)
+            | EvalError::UndefinedMixin(_, span)
+            | EvalError::Function(_, _, span)
+            | EvalError::PluginNotFound(_, span)
+            | EvalError::PluginFailed(_, _, span)
+            | EvalError::IncompatibleUnits(_, _, span) => Some(span),
+            EvalError::Warning(_, warning) => warning.primary_span(),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            EvalError::UndefinedVariable(..)
+            | EvalError::RecursiveVariable(..)
+            | EvalError::UndefinedMixin(..) => ErrorKind::Name,
+            EvalError::InvalidOperation | EvalError::IncompatibleUnits(..) => ErrorKind::Syntax,
+            EvalError::Function(..) => ErrorKind::Argument,
+            EvalError::PluginNotFound(..) => ErrorKind::Import,
+            EvalError::PluginFailed(..) | EvalError::Unsupported | EvalError::Warning(..) => {
+                ErrorKind::Runtime
+            }
+        }
+    }
+
+    pub fn code(&self) -> Code {
+        match self {
+            EvalError::UndefinedVariable(..) => Code::UndefinedVariable,
+            EvalError::RecursiveVariable(..) => Code::RecursiveVariable,
+            EvalError::UndefinedMixin(..) => Code::UndefinedMixin,
+            EvalError::InvalidOperation => Code::InvalidOperation,
+            EvalError::IncompatibleUnits(..) => Code::IncompatibleUnits,
+            EvalError::Function(..) => Code::FunctionArgument,
+            EvalError::PluginNotFound(..) => Code::PluginNotFound,
+            EvalError::PluginFailed(..) => Code::PluginFailed,
+            EvalError::Unsupported => Code::Unsupported,
+            EvalError::Warning(code, _) => *code,
+        }
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(self.code());
+        match self {
+            EvalError::UndefinedVariable(name, span) => diagnostic
+                .with_label(Label::primary(span.clone(), "not defined in this scope"))
+                .with_help(format!("define it with `{}: value;`", name)),
+            EvalError::RecursiveVariable(_, span, defined) => diagnostic
+                .with_label(Label::primary(span.clone(), "used while it is evaluated"))
+                .with_label(Label::secondary(defined.clone(), "variable defined here")),
+            EvalError::UndefinedMixin(_, span) => {
+                diagnostic.with_label(Label::primary(span.clone(), "no mixin matches this call"))
+            }
+            EvalError::IncompatibleUnits(_, _, span) => diagnostic
+                .with_label(Label::primary(span.clone(), "units differ"))
+                .with_help("convert one side with `unit()` or turn off --strict-units"),
+            EvalError::Function(_, error, span) => {
+                diagnostic.with_label(Label::primary(span.clone(), error.to_string()))
+            }
+            EvalError::PluginNotFound(_, span) => diagnostic
+                .with_label(Label::primary(span.clone(), "no plugin with this name"))
+                .with_help("register its functions in `Options::function_plugins` or put a `.wasm` module next to the file"),
+            EvalError::PluginFailed(_, _, span) => {
+                diagnostic.with_label(Label::primary(span.clone(), "loaded here"))
+            }
+            EvalError::Warning(_, warning) => Diagnostic {
+                severity: Severity::Error,
+                ..*warning.clone()
+            }
+            .with_note("warnings are treated as errors"),
+            EvalError::InvalidOperation | EvalError::Unsupported => diagnostic,
+        }
+    }
+
+    pub fn to_less_error(&self, files: &SourceFiles) -> LessError {
+        let error = LessError::new(self.kind(), self.to_string());
+        match self.span() {
+            Some(span) => error.with_span(files, span),
+            None => error,
+        }
+    }
+}
+
+enum Binding {
+    // evaluated on first use in the scope it was defined in, the span is the definition
+    Lazy(VariableValueList, Span),
+    Value(Value),
+}
+
+#[derive(Clone)]
+enum Mixin {
+    Defined(MixinDefined),
+    // .a { } can be called as .a();
+    Ruleset(QualifiedRule),
+}
+
+#[derive(Default)]
+struct Frame {
+    variables: HashMap<Atom, Binding>,
+    mixins: Vec<(String, Mixin)>,
+    // added by `@plugin`, the last one wins
+    functions: Vec<Functions>,
+}
+
+// a `.wasm` module loaded by `@plugin`, once per path
+struct LoadedPlugin {
+    path: PathBuf,
+    name: Atom,
+    span: Span,
+    plugin: WasmPlugin,
+}
+
+// declarations (and comments) of the rule being evaluated and the rules nested in it
+#[derive(Default)]
+struct BlockOutput {
+    declarations: Vec<Node>,
+    nested: Vec<Node>,
+}
+
+pub struct Evaluator<'a> {
+    options: &'a Options,
+    frames: Vec<Frame>,
+    // variables currently being evaluated, to detect recursion
+    evaluating: Vec<Atom>,
+    // the files spans point into, urls are rewritten relative to the file they are in
+    files: Option<&'a SourceFiles>,
+    // parentheses around the expression being evaluated
+    parens: usize,
+    warnings: Vec<Diagnostic>,
+    // codes silenced by `// less-disable L0013` comments, per file
+    disabled: HashMap<FileId, Vec<Code>>,
+    wasm_plugins: Vec<LoadedPlugin>,
+    // `@plugin` modules are read from it, next to the file or in an include path as imports are
+    file_system: Arc<dyn FileSystem>,
+    include_paths: Vec<PathBuf>,
+    // the fuel left for the modules of this evaluator
+    wasm_budget: FuelBudget,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(options: &'a Options) -> Self {
+        Self {
+            options,
+            frames: Vec::new(),
+            evaluating: Vec::new(),
+            files: None,
+            parens: 0,
+            warnings: Vec::new(),
+            disabled: HashMap::new(),
+            wasm_plugins: Vec::new(),
+            file_system: Arc::new(Disk),
+            include_paths: Vec::new(),
+            wasm_budget: FuelBudget::new(options.wasm_limits.total_fuel),
+        }
+    }
+
+    pub fn with_files(mut self, files: &'a SourceFiles) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// where `@plugin` modules are read from, the disk by default
+    pub fn with_file_system(
+        mut self,
+        file_system: Arc<dyn FileSystem>,
+        include_paths: Vec<PathBuf>,
+    ) -> Self {
+        self.file_system = file_system;
+        self.include_paths = include_paths;
+        self
+    }
+
+    /// the warnings of the last `eval`, without the ones silenced in their file
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        let disabled = &self.disabled;
+        std::mem::take(&mut self.warnings)
+            .into_iter()
+            .filter(|warning| {
+                let code = warning.code.as_deref().and_then(Code::parse);
+                let file = warning.primary_span().map(|span| span.file);
+                match (code, file.and_then(|file| disabled.get(&file))) {
+                    (Some(code), Some(codes)) => !codes.contains(&code),
+                    _ => true,
+                }
+            })
+            .collect()
+    }
+
+    fn warn(&mut self, code: Code, span: &Span, message: String, label: &str) {
+        let warning = Diagnostic::warning(message)
+            .with_code(code)
+            .with_label(Label::primary(span.clone(), label));
+        self.warnings.push(warning);
+    }
+
+    pub fn eval(&mut self, stylesheets: &Stylesheets) -> Result<Stylesheet, EvalError> {
+        let mut frame = Frame::default();
+        for content in &stylesheets.content {
+            match content {
+                StyleContent::DefinedStatement(statement) => Self::define(&mut frame, statement),
+                StyleContent::QualifiedRule(rule) => Self::define_ruleset(&mut frame, rule),
+                StyleContent::AtRule(at_rule) => self.define_plugin(&mut frame, at_rule)?,
+                _ => {}
+            }
+        }
+        self.frames.push(frame);
+
+        let mut nodes = Vec::new();
+        for content in &stylesheets.content {
+            match content {
+                StyleContent::QualifiedRule(rule) => {
+                    nodes.extend(self.eval_qualified_rule(&[], rule)?);
+                }
+                StyleContent::AtRule(at_rule) => {
+                    nodes.extend(self.eval_at_rule(&[], at_rule)?);
+                }
+                StyleContent::MixinCall(mixin_call) => {
+                    let mut output = BlockOutput::default();
+                    self.eval_mixin_call(&[], mixin_call, &mut output)?;
+                    nodes.extend(output.nested);
+                }
+                StyleContent::Comment(comment) => nodes.extend(self.eval_comment(comment)),
+                StyleContent::DefinedStatement(_) => {}
+            }
+        }
+        self.frames.pop();
+        // css imports must precede all other rules
+        let (imports, rest): (Vec<Node>, Vec<Node>) = nodes
+            .into_iter()
+            .partition(|node| matches!(node, Node::AtRule(at_rule) if at_rule.name.eq_ignore_ascii_case("@import")));
+        let mut nodes = imports;
+        nodes.extend(rest);
+        let mut stylesheet = Stylesheet { nodes };
+        for loaded in &self.wasm_plugins {
+            loaded.plugin.visit(&mut stylesheet).map_err(|error| {
+                EvalError::PluginFailed(loaded.name.clone(), error.to_string(), loaded.span.clone())
+            })?;
+        }
+        if self.options.warnings_as_errors {
+            let warnings = self.take_warnings();
+            if let Some(warning) = warnings.first() {
+                let code = warning.code.as_deref().and_then(Code::parse);
+                let code = code.unwrap_or(Code::Unsupported);
+                return Err(EvalError::Warning(code, Box::new(warning.clone())));
+            }
+            self.warnings = warnings;
+        }
+        Ok(stylesheet)
+    }
+
+    fn define(frame: &mut Frame, statement: &DefinedStatement) {
+        match statement {
+            DefinedStatement::VariableDefined(variable) => {
+                frame.variables.insert(
+                    variable.name.name.clone(),
+                    Binding::Lazy(variable.value.clone(), variable.span.clone()),
+                );
+            }
+            DefinedStatement::MixinDefined(mixin) => {
+                let name = mixin.name.name.trim().to_string();
+                frame.mixins.push((name, Mixin::Defined(mixin.clone())));
+            }
+            DefinedStatement::MapVariableDefined(_) => {}
+        }
+    }
+
+    fn define_ruleset(frame: &mut Frame, rule: &QualifiedRule) {
+        if let [component] = rule.prelude.as_slice() {
+            let name = selector_to_string(component);
+            if name.starts_with('.') || name.starts_with('#') {
+                frame.mixins.push((name, Mixin::Ruleset(rule.clone())));
+            }
+        }
+    }
+
+    // @plugin "brand"; makes its functions callable in the whole block, like a variable
+    fn define_plugin(&mut self, frame: &mut Frame, at_rule: &AtRule) -> Result<(), EvalError> {
+        if !is_plugin(at_rule) {
+            return Ok(());
+        }
+        // @plugin (option) "brand";
+        let name = match self.eval_value_list(&at_rule.prelude)?.0 {
+            Value::List(list) => list.items.into_iter().rev().find_map(plugin_name),
+            value => plugin_name(value),
+        };
+        let Some(name) = name else {
+            return Err(EvalError::PluginNotFound(
+                String::new(),
+                at_rule.span.clone(),
+            ));
+        };
+        let registered = Path::new(&name).file_stem().and_then(|stem| {
+            let stem = stem.to_str()?;
+            let stem = stem.strip_suffix(".js").unwrap_or(stem);
+            let plugins = &self.options.function_plugins;
+            plugins.get(&name).or_else(|| plugins.get(stem))
+        });
+        let functions = match registered {
+            Some(functions) => functions.clone(),
+            None => self.load_wasm_plugin(&name, at_rule)?,
+        };
+        frame.functions.push(functions);
+        Ok(())
+    }
+
+    // @plugin "brand.wasm"; or "brand", relative to the file it is in, then in every include path
+    fn load_wasm_plugin(&mut self, name: &str, at_rule: &AtRule) -> Result<Functions, EvalError> {
+        let file = self.files.and_then(|files| files.get(at_rule.span.file));
+        let directory = file
+            .and_then(|file| Path::new(&file.name).parent())
+            .unwrap_or(Path::new(""));
+        let path = std::iter::once(directory)
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .flat_map(|directory| {
+                [
+                    directory.join(name),
+                    directory.join(format!("{}.wasm", name)),
+                ]
+            })
+            .find(|path| self.file_system.is_file(path))
+            .ok_or_else(|| EvalError::PluginNotFound(name.to_string(), at_rule.span.clone()))?;
+        let path = self.file_system.canonicalize(&path);
+        if let Some(loaded) = self.wasm_plugins.iter().find(|loaded| loaded.path == path) {
+            return Ok(loaded.plugin.functions.clone());
+        }
+        let failed =
+            |error: String| EvalError::PluginFailed(name.to_string(), error, at_rule.span.clone());
+        let wasm = self
+            .file_system
+            .read_bytes(&path)
+            .map_err(|error| failed(error.to_string()))?;
+        let plugin = WasmPlugin::load(&wasm, &self.options.wasm_limits, &self.wasm_budget)
+            .map_err(|error| failed(error.to_string()))?;
+        let functions = plugin.functions.clone();
+        self.wasm_plugins.push(LoadedPlugin {
+            path,
+            name: name.to_string(),
+            span: at_rule.span.clone(),
+            plugin,
+        });
+        Ok(functions)
+    }
+
+    fn eval_qualified_rule(
+        &mut self,
+        parents: &[String],
+        rule: &QualifiedRule,
+    ) -> Result<Vec<Node>, EvalError> {
+        let selectors = join_selectors(parents, &rule.prelude);
+        let output = self.eval_block(&selectors, &rule.block.content)?;
+        let mut nodes = Vec::new();
+        if !output.declarations.is_empty() {
+            nodes.push(Node::Rule(Rule {
+                span: rule.span.clone(),
+                selectors,
+                declarations: output.declarations,
+            }));
+        }
+        nodes.extend(output.nested);
+        Ok(nodes)
+    }
+
+    fn eval_block(
+        &mut self,
+        selectors: &[String],
+        content: &[CurlyBracketsBlockContent],
+    ) -> Result<BlockOutput, EvalError> {
+        let mut frame = Frame::default();
+        for item in content {
+            match item {
+                CurlyBracketsBlockContent::DefinedStatement(statement) => {
+                    Self::define(&mut frame, statement)
+                }
+                CurlyBracketsBlockContent::QualifiedRule(rule) => {
+                    Self::define_ruleset(&mut frame, rule)
+                }
+                // a variable after a declaration is parsed as a declaration: a: 1; @b: 2;
+                CurlyBracketsBlockContent::DeclarationList(declarations) => {
+                    for declaration in declarations.iter().filter(|d| d.name.starts_with('@')) {
+                        frame.variables.insert(
+                            declaration.name.clone(),
+                            Binding::Lazy(declaration.value.clone(), declaration.span.clone()),
+                        );
+                    }
+                }
+                CurlyBracketsBlockContent::AtRule(at_rule) => {
+                    self.define_plugin(&mut frame, at_rule)?
+                }
+                _ => {}
+            }
+        }
+        self.frames.push(frame);
+        let mut output = BlockOutput::default();
+        let result = self.eval_block_content(selectors, content, &mut output);
+        self.frames.pop();
+        result.map(|_| output)
+    }
+
+    fn eval_block_content(
+        &mut self,
+        selectors: &[String],
+        content: &[CurlyBracketsBlockContent],
+        output: &mut BlockOutput,
+    ) -> Result<(), EvalError> {
+        for item in content {
+            match item {
+                CurlyBracketsBlockContent::DeclarationList(declarations) => {
+                    for declaration in declarations.iter().filter(|d| !d.name.starts_with('@')) {
+                        let declaration = self.eval_declaration(declaration)?;
+                        output.declarations.push(Node::Declaration(declaration));
+                    }
+                }
+                CurlyBracketsBlockContent::QualifiedRule(rule) => {
+                    let nodes = self.eval_qualified_rule(selectors, rule)?;
+                    output.nested.extend(nodes);
+                }
+                CurlyBracketsBlockContent::AtRule(at_rule) => {
+                    let nodes = self.eval_at_rule(selectors, at_rule)?;
+                    output.nested.extend(nodes);
+                }
+                CurlyBracketsBlockContent::MixinCall(mixin_call) => {
+                    self.eval_mixin_call(selectors, mixin_call, output)?;
+                }
+                CurlyBracketsBlockContent::Comment(comment) => {
+                    output.declarations.extend(self.eval_comment(comment));
+                }
+                CurlyBracketsBlockContent::DefinedStatement(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_at_rule(
+        &mut self,
+        selectors: &[String],
+        at_rule: &AtRule,
+    ) -> Result<Vec<Node>, EvalError> {
+        if is_plugin(at_rule) {
+            return Ok(Vec::new());
+        }
+        let mut prelude = if at_rule.prelude.iter().all(is_whitespace) {
+            None
+        } else {
+            Some(self.eval_value_list(&at_rule.prelude)?.0)
+        };
+        if let Some(prelude) = &mut prelude {
+            if self.is_missing_optional_import(at_rule, prelude) {
+                return Ok(Vec::new());
+            }
+            if at_rule.name.name.eq_ignore_ascii_case("@import") {
+                self.rewrite_import(prelude, &at_rule.span);
+            }
+        }
+        let block = match &at_rule.block {
+            Some(block) => {
+                let output = self.eval_block(selectors, &block.content)?;
+                let mut nodes = Vec::new();
+                if selectors.is_empty() {
+                    nodes.extend(output.declarations);
+                } else if !output.declarations.is_empty() {
+                    // @media inside a rule bubbles up and wraps the rule
+                    nodes.push(Node::Rule(Rule {
+                        span: at_rule.span.clone(),
+                        selectors: selectors.to_vec(),
+                        declarations: output.declarations,
+                    }));
+                }
+                nodes.extend(output.nested);
+                // @media print { }
+                if nodes.is_empty() {
+                    return Ok(Vec::new());
+                }
+                Some(nodes)
+            }
+            None => None,
+        };
+        Ok(vec![Node::AtRule(css::AtRule {
+            span: at_rule.span.clone(),
+            name: at_rule.name.name.clone(),
+            prelude,
+            block,
+        })])
+    }
+
+    /**
+     * @import (optional) "missing.less"; is dropped with a warning
+     * the `Importer` resolved it with its include paths and file system, an optional import it left
+     * in place wasn't found, without `files` the stylesheet wasn't loaded by an importer
+     */
+    fn is_missing_optional_import(&mut self, at_rule: &AtRule, prelude: &Value) -> bool {
+        if !at_rule.name.name.eq_ignore_ascii_case("@import") || self.files.is_none() {
+            return false;
+        }
+        let prelude = prelude.to_string();
+        let Some((options, path)) = prelude
+            .trim()
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(')'))
+        else {
+            return false;
+        };
+        if !options.split(',').any(|option| option.trim() == "optional") {
+            return false;
+        }
+        let path = path
+            .trim()
+            .trim_start_matches("url(")
+            .trim_end_matches(')')
+            .trim_matches(|ch| ch == '"' || ch == '\'');
+        self.warn(
+            Code::MissingOptionalImport,
+            &at_rule.span,
+            format!("skipped optional import of missing file `{}`", path),
+            "file not found",
+        );
+        true
+    }
+
+    // @import "a.css"; the path is relative to the file the import is in, `url()` is rewritten already
+    fn rewrite_import(&self, prelude: &mut Value, span: &Span) {
+        let path = match prelude {
+            Value::List(list) => list.items.first_mut(),
+            value => Some(value),
+        };
+        if let Some(Value::Quoted(quoted)) = path {
+            quoted.value = rewrite_import(&quoted.value, &self.rootpath(span));
+        }
+    }
+
+    fn find_mixins(&self, name: &str) -> Vec<Mixin> {
+        for frame in self.frames.iter().rev() {
+            let mixins: Vec<Mixin> = frame
+                .mixins
+                .iter()
+                .filter(|(mixin_name, _)| mixin_name == name)
+                .map(|(_, mixin)| mixin.clone())
+                .collect();
+            if !mixins.is_empty() {
+                return mixins;
+            }
+        }
+        Vec::new()
+    }
+
+    fn eval_mixin_call(
+        &mut self,
+        selectors: &[String],
+        mixin_call: &MixinCall,
+        output: &mut BlockOutput,
+    ) -> Result<(), EvalError> {
+        let name = selector_to_string(&mixin_call.name);
+        let args = match &mixin_call.params {
+            Some(params) => self.eval_args(params)?,
+            None => Vec::new(),
+        };
+        let mut matched = false;
+        for mixin in self.find_mixins(&name) {
+            match mixin {
+                Mixin::Defined(mixin) => {
+                    if args.len() > mixin.params.len() {
+                        continue;
+                    }
+                    let mut frame = Frame::default();
+                    for (index, param) in mixin.params.iter().enumerate() {
+                        let binding = match (args.get(index), &param.default_params) {
+                            (Some(arg), _) => Binding::Value(arg.clone()),
+                            (None, Some(default)) => {
+                                Binding::Lazy(default.clone(), param.span.clone())
+                            }
+                            (None, None) => break,
+                        };
+                        frame.variables.insert(param.name.name.clone(), binding);
+                    }
+                    if frame.variables.len() != mixin.params.len() {
+                        continue;
+                    }
+                    matched = true;
+                    self.frames.push(frame);
+                    let result = self.eval_block(selectors, &mixin.block.content);
+                    self.frames.pop();
+                    let result = result?;
+                    output.declarations.extend(result.declarations);
+                    output.nested.extend(result.nested);
+                }
+                Mixin::Ruleset(rule) => {
+                    if !args.is_empty() {
+                        continue;
+                    }
+                    matched = true;
+                    let result = self.eval_block(selectors, &rule.block.content)?;
+                    output.declarations.extend(result.declarations);
+                    output.nested.extend(result.nested);
+                }
+            }
+        }
+        if !matched {
+            return Err(EvalError::UndefinedMixin(name, mixin_call.span.clone()));
+        }
+        Ok(())
+    }
+
+    fn eval_declaration(
+        &mut self,
+        declaration: &Declaration,
+    ) -> Result<css::Declaration, EvalError> {
+        let (value, important) = self.eval_value_list(&declaration.value)?;
+        Ok(css::Declaration {
+            span: declaration.span.clone(),
+            name: declaration.name.clone(),
+            value,
+            important,
+        })
+    }
+
+    fn lookup_variable(&mut self, name: &str, span: &Span) -> Result<Value, EvalError> {
+        let Some(index) = self
+            .frames
+            .iter()
+            .rposition(|frame| frame.variables.contains_key(name))
+        else {
+            return Err(EvalError::UndefinedVariable(name.to_string(), span.clone()));
+        };
+        let (value_list, defined) = match &self.frames[index].variables[name] {
+            Binding::Value(value) => return Ok(value.clone()),
+            Binding::Lazy(value_list, defined) => (value_list.clone(), defined.clone()),
+        };
+        if self.evaluating.iter().any(|evaluating| evaluating == name) {
+            return Err(EvalError::RecursiveVariable(
+                name.to_string(),
+                span.clone(),
+                defined,
+            ));
+        }
+        // evaluate in the scope the variable was defined in
+        let inner_frames = self.frames.split_off(index + 1);
+        self.evaluating.push(name.to_string());
+        let result = self.eval_value_list(&value_list);
+        self.evaluating.pop();
+        self.frames.extend(inner_frames);
+        Ok(result?.0)
+    }
+
+    /// split a function or mixin argument list on commas
+    fn eval_args(&mut self, params: &VariableValueList) -> Result<Vec<Value>, EvalError> {
+        let mut args = Vec::new();
+        for group in params.split(is_comma) {
+            if group.iter().all(is_whitespace) {
+                continue;
+            }
+            args.push(self.eval_space_list(group)?);
+        }
+        Ok(args)
+    }
+
+    /// `1px solid red, blue !important` -> (list, important)
+    pub fn eval_value_list(
+        &mut self,
+        value_list: &VariableValueList,
+    ) -> Result<(Value, bool), EvalError> {
+        let important = value_list
+            .iter()
+            .any(|value| matches!(value, VariableDefinedValue::Important(_)));
+        let mut groups = Vec::new();
+        for group in value_list.split(is_comma) {
+            groups.push(self.eval_space_list(group)?);
+        }
+        let value = if groups.len() == 1 {
+            groups.pop().unwrap()
+        } else {
+            Value::List(List {
+                items: groups,
+                separator: Separator::Comma,
+            })
+        };
+        Ok((value, important))
+    }
+
+    fn eval_space_list(&mut self, values: &[VariableDefinedValue]) -> Result<Value, EvalError> {
+        let mut items = Vec::new();
+        for value in values {
+            if is_whitespace(value)
+                || matches!(value, VariableDefinedValue::Important(_))
+                || matches!(value, VariableDefinedValue::Comment(comment) if comment.is_line)
+            {
+                continue;
+            }
+            items.push(self.eval_value(value)?);
+        }
+        if items.len() == 1 {
+            return Ok(items.pop().unwrap());
+        }
+        Ok(Value::List(List {
+            items,
+            separator: Separator::Space,
+        }))
+    }
+
+    fn eval_value(&mut self, value: &VariableDefinedValue) -> Result<Value, EvalError> {
+        match value {
+            VariableDefinedValue::Express(express) => self.eval_express(express),
+            VariableDefinedValue::Ident(ident) | VariableDefinedValue::Important(ident) => {
+                Ok(Value::Keyword(ident.name.clone()))
+            }
+            VariableDefinedValue::PreservedToken(token) => self.eval_preserved_token(token),
+            VariableDefinedValue::Comment(comment) => Ok(Value::Comment(comment.value.clone())),
+        }
+    }
+
+    fn eval_preserved_token(&mut self, token: &PreservedToken) -> Result<Value, EvalError> {
+        match token {
+            PreservedToken::Ident(ident) => Ok(Value::Keyword(ident.name.clone())),
+            PreservedToken::AtKeyword(keyword) => {
+                self.lookup_variable(&keyword.name, &keyword.span)
+            }
+            PreservedToken::String(string) => {
+                let quoted = Quoted::from_raw(&string.value, false);
+                Ok(Value::Quoted(self.interpolate(quoted, &string.span)?))
+            }
+            PreservedToken::Number(number) => Ok(Value::Number(Number {
+                value: number.value,
+                unit: number.unit.clone(),
+            })),
+            PreservedToken::Url(url) => Ok(Value::Url(self.eval_url(url))),
+            PreservedToken::Token(token) => Ok(Value::Keyword(token.name.clone())),
+        }
+    }
+
+    fn eval_url(&self, url: &UrlLiteral) -> Url {
+        Url {
+            value: rewrite_url(
+                &url.value,
+                url.quote.is_some(),
+                &self.rootpath(&url.span),
+                self.options,
+            ),
+            quote: url.quote,
+        }
+    }
+
+    // the directory of the file `span` is in, relative to the file that imported it first
+    fn rootpath(&self, span: &Span) -> String {
+        let Some(files) = self.files else {
+            return file_rootpath(self.options, Path::new(""));
+        };
+        let Some(file) = files.get(span.file) else {
+            return file_rootpath(self.options, Path::new(""));
+        };
+        let entry = match files.import_chain(span.file).last() {
+            Some(from) => files.get(from.file).unwrap_or(file),
+            None => file,
+        };
+        let directory = relative_dir(Path::new(&entry.name), Path::new(&file.name));
+        file_rootpath(self.options, &directory)
+    }
+
+    /// replace every `@{name}` until nothing is left to replace
+    fn interpolate(&mut self, mut quoted: Quoted, span: &Span) -> Result<Quoted, EvalError> {
+        loop {
+            let mut result = String::with_capacity(quoted.value.len());
+            let mut rest = quoted.value.as_str();
+            while let Some(start) = rest.find("@{") {
+                let name_len = rest[start + 2..]
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '-' || ch == '_'))
+                    .unwrap_or(rest.len() - start - 2);
+                let end = start + 2 + name_len;
+                if name_len == 0 || !rest[end..].starts_with('}') {
+                    result.push_str(&rest[..start + 2]);
+                    rest = &rest[start + 2..];
+                    continue;
+                }
+                result.push_str(&rest[..start]);
+                let name = format!("@{}", &rest[start + 2..end]);
+                match self.lookup_variable(&name, span)? {
+                    Value::Quoted(value) => result.push_str(&value.value),
+                    value => result.push_str(&value.to_string()),
+                }
+                rest = &rest[end + 1..];
+            }
+            result.push_str(rest);
+            if result == quoted.value {
+                return Ok(quoted);
+            }
+            quoted.value = result;
+        }
+    }
+
+    fn eval_express(&mut self, express: &Express) -> Result<Value, EvalError> {
+        match express {
+            Express::BinaryExpression(binary) => self.eval_binary_expression(binary),
+            Express::FunctionExpression(function) => self.eval_function(function),
+            Express::MixinCall(_) => Err(EvalError::Unsupported),
+            Express::VariableExpression(variable) => match variable {
+                VariableExpression::Variable(keyword) => {
+                    self.lookup_variable(&keyword.name, &keyword.span)
+                }
+                VariableExpression::Color(color) => Ok(Value::Color(
+                    Color::from_hex(&color.value).ok_or(EvalError::Unsupported)?,
+                )),
+                VariableExpression::PreservedToken(token) => self.eval_preserved_token(token),
+                VariableExpression::MapVariable(_) => Err(EvalError::Unsupported),
+            },
+            Express::ParenthesesExpression(parentheses) => {
+                self.parens += 1;
+                let value = self.eval_express(&parentheses.express);
+                self.parens -= 1;
+                value
+            }
+            Express::StringEscape(string) => {
+                let quoted = Quoted::from_raw(&string.value, true);
+                Ok(Value::Quoted(self.interpolate(quoted, &string.span)?))
+            }
+        }
+    }
+
+    fn eval_function(&mut self, function: &FunctionExpression) -> Result<Value, EvalError> {
+        let name = &function.name.name;
+        let args = self.eval_args(&function.params)?;
+        if let Some(custom) = self.custom_function(name) {
+            return custom(&args)
+                .map_err(|error| EvalError::Function(name.clone(), error, function.span.clone()));
+        }
+        if name.eq_ignore_ascii_case("url") {
+            // url(@var)
+            let url = match args.first() {
+                Some(Value::Quoted(quoted)) => UrlLiteral {
+                    span: function.name.span.clone(),
+                    value: quoted.value.clone(),
+                    quote: quoted.quote,
+                },
+                Some(value) => UrlLiteral {
+                    span: function.name.span.clone(),
+                    value: value.to_string(),
+                    quote: None,
+                },
+                None => return Err(EvalError::Unsupported),
+            };
+            return Ok(Value::Url(self.eval_url(&url)));
+        }
+        if name.eq_ignore_ascii_case("e") {
+            self.warn(
+                Code::DeprecatedSyntax,
+                &function.span,
+                "`e()` is deprecated".to_string(),
+                "use the `~\"...\"` escape instead",
+            );
+        }
+        match get_function(name) {
+            Some(builtin) => builtin(&args)
+                .map_err(|error| EvalError::Function(name.clone(), error, function.span.clone())),
+            None => {
+                if !is_css_function(name) {
+                    self.warn(
+                        Code::UnknownFunction,
+                        &function.span,
+                        format!("unknown function `{}` is printed as is", name),
+                        "not a less builtin or css function",
+                    );
+                }
+                Ok(Value::Function(Function {
+                    name: name.clone(),
+                    args,
+                }))
+            }
+        }
+    }
+
+    // from the innermost `@plugin` to the ones of the options
+    fn custom_function(&self, name: &str) -> Option<CustomFunction> {
+        self.frames
+            .iter()
+            .rev()
+            .flat_map(|frame| frame.functions.iter().rev())
+            .chain([&self.options.functions])
+            .find_map(|functions| functions.get(name))
+            .cloned()
+    }
+
+    fn eval_binary_expression(&mut self, binary: &BinaryExpression) -> Result<Value, EvalError> {
+        let left = self.eval_express(&binary.left)?;
+        let right = self.eval_express(&binary.right)?;
+        let is_division = binary.operator == BinaryOperator::Div;
+        let evaluated = match self.options.math {
+            Math::Always => true,
+            Math::ParensDivision => !is_division || self.parens > 0,
+            Math::Parens => self.parens > 0,
+        };
+        if !evaluated {
+            // 12px/1.5 keeps its spelling, 10px / 2 its spaces
+            let spaced = binary.right.span().start > binary.left.span().end + 1;
+            let operator = operator_str(&binary.operator);
+            return Ok(Value::Keyword(if spaced {
+                format!("{} {} {}", left, operator, right)
+            } else {
+                format!("{}{}{}", left, operator, right)
+            }));
+        }
+        if let (true, Value::Number(left), Value::Number(right)) =
+            (self.options.strict_units, &left, &right)
+        {
+            if let (Some(left_unit), Some(right_unit)) = (&left.unit, &right.unit) {
+                let is_additive = matches!(
+                    binary.operator,
+                    BinaryOperator::Plus | BinaryOperator::Minus
+                );
+                if is_additive && left_unit != right_unit {
+                    return Err(EvalError::IncompatibleUnits(
+                        left_unit.clone(),
+                        right_unit.clone(),
+                        binary.span.clone(),
+                    ));
+                }
+            }
+        }
+        if is_division && self.parens == 0 {
+            self.warn(
+                Code::DivisionOutsideParens,
+                &binary.span,
+                "division outside parentheses".to_string(),
+                "evaluated as a division",
+            );
+        }
+        operate(&binary.operator, left, right)
+    }
+
+    // line comments are dropped, `less-disable` directives apply to the whole file
+    fn eval_comment(&mut self, comment: &Comment) -> Option<Node> {
+        let codes = disabled_codes(&comment.value);
+        if !codes.is_empty() {
+            self.disabled
+                .entry(comment.span.file)
+                .or_default()
+                .extend(codes);
+        }
+        (!comment.is_line).then(|| {
+            Node::Comment(css::Comment {
+                span: comment.span.clone(),
+                value: comment.value.clone(),
+            })
+        })
+    }
+}
+
+fn operator_str(operator: &BinaryOperator) -> &'static str {
+    match operator {
+        BinaryOperator::Plus => "+",
+        BinaryOperator::Minus => "-",
+        BinaryOperator::Mul => "*",
+        BinaryOperator::Div => "/",
+    }
+}
+
+fn operate_number(operator: &BinaryOperator, left: f64, right: f64) -> f64 {
+    match operator {
+        BinaryOperator::Plus => left + right,
+        BinaryOperator::Minus => left - right,
+        BinaryOperator::Mul => left * right,
+        BinaryOperator::Div => left / right,
+    }
+}
+
+fn operate(operator: &BinaryOperator, left: Value, right: Value) -> Result<Value, EvalError> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => Ok(Value::Number(Number {
+            value: operate_number(operator, left.value, right.value),
+            unit: left.unit.or(right.unit),
+        })),
+        (Value::Color(left), Value::Color(right)) => Ok(Value::Color(Color {
+            rgb: [0, 1, 2].map(|i| operate_number(operator, left.rgb[i], right.rgb[i])),
+            alpha: left.alpha,
+            raw: None,
+        })),
+        (Value::Color(color), Value::Number(number))
+        | (Value::Number(number), Value::Color(color)) => Ok(Value::Color(Color {
+            rgb: color
+                .rgb
+                .map(|channel| operate_number(operator, channel, number.value)),
+            alpha: color.alpha,
+            raw: None,
+        })),
+        _ => Err(EvalError::InvalidOperation),
+    }
+}
+
+fn is_comma(value: &VariableDefinedValue) -> bool {
+    matches!(value, VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) if token.name == ",")
+}
+
+fn is_plugin(at_rule: &AtRule) -> bool {
+    at_rule.name.name.eq_ignore_ascii_case("@plugin")
+}
+
+fn plugin_name(value: Value) -> Option<String> {
+    match value {
+        Value::Quoted(quoted) => Some(quoted.value),
+        _ => None,
+    }
+}
+
+fn is_whitespace(value: &VariableDefinedValue) -> bool {
+    matches!(value, VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) if token.name.trim().is_empty())
+}
+
+fn selector_to_string(component: &SelectorComponentList) -> String {
+    let mut result = String::new();
+    for selector in component {
+        match selector {
+            Selector::ParentSelector(_) => result.push('&'),
+            Selector::SimpleSelector(simple) => result.push_str(&simple.name),
+            Selector::PseudoSelector(pseudo) => match pseudo {
+                less_ast::ast::PseudoSelector::PseudoFunction(function) => {
+                    result.push_str(&function.name)
+                }
+                less_ast::ast::PseudoSelector::PseudoElement(element) => {
+                    result.push_str(&element.name)
+                }
+            },
+        }
+    }
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// .a, .b { &:hover, .c {} } -> .a:hover, .b:hover, .a .c, .b .c
+fn join_selectors(parents: &[String], prelude: &SelectorList) -> Vec<String> {
+    let children: Vec<String> = prelude.iter().map(selector_to_string).collect();
+    if parents.is_empty() {
+        return children
+            .into_iter()
+            .map(|child| child.replace('&', "").trim().to_string())
+            .collect();
+    }
+    let mut result = Vec::new();
+    for child in &children {
+        for parent in parents {
+            if child.contains('&') {
+                result.push(child.replace('&', parent));
+            } else {
+                result.push(format!("{} {}", parent, child));
+            }
+        }
+    }
+    result
+}
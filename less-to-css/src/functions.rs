@@ -0,0 +1,558 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use thiserror::Error;
+
+use crate::value::{Color, List, Number, Quoted, Separator, Value};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum FunctionError {
+    #[error("expected {expected} arguments, found {found}")]
+    ArgumentCount { expected: usize, found: usize },
+    #[error("argument must be a string")]
+    ExpectedString,
+    #[error("argument must be a number")]
+    ExpectedNumber,
+    #[error("argument must be a color")]
+    ExpectedColor,
+    // returned by registered functions, shown as is
+    #[error("{0}")]
+    Message(String),
+}
+
+pub type BuiltinFunction = fn(&[Value]) -> Result<Value, FunctionError>;
+
+pub fn get_function(name: &str) -> Option<BuiltinFunction> {
+    let function: BuiltinFunction = match name.to_ascii_lowercase().as_str() {
+        "e" => e,
+        "%" => format,
+        "escape" => escape,
+        "rgb" => rgb,
+        "rgba" => rgba,
+        _ => return None,
+    };
+    Some(function)
+}
+
+// functions of css itself, printed as they are without a warning
+const CSS_FUNCTIONS: &[&str] = &[
+    "alpha",
+    "attr",
+    "blur",
+    "brightness",
+    "calc",
+    "clamp",
+    "color",
+    "color-mix",
+    "conic-gradient",
+    "contrast",
+    "counter",
+    "counters",
+    "cross-fade",
+    "cubic-bezier",
+    "drop-shadow",
+    "element",
+    "env",
+    "expression",
+    "fit-content",
+    "format",
+    "grayscale",
+    "hsl",
+    "hsla",
+    "hue-rotate",
+    "hwb",
+    "image",
+    "image-set",
+    "invert",
+    "lab",
+    "lch",
+    "linear-gradient",
+    "local",
+    "matrix",
+    "matrix3d",
+    "max",
+    "min",
+    "minmax",
+    "oklab",
+    "oklch",
+    "opacity",
+    "perspective",
+    "radial-gradient",
+    "repeat",
+    "repeating-conic-gradient",
+    "repeating-linear-gradient",
+    "repeating-radial-gradient",
+    "rotate",
+    "rotate3d",
+    "rotatex",
+    "rotatey",
+    "rotatez",
+    "saturate",
+    "scale",
+    "scale3d",
+    "scalex",
+    "scaley",
+    "scalez",
+    "sepia",
+    "skew",
+    "skewx",
+    "skewy",
+    "steps",
+    "translate",
+    "translate3d",
+    "translatex",
+    "translatey",
+    "translatez",
+    "url",
+    "var",
+];
+
+/// a css function, or a vendor prefixed one like `-webkit-gradient`
+pub fn is_css_function(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with('-') || CSS_FUNCTIONS.contains(&name.as_str())
+}
+
+/// a function registered with `Functions`, called with the evaluated arguments
+pub type CustomFunction = Arc<dyn Fn(&[Value]) -> Result<Value, FunctionError> + Send + Sync>;
+
+/**
+ * native functions by name, they take precedence over the builtins of the same name
+ *
+ * ```
+ * use less_to_css::{functions::Functions, value::Number};
+ *
+ * let mut functions = Functions::new();
+ * functions.register("rem", |px: Number| {
+ *     Ok(Number {
+ *         value: px.value / 16.0,
+ *         unit: Some("rem".to_string()),
+ *     })
+ * });
+ * assert!(functions.get("REM").is_some());
+ * ```
+ */
+#[derive(Clone, Default)]
+pub struct Functions {
+    functions: HashMap<String, CustomFunction>,
+}
+
+impl Functions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a function taking up to 5 arguments of types implementing `FromValue`,
+    /// called with another number of arguments it fails with `ArgumentCount`
+    pub fn register<Args>(&mut self, name: &str, function: impl IntoFunction<Args>) -> &mut Self {
+        self.functions
+            .insert(name.to_ascii_lowercase(), function.into_function());
+        self
+    }
+
+    /// a function getting the arguments as they are, for optional or repeated arguments
+    pub fn register_variadic(
+        &mut self,
+        name: &str,
+        function: impl Fn(&[Value]) -> Result<Value, FunctionError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.functions
+            .insert(name.to_ascii_lowercase(), Arc::new(function));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CustomFunction> {
+        self.functions.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+impl fmt::Debug for Functions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.functions.keys().collect();
+        names.sort();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+/**
+ * an argument of a registered function converted from its evaluated value,
+ * take a `Value` for anything else.
+ * there is no impl for detached rulesets or mixins: the parser doesn't read `@r: { ... }`
+ * and `Value` has no variant for a ruleset, so a call never has one to pass
+ */
+pub trait FromValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, FunctionError>;
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromValue for Number {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        match value {
+            Value::Number(number) => Ok(number.clone()),
+            _ => Err(FunctionError::ExpectedNumber),
+        }
+    }
+}
+
+/// the number without its unit
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        Number::from_value(value).map(|number| number.value)
+    }
+}
+
+impl FromValue for Color {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        match value {
+            Value::Color(color) => Ok(color.clone()),
+            _ => Err(FunctionError::ExpectedColor),
+        }
+    }
+}
+
+impl FromValue for Quoted {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        match value {
+            Value::Quoted(quoted) => Ok(quoted.clone()),
+            _ => Err(FunctionError::ExpectedString),
+        }
+    }
+}
+
+/// the content of a quoted string or a keyword: brand-color(primary)
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        match value {
+            Value::Quoted(quoted) => Ok(quoted.value.clone()),
+            Value::Keyword(keyword) => Ok(keyword.clone()),
+            _ => Err(FunctionError::ExpectedString),
+        }
+    }
+}
+
+/// a single value is a list of one item, as in less.js
+impl FromValue for List {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        match value {
+            Value::List(list) => Ok(list.clone()),
+            _ => Ok(List {
+                items: vec![value.clone()],
+                separator: Separator::Space,
+            }),
+        }
+    }
+}
+
+impl FromValue for Vec<Value> {
+    fn from_value(value: &Value) -> Result<Self, FunctionError> {
+        List::from_value(value).map(|list| list.items)
+    }
+}
+
+/// a closure that `Functions::register` accepts, `Args` is the tuple of its arguments
+pub trait IntoFunction<Args> {
+    fn into_function(self) -> CustomFunction;
+}
+
+macro_rules! into_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> IntoFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, FunctionError> + Send + Sync + 'static,
+            R: Into<Value>,
+            $($arg: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn into_function(self) -> CustomFunction {
+                Arc::new(move |args: &[Value]| {
+                    expect_args(args, <[&str]>::len(&[$(stringify!($arg)),*]))?;
+                    let mut args = args.iter();
+                    $(let $arg = $arg::from_value(args.next().unwrap())?;)*
+                    self($($arg),*).map(Into::into)
+                })
+            }
+        }
+    };
+}
+
+into_function!();
+into_function!(A);
+into_function!(A, B);
+into_function!(A, B, C);
+into_function!(A, B, C, D);
+into_function!(A, B, C, D, E);
+
+fn expect_args(args: &[Value], expected: usize) -> Result<(), FunctionError> {
+    if args.len() != expected {
+        return Err(FunctionError::ArgumentCount {
+            expected,
+            found: args.len(),
+        });
+    }
+    Ok(())
+}
+
+// the value of a quoted string, or the css of anything else
+fn string_value(value: &Value) -> String {
+    match value {
+        Value::Quoted(quoted) => quoted.value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn percent_encode(value: &str, keep: fn(char) -> bool) -> String {
+    let mut result = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if ch.is_ascii_alphanumeric() || keep(ch) {
+            result.push(ch);
+            continue;
+        }
+        let mut buf = [0; 4];
+        for byte in ch.encode_utf8(&mut buf).bytes() {
+            result.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    result
+}
+
+// encodeURIComponent
+fn encode_uri_component(value: &str) -> String {
+    percent_encode(value, |ch| {
+        matches!(ch, '-' | '_' | '.' | '!' | '~' | '*' | '\'' | '(' | ')')
+    })
+}
+
+/// e("-Some::weird(#thing, y)") -> -Some::weird(#thing, y)
+fn e(args: &[Value]) -> Result<Value, FunctionError> {
+    expect_args(args, 1)?;
+    Ok(Value::Quoted(Quoted {
+        value: string_value(&args[0]),
+        quote: Some('"'),
+        escaped: true,
+    }))
+}
+
+/// %("rgb(%d, %d, %d)", @r, 128, 64) -> "rgb(32, 128, 64)"
+/// %s inserts the value of a string, %d and %a the css of the argument,
+/// upper case placeholders are url encoded
+fn format(args: &[Value]) -> Result<Value, FunctionError> {
+    let Some(Value::Quoted(string)) = args.first() else {
+        return Err(FunctionError::ExpectedString);
+    };
+    let mut result = string.value.clone();
+    let mut search_from = 0;
+    for arg in &args[1..] {
+        let Some((pos, placeholder)) = find_placeholder(&result, search_from) else {
+            break;
+        };
+        let value = match (arg, placeholder.to_ascii_lowercase()) {
+            (Value::Quoted(quoted), 's') => quoted.value.clone(),
+            _ => arg.to_string(),
+        };
+        let value = if placeholder.is_ascii_uppercase() {
+            encode_uri_component(&value)
+        } else {
+            value
+        };
+        result.replace_range(pos..pos + 2, &value);
+        search_from = pos + value.len();
+    }
+    Ok(Value::Quoted(Quoted {
+        value: result.replace("%%", "%"),
+        quote: string.quote,
+        escaped: string.escaped,
+    }))
+}
+
+fn find_placeholder(value: &str, from: usize) -> Option<(usize, char)> {
+    let bytes = value.as_bytes();
+    (from..bytes.len().saturating_sub(1)).find_map(|pos| {
+        let placeholder = bytes[pos + 1] as char;
+        (bytes[pos] == b'%' && matches!(placeholder.to_ascii_lowercase(), 's' | 'd' | 'a'))
+            .then_some((pos, placeholder))
+    })
+}
+
+/// escape("a=1") -> a%3D1
+fn escape(args: &[Value]) -> Result<Value, FunctionError> {
+    expect_args(args, 1)?;
+    let value = percent_encode(&string_value(&args[0]), |ch| {
+        matches!(
+            ch,
+            '-' | '_' | '.' | '!' | '~' | '*' | '\'' | ',' | '/' | '?' | '@' | '&' | '+' | '$'
+        )
+    });
+    Ok(Value::Keyword(value))
+}
+
+// 128 or 50% of `max`
+fn channel(value: &Value, max: f64) -> Result<f64, FunctionError> {
+    match value {
+        Value::Number(number) if number.unit.as_deref() == Some("%") => {
+            Ok(number.value * max / 100.0)
+        }
+        Value::Number(number) => Ok(number.value),
+        _ => Err(FunctionError::ExpectedNumber),
+    }
+}
+
+/// rgb(255, 238, 170) -> #ffeeaa
+fn rgb(args: &[Value]) -> Result<Value, FunctionError> {
+    expect_args(args, 3)?;
+    rgba(&[
+        args[0].clone(),
+        args[1].clone(),
+        args[2].clone(),
+        Value::Number(Number {
+            value: 1.0,
+            unit: None,
+        }),
+    ])
+}
+
+/// rgba(255, 238, 170, 0.1)
+fn rgba(args: &[Value]) -> Result<Value, FunctionError> {
+    expect_args(args, 4)?;
+    Ok(Value::Color(Color {
+        rgb: [
+            channel(&args[0], 255.0)?,
+            channel(&args[1], 255.0)?,
+            channel(&args[2], 255.0)?,
+        ],
+        alpha: channel(&args[3], 1.0)?,
+        raw: None,
+    }))
+}
+
+#[cfg(test)]
+fn quoted(value: &str) -> Value {
+    Value::Quoted(Quoted::from_raw(value, false))
+}
+
+#[test]
+fn format_string() {
+    let number = |value| Value::Number(Number { value, unit: None });
+    let result = format(&[
+        quoted("\"rgb(%d, %d, %d)\""),
+        number(32.0),
+        number(128.0),
+        number(64.0),
+    ]);
+    assert_eq!(result.unwrap().to_string(), "\"rgb(32, 128, 64)\"");
+
+    let result = format(&[quoted("\"hello %s %d\""), quoted("\"earth\""), number(2.0)]);
+    assert_eq!(result.unwrap().to_string(), "\"hello earth 2\"");
+
+    let red = Value::Color(Color::from_hex("#ff0000").unwrap());
+    let result = format(&[quoted("\"red is %A\""), red]);
+    assert_eq!(result.unwrap().to_string(), "\"red is %23ff0000\"");
+
+    let result = format(&[quoted("'hello %s'"), quoted("\"single world\"")]);
+    assert_eq!(result.unwrap().to_string(), "'hello single world'");
+
+    let escaped = Value::Quoted(Quoted::from_raw("\"hello %s\"", true));
+    let result = format(&[escaped, quoted("\"escaped world\"")]);
+    assert_eq!(result.unwrap().to_string(), "hello escaped world");
+
+    let result = format(&[quoted("\"%s\""), quoted("\"100%\"")]);
+    assert_eq!(result.unwrap().to_string(), "\"100%\"");
+}
+
+#[test]
+fn colors() {
+    let number = |value, unit: Option<&str>| {
+        Value::Number(Number {
+            value,
+            unit: unit.map(|unit| unit.to_string()),
+        })
+    };
+    let result = rgb(&[
+        number(255.0, None),
+        number(238.0, None),
+        number(170.0, None),
+    ]);
+    assert_eq!(result.unwrap().to_string(), "#ffeeaa");
+    let result = rgba(&[
+        number(100.0, Some("%")),
+        number(0.0, None),
+        number(0.0, None),
+        number(10.0, Some("%")),
+    ]);
+    assert_eq!(result.unwrap().to_string(), "rgba(255, 0, 0, 0.1)");
+    assert_eq!(
+        rgb(&[quoted("'a'"), number(0.0, None), number(0.0, None)]),
+        Err(FunctionError::ExpectedNumber)
+    );
+}
+
+#[test]
+fn e_and_escape() {
+    let result = e(&[quoted("\"-Some::weird(#thing, y)\"")]);
+    assert_eq!(result.unwrap().to_string(), "-Some::weird(#thing, y)");
+    let result = escape(&[quoted("'a=1'")]);
+    assert_eq!(result.unwrap().to_string(), "a%3D1");
+    assert_eq!(
+        e(&[]),
+        Err(FunctionError::ArgumentCount {
+            expected: 1,
+            found: 0
+        })
+    );
+}
+
+#[test]
+fn registered() {
+    let number = |value| Value::Number(Number { value, unit: None });
+    let mut functions = Functions::new();
+    functions
+        .register("add", |a: f64, b: f64| {
+            Ok(Number {
+                value: a + b,
+                unit: None,
+            })
+        })
+        .register("count", move |list: Vec<Value>| {
+            Ok(number(list.len() as f64))
+        })
+        .register_variadic("first", |args| {
+            args.first()
+                .cloned()
+                .ok_or_else(|| FunctionError::Message("no arguments".to_string()))
+        });
+    let call = |name: &str, args: &[Value]| functions.get(name).unwrap()(args);
+
+    assert_eq!(call("ADD", &[number(1.0), number(2.0)]), Ok(number(3.0)));
+    assert_eq!(
+        call("add", &[number(1.0)]),
+        Err(FunctionError::ArgumentCount {
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        call("add", &[number(1.0), quoted("'a'")]),
+        Err(FunctionError::ExpectedNumber)
+    );
+    let list = Value::List(List {
+        items: vec![number(1.0), number(2.0)],
+        separator: Separator::Comma,
+    });
+    assert_eq!(call("count", &[list]), Ok(number(2.0)));
+    assert_eq!(call("count", &[number(1.0)]), Ok(number(1.0)));
+    assert_eq!(
+        call("first", &[]),
+        Err(FunctionError::Message("no arguments".to_string()))
+    );
+    assert_eq!(
+        format!("{:?}", functions),
+        "{\"add\", \"count\", \"first\"}"
+    );
+}
@@ -0,0 +1,690 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use less_ast::{
+    ast::{
+        AtKeyword, AtRule, DefinedStatement, PreservedToken, Span, StyleContent, Stylesheets,
+        VariableDefined, VariableDefinedValue,
+    },
+    source::{FileId, SourceFiles},
+};
+use less_error::{
+    codes::Code,
+    diagnostic::{Diagnostic, Label},
+    ErrorKind, LessError,
+};
+use less_parser::{Parser, ParserError};
+use thiserror::Error;
+
+use crate::plugin::{self, Plugin};
+
+use crate::options::{CompileOptions, Variables};
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("'{0}' wasn't found")]
+    NotFound(String, Span),
+    #[error("'{0}' can't be read: {1}")]
+    Read(String, io::Error, Option<Span>),
+    #[error("{0}")]
+    Parse(ParserError, FileId),
+    #[error("import option ({0}) is not supported")]
+    UnsupportedOption(String, Span),
+}
+
+impl ImportError {
+    pub fn code(&self) -> Code {
+        match self {
+            ImportError::NotFound(..) | ImportError::Read(..) => Code::ImportNotFound,
+            ImportError::Parse(error, _) => error.code(),
+            ImportError::UnsupportedOption(..) => Code::Unsupported,
+        }
+    }
+
+    pub fn to_diagnostic(&self, files: &SourceFiles) -> Diagnostic {
+        let label = |span: &Span, message: &str| Label::primary(span.clone(), message);
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(self.code());
+        match self {
+            ImportError::NotFound(_, span) => diagnostic
+                .with_label(label(span, "imported here"))
+                .with_help("add the directory that contains it with --include-path"),
+            ImportError::Read(_, _, Some(span)) => {
+                diagnostic.with_label(label(span, "imported here"))
+            }
+            ImportError::Read(_, _, None) => diagnostic,
+            ImportError::Parse(error, file) => match files.get(*file) {
+                Some(file) => error.to_diagnostic(file),
+                None => diagnostic,
+            },
+            ImportError::UnsupportedOption(_, span) => {
+                diagnostic.with_label(label(span, "imported here"))
+            }
+        }
+    }
+
+    pub fn to_less_error(&self, files: &SourceFiles) -> LessError {
+        match self {
+            ImportError::NotFound(_, span) | ImportError::UnsupportedOption(_, span) => {
+                LessError::new(ErrorKind::Import, self.to_string()).with_span(files, span)
+            }
+            ImportError::Read(_, _, span) => {
+                let error = LessError::new(ErrorKind::Import, self.to_string());
+                match span {
+                    Some(span) => error.with_span(files, span),
+                    None => error,
+                }
+            }
+            ImportError::Parse(error, file) => match files.get(*file) {
+                Some(file) => error.to_less_error(file),
+                None => LessError::new(ErrorKind::Parse, error.to_string()),
+            },
+        }
+    }
+}
+
+// @import (optional, less) "a";
+struct Import {
+    path: String,
+    options: Vec<String>,
+}
+
+impl Import {
+    fn has(&self, option: &str) -> bool {
+        self.options.iter().any(|name| name == option)
+    }
+
+    // a.less and a are inlined, a.css and everything with (css) is left to the browser
+    fn is_less(&self) -> bool {
+        if self.has("css") {
+            return false;
+        }
+        if self.has("less") {
+            return true;
+        }
+        let path = self.path.split(['?', '#']).next().unwrap_or_default();
+        match Path::new(path).extension() {
+            Some(extension) => extension != "css",
+            None => !self.path.contains("://"),
+        }
+    }
+}
+
+// only a string or url, optionally preceded by options: no media queries, no interpolation
+fn parse_import(at_rule: &AtRule) -> Option<Import> {
+    if !at_rule.name.name.eq_ignore_ascii_case("@import") || at_rule.block.is_some() {
+        return None;
+    }
+    let mut values = at_rule.prelude.iter().filter(|value| {
+        !matches!(value,
+            VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) if token.name.trim().is_empty())
+    });
+    let mut options = Vec::new();
+    let mut value = values.next()?;
+    if matches!(value, VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) if token.name == "(")
+    {
+        loop {
+            match values.next()? {
+                VariableDefinedValue::PreservedToken(PreservedToken::Ident(ident)) => {
+                    options.push(ident.name.to_ascii_lowercase())
+                }
+                VariableDefinedValue::PreservedToken(PreservedToken::Token(token))
+                    if token.name == "," => {}
+                VariableDefinedValue::PreservedToken(PreservedToken::Token(token))
+                    if token.name == ")" =>
+                {
+                    break
+                }
+                _ => return None,
+            }
+        }
+        value = values.next()?;
+    }
+    let path = match value {
+        VariableDefinedValue::PreservedToken(PreservedToken::String(string)) => {
+            string.value[1..string.value.len() - 1].to_string()
+        }
+        VariableDefinedValue::PreservedToken(PreservedToken::Url(url)) => url.value.clone(),
+        _ => return None,
+    };
+    if values.next().is_some() || path.contains("@{") {
+        return None;
+    }
+    Some(Import { path, options })
+}
+
+/// where imported files and `@plugin` modules are read from
+pub trait FileSystem: Send + Sync {
+    fn is_file(&self, path: &Path) -> bool;
+    fn read(&self, path: &Path) -> io::Result<String>;
+    // the contents as they are, for `.wasm` modules
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>>;
+    // the same path for every way of naming a file, `a/../b.less` and `b.less`
+    fn canonicalize(&self, path: &Path) -> PathBuf;
+}
+
+/// the real file system
+pub struct Disk;
+
+impl FileSystem for Disk {
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+/// files kept in memory, for hosts without a file system like the browser
+#[derive(Debug, Clone, Default)]
+pub struct MemoryFileSystem {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryFileSystem {
+    /// a stylesheet, or the bytes of a `.wasm` module
+    pub fn insert(&mut self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) {
+        let path = self.canonicalize(path.as_ref());
+        self.files.insert(path, contents.into());
+    }
+
+    pub fn remove(&mut self, path: impl AsRef<Path>) {
+        let path = self.canonicalize(path.as_ref());
+        self.files.remove(&path);
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(&self.canonicalize(path))
+    }
+
+    fn read(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read_bytes(path)?)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    fn read_bytes(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(&self.canonicalize(path))
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        normalize(path)
+    }
+}
+
+// `./a/../b.less` -> `b.less`, without looking at the disk, leading `..` are kept
+fn normalize(path: &Path) -> PathBuf {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+                components.pop();
+            }
+            component => components.push(component),
+        }
+    }
+    components.iter().collect()
+}
+
+/**
+ * parsed files shared between importers, a file imported by several entry points is parsed once
+ * the import chains are kept by each importer, see `Importer::files`
+ */
+#[derive(Default)]
+pub struct ImportCache {
+    files: Mutex<SourceFiles>,
+    // canonical path -> the first parse of the file, before its imports are inlined
+    parsed: Mutex<HashMap<PathBuf, Arc<OnceLock<Option<Parsed>>>>>,
+}
+
+type Parsed = (FileId, Arc<Stylesheets>);
+
+impl ImportCache {
+    pub fn files(&self) -> MutexGuard<'_, SourceFiles> {
+        self.files.lock().unwrap()
+    }
+
+    pub fn into_files(self) -> SourceFiles {
+        self.files.into_inner().unwrap()
+    }
+}
+
+/**
+ * reads a stylesheet and inlines the `.less` files it imports, recursively
+ * paths are tried relative to the importing file, then in every include path
+ */
+pub struct Importer {
+    include_paths: Vec<PathBuf>,
+    cache: Arc<ImportCache>,
+    file_system: Arc<dyn FileSystem>,
+    // every imported file once, in import order, `.` and `..` resolved, the entry file is not included
+    dependencies: Vec<PathBuf>,
+    // every path an import that wasn't found was looked for at
+    missing: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    // the @import that pulled in each file for this entry, a cached file may come from another
+    imported_from: HashMap<FileId, Span>,
+    // `pre_process` every file before it is parsed
+    plugins: Vec<Arc<dyn Plugin>>,
+}
+
+impl Importer {
+    pub fn new(include_paths: Vec<PathBuf>) -> Self {
+        Self::with_cache(include_paths, Arc::default())
+    }
+
+    pub fn with_cache(include_paths: Vec<PathBuf>, cache: Arc<ImportCache>) -> Self {
+        Self {
+            include_paths,
+            cache,
+            file_system: Arc::new(Disk),
+            dependencies: Vec::new(),
+            missing: Vec::new(),
+            seen: HashSet::new(),
+            imported_from: HashMap::new(),
+            plugins: Vec::new(),
+        }
+    }
+
+    pub fn with_file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = file_system;
+        self
+    }
+
+    /// where the files are read from, `@plugin` modules are read from it too
+    pub fn file_system(&self) -> Arc<dyn FileSystem> {
+        Arc::clone(&self.file_system)
+    }
+
+    /// the cache must only be shared with importers running the same plugins
+    pub fn with_plugins(mut self, plugins: Vec<Arc<dyn Plugin>>) -> Self {
+        self.plugins = plugins;
+        self
+    }
+
+    /**
+     * every file loaded so far, by this importer and the others sharing its cache,
+     * with the import chains of this importer's entry
+     */
+    pub fn files(&self) -> SourceFiles {
+        let mut files = self.cache.files().clone();
+        for (id, from) in &self.imported_from {
+            files.set_imported_from(*id, from.clone());
+        }
+        files
+    }
+
+    pub fn into_files(self) -> SourceFiles {
+        self.files()
+    }
+
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    /// where the imports that weren't found were looked for, `.` and `..` resolved
+    pub fn missing(&self) -> &[PathBuf] {
+        &self.missing
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> Result<Stylesheets, ImportError> {
+        let (id, stylesheets) = self.parse_file(path, None)?;
+        self.inline_imports(id, stylesheets)
+    }
+
+    /// `name` is used in messages and to resolve imports, e.g. `stdin` imports from the working directory
+    pub fn load(&mut self, name: &str, source: String) -> Result<Stylesheets, ImportError> {
+        let source = plugin::pre_process(&self.plugins, source, name);
+        let id = self.cache.files().add(name, source.clone());
+        let stylesheets = Parser::with_file(&source, id)
+            .parse()
+            .map_err(|error| ImportError::Parse(error, id))?;
+        self.inline_imports(id, stylesheets)
+    }
+
+    /// `vars` as variable declarations, every value is parsed on its own as a file named `{name} @var`
+    pub fn load_variables(
+        &mut self,
+        name: &str,
+        vars: &Variables,
+    ) -> Result<Vec<StyleContent>, ImportError> {
+        let mut content = Vec::with_capacity(vars.len());
+        for (var, value) in vars {
+            let var = format!("@{}", var.trim().trim_start_matches('@'));
+            // `red;` is accepted as less.js does
+            let value = value.trim();
+            let value = value.strip_suffix(';').unwrap_or(value);
+            let id = self
+                .cache
+                .files()
+                .add(format!("{} {}", name, var), value.to_string());
+            let parsed = Parser::with_file(value, id)
+                .parse_value()
+                .map_err(|error| ImportError::Parse(error, id))?;
+            let span = Span {
+                start: 0,
+                end: value.len(),
+                file: id,
+            };
+            content.push(StyleContent::DefinedStatement(
+                DefinedStatement::VariableDefined(Box::new(VariableDefined {
+                    span: span.clone(),
+                    name: AtKeyword { name: var, span },
+                    value: parsed,
+                })),
+            ));
+        }
+        Ok(content)
+    }
+
+    /// surrounds a loaded file with the global and modified variables of `options`
+    pub fn apply_variables(
+        &mut self,
+        stylesheets: Stylesheets,
+        options: &CompileOptions,
+    ) -> Result<Stylesheets, ImportError> {
+        let mut content = self.load_variables("global-vars", &options.global_vars)?;
+        content.extend(stylesheets.content);
+        content.extend(self.load_variables("modify-vars", &options.modify_vars)?);
+        Ok(Stylesheets {
+            span: stylesheets.span,
+            content,
+        })
+    }
+
+    // parsed once per cache, `from` is the @import, recorded for this entry
+    fn parse_file(
+        &mut self,
+        path: &Path,
+        from: Option<Span>,
+    ) -> Result<(FileId, Stylesheets), ImportError> {
+        let result = self.parse_cached(path, from.clone());
+        let id = match &result {
+            Ok((id, _)) | Err(ImportError::Parse(_, id)) => Some(*id),
+            Err(_) => None,
+        };
+        if let (Some(id), Some(from)) = (id, from) {
+            self.imported_from.insert(id, from);
+        }
+        result
+    }
+
+    fn parse_cached(
+        &mut self,
+        path: &Path,
+        from: Option<Span>,
+    ) -> Result<(FileId, Stylesheets), ImportError> {
+        let canonical = self.file_system.canonicalize(path);
+        self.seen.insert(canonical.clone());
+        // the first importer parses the file, the others sharing the cache wait for it
+        let cell = Arc::clone(
+            self.cache
+                .parsed
+                .lock()
+                .unwrap()
+                .entry(canonical)
+                .or_default(),
+        );
+        let mut failed = None;
+        let parsed = cell.get_or_init(|| match self.read_file(path, from.clone()) {
+            Ok((id, stylesheets)) => Some((id, Arc::new(stylesheets))),
+            Err(error) => {
+                failed = Some(error);
+                None
+            }
+        });
+        match (parsed, failed) {
+            (_, Some(error)) => Err(error),
+            (Some((id, stylesheets)), None) => Ok((*id, Stylesheets::clone(stylesheets))),
+            // it failed for the first importer, it fails again with the span of this import
+            (None, None) => self.read_file(path, from),
+        }
+    }
+
+    fn read_file(
+        &self,
+        path: &Path,
+        from: Option<Span>,
+    ) -> Result<(FileId, Stylesheets), ImportError> {
+        let name = path.display().to_string();
+        let source = self
+            .file_system
+            .read(path)
+            .map_err(|error| ImportError::Read(name.clone(), error, from.clone()))?;
+        let source = plugin::pre_process(&self.plugins, source, &name);
+        let id = self.cache.files().add(name, source.clone());
+        let stylesheets = Parser::with_file(&source, id)
+            .parse()
+            .map_err(|error| ImportError::Parse(error, id))?;
+        Ok((id, stylesheets))
+    }
+
+    fn inline_imports(
+        &mut self,
+        id: FileId,
+        mut stylesheets: Stylesheets,
+    ) -> Result<Stylesheets, ImportError> {
+        let directory = match self.cache.files().get(id) {
+            Some(file) => Path::new(&file.name).parent().map(Path::to_path_buf),
+            None => None,
+        }
+        .unwrap_or_default();
+        let mut content = Vec::with_capacity(stylesheets.content.len());
+        for item in stylesheets.content {
+            let StyleContent::AtRule(mut at_rule) = item else {
+                content.push(item);
+                continue;
+            };
+            match parse_import(&at_rule) {
+                Some(import) if import.is_less() => {
+                    match self.import(&directory, &import, at_rule.span.clone())? {
+                        Some(imported) => content.extend(imported.content),
+                        // a missing optional import stays, the evaluator warns about it
+                        None => content.push(StyleContent::AtRule(at_rule)),
+                    }
+                }
+                // @import (css) "a"; is printed as @import "a";
+                Some(import) if !import.options.is_empty() => {
+                    let close = at_rule.prelude.iter().position(|value| {
+                        matches!(value, VariableDefinedValue::PreservedToken(PreservedToken::Token(token)) if token.name == ")")
+                    });
+                    if let Some(close) = close {
+                        at_rule.prelude.drain(..=close);
+                    }
+                    content.push(StyleContent::AtRule(at_rule));
+                }
+                _ => content.push(StyleContent::AtRule(at_rule)),
+            }
+        }
+        stylesheets.content = content;
+        Ok(stylesheets)
+    }
+
+    // None when the file is optional and missing, nothing when it was already imported
+    fn import(
+        &mut self,
+        directory: &Path,
+        import: &Import,
+        span: Span,
+    ) -> Result<Option<Stylesheets>, ImportError> {
+        if let Some(option) = import
+            .options
+            .iter()
+            .find(|option| matches!(option.as_str(), "reference" | "inline"))
+        {
+            return Err(ImportError::UnsupportedOption(option.clone(), span));
+        }
+        let mut path = import.path.clone();
+        if Path::new(&path).extension().is_none() {
+            path.push_str(".less");
+        }
+        let candidates: Vec<PathBuf> = std::iter::once(directory)
+            .chain(self.include_paths.iter().map(PathBuf::as_path))
+            .map(|directory| directory.join(&path))
+            .collect();
+        let found = candidates
+            .iter()
+            .find(|path| self.file_system.is_file(path))
+            .cloned();
+        let Some(found) = found else {
+            self.missing
+                .extend(candidates.iter().map(|path| normalize(path)));
+            if import.has("optional") {
+                return Ok(None);
+            }
+            return Err(ImportError::NotFound(path, span));
+        };
+        let canonical = self.file_system.canonicalize(&found);
+        if self.seen.contains(&canonical) && !import.has("multiple") {
+            return Ok(Some(Stylesheets {
+                span,
+                content: Vec::new(),
+            }));
+        }
+        self.dependencies.push(normalize(&found));
+        let (id, stylesheets) = self.parse_file(&found, Some(span))?;
+        self.inline_imports(id, stylesheets).map(Some)
+    }
+}
+
+#[test]
+fn imports() {
+    let directory = std::env::temp_dir().join(format!("less-import-{}", std::process::id()));
+    let include = directory.join("include");
+    fs::create_dir_all(&include).unwrap();
+    fs::write(directory.join("a.less"), "@import \"b\";\n.a { c: d; }\n").unwrap();
+    fs::write(
+        include.join("b.less"),
+        "@import '../a.less';\n.b { c: d; }\n",
+    )
+    .unwrap();
+    fs::write(directory.join("c.less"), "@import \"missing\";\n").unwrap();
+
+    let mut importer = Importer::new(vec![include.clone()]);
+    let source = "@import \"a\";\n@import (css) \"a\";\n@import \"e.css\";\n@import (optional) \"f\";\n@import \"a.less\" screen;\n.main { c: d; }\n";
+    let stylesheets = importer
+        .load(
+            &directory.join("main.less").display().to_string(),
+            source.to_string(),
+        )
+        .unwrap();
+    let output = crate::render(&stylesheets, &importer.files(), &Default::default()).unwrap();
+    assert_eq!(output.warnings.len(), 1, "f.less is missing");
+    assert_eq!(
+        output.css,
+        "@import \"a\";\n@import \"e.css\";\n@import \"a.less\" screen;\n.b {\n  c: d;\n}\n.a {\n  c: d;\n}\n.main {\n  c: d;\n}\n"
+    );
+    assert_eq!(
+        importer.dependencies(),
+        [directory.join("a.less"), include.join("b.less")]
+    );
+    assert_eq!(
+        importer.files().import_chain(FileId(2)).len(),
+        2,
+        "b.less is imported from a.less, imported from main.less"
+    );
+
+    let mut importer = Importer::new(Vec::new());
+    let error = importer.load_file(&directory.join("c.less")).unwrap_err();
+    assert_eq!(error.to_string(), "'missing.less' wasn't found");
+    assert_eq!(
+        error.to_less_error(&importer.files()).to_string(),
+        format!(
+            "FileError: 'missing.less' wasn't found in {} on line 1, column 1:\n1 @import \"missing\";\n2 ",
+            directory.join("c.less").display()
+        )
+    );
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn shared_cache() {
+    let mut file_system = MemoryFileSystem::default();
+    file_system.insert("shared.less", ".shared { a: b; }\n");
+    let file_system: Arc<dyn FileSystem> = Arc::new(file_system);
+    let cache = Arc::new(ImportCache::default());
+    std::thread::scope(|scope| {
+        for entry in 0..8 {
+            let mut importer = Importer::with_cache(Vec::new(), cache.clone())
+                .with_file_system(file_system.clone());
+            scope.spawn(move || {
+                let stylesheets = importer
+                    .load(
+                        &format!("entry{}.less", entry),
+                        "@import 'shared';".to_string(),
+                    )
+                    .unwrap();
+                assert_eq!(stylesheets.content.len(), 1);
+            });
+        }
+    });
+    // every entry imports it, only one of them parsed it
+    let files = cache.files();
+    assert_eq!(
+        files
+            .iter()
+            .filter(|file| file.name == "shared.less")
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn variables() {
+    let vars = |json: &str| serde_json::from_str::<Variables>(json).unwrap();
+    let compile = |less: &str, options: &CompileOptions| {
+        let mut importer = Importer::new(Vec::new());
+        let stylesheets = importer.load("main.less", less.to_string()).unwrap();
+        let stylesheets = importer.apply_variables(stylesheets, options).unwrap();
+        let files = importer.into_files();
+        crate::render(&stylesheets, &files, &options.options)
+            .unwrap()
+            .css
+    };
+
+    let less = include_str!("../../less-test-data/test-data/less/modifyVars/extended.less");
+    let css = include_str!("../../less-test-data/test-data/css/modifyVars/extended.css");
+    let options = CompileOptions {
+        modify_vars: vars(include_str!(
+            "../../less-test-data/test-data/less/modifyVars/extended.json"
+        )),
+        ..Default::default()
+    };
+    assert_eq!(compile(less, &options).trim(), css.trim());
+
+    // the file overrides a global variable
+    let options = CompileOptions {
+        global_vars: vars(r#"{ "@color": "red", "size": "1px;" }"#),
+        ..Default::default()
+    };
+    assert_eq!(
+        compile(".a { b: @color @size; }\n@color: blue;", &options).trim(),
+        ".a {\n  b: blue 1px;\n}"
+    );
+
+    let mut importer = Importer::new(Vec::new());
+    let error = importer
+        .load_variables("global-vars", &vars(r#"{ "a": "red; .b { c: d }" }"#))
+        .unwrap_err();
+    assert_eq!(
+        error.to_less_error(&importer.files()).to_string(),
+        "ParseError: Unrecognised input in global-vars @a on line 1, column 4:\n1 red; .b { c: d }"
+    );
+}
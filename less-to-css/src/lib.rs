@@ -0,0 +1,1071 @@
+pub mod batch;
+pub mod css;
+pub mod eval;
+pub mod functions;
+pub mod import;
+pub mod options;
+pub mod plugin;
+pub mod source_map;
+pub mod to_css;
+pub mod url;
+pub mod value;
+pub mod wasm;
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use eval::{EvalError, Evaluator};
+use import::{Disk, FileSystem, ImportError, Importer};
+use less_ast::{ast::Stylesheets, source::SourceFiles};
+use less_error::diagnostic::{Diagnostic, Renderer};
+use options::{CompileOptions, Options};
+use plugin::Plugin;
+use source_map::SourceMap;
+use to_css::ToCss;
+
+/// the generated css and, with `Options::source_map`, its source map
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    pub css: String,
+    pub map: Option<SourceMap>,
+    // warnings not silenced by a `less-disable` comment
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// evaluate a parsed stylesheet and print it as css
+pub fn to_css(stylesheets: &Stylesheets, options: &Options) -> Result<String, EvalError> {
+    let stylesheet = Evaluator::new(options).eval(stylesheets)?;
+    let mut to_css = ToCss::new(String::new(), options.compress);
+    to_css.write_stylesheet(&stylesheet).unwrap();
+    Ok(to_css.result)
+}
+
+/// like `to_css`, `files` holds every file the spans in `stylesheets` point into
+pub fn render(
+    stylesheets: &Stylesheets,
+    files: &SourceFiles,
+    options: &Options,
+) -> Result<Output, EvalError> {
+    render_with(stylesheets, files, options, &[])
+}
+
+/// like `render`, running the `post_eval` and `post_process` hooks of `plugins`
+pub fn render_with(
+    stylesheets: &Stylesheets,
+    files: &SourceFiles,
+    options: &Options,
+    plugins: &[Arc<dyn Plugin>],
+) -> Result<Output, EvalError> {
+    let evaluator = Evaluator::new(options).with_files(files);
+    render_evaluator(evaluator, stylesheets, files, options, plugins)
+}
+
+/**
+ * like `render_with` for what an `Importer` loaded, `@plugin` modules are read from
+ * `file_system`, next to the file or in `options.include_paths` as imports are
+ */
+pub fn render_from(
+    stylesheets: &Stylesheets,
+    files: &SourceFiles,
+    options: &CompileOptions,
+    file_system: Arc<dyn FileSystem>,
+) -> Result<Output, EvalError> {
+    let evaluator = Evaluator::new(&options.options)
+        .with_files(files)
+        .with_file_system(file_system, options.include_paths.clone());
+    render_evaluator(
+        evaluator,
+        stylesheets,
+        files,
+        &options.options,
+        &options.plugins,
+    )
+}
+
+fn render_evaluator(
+    mut evaluator: Evaluator,
+    stylesheets: &Stylesheets,
+    files: &SourceFiles,
+    options: &Options,
+    plugins: &[Arc<dyn Plugin>],
+) -> Result<Output, EvalError> {
+    let mut stylesheet = evaluator.eval(stylesheets)?;
+    plugin::post_eval(plugins, &mut stylesheet);
+    let warnings = evaluator.take_warnings();
+    let mut to_css = ToCss::new(String::new(), options.compress);
+    to_css.write_stylesheet(&stylesheet).unwrap();
+    let mut css = plugin::post_process(plugins, to_css.result);
+    let Some(source_map_options) = &options.source_map else {
+        return Ok(Output {
+            css,
+            map: None,
+            warnings,
+        });
+    };
+    let map = SourceMap::new(&to_css.mappings, files, source_map_options);
+    if let Some(annotation) = source_map::annotation(&map, source_map_options) {
+        css.push_str(&annotation);
+    }
+    Ok(Output {
+        css,
+        map: Some(map),
+        warnings,
+    })
+}
+
+/// what `compile` produced, `files` holds the sources the warnings point into
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    pub css: String,
+    pub map: Option<SourceMap>,
+    // every imported file once, in import order
+    pub imports: Vec<PathBuf>,
+    // every path a missing optional import was looked for at, to watch for it
+    pub missing: Vec<PathBuf>,
+    pub warnings: Vec<Diagnostic>,
+    pub files: SourceFiles,
+}
+
+/// why a compilation failed, displayed as plain code frames
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+    // the files imported before it failed, in import order
+    pub imports: Vec<PathBuf>,
+    // every path a missing import was looked for at, to watch for it
+    pub missing: Vec<PathBuf>,
+    pub files: SourceFiles,
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let renderer = Renderer::plain();
+        for diagnostic in &self.diagnostics {
+            f.write_str(&renderer.render(diagnostic, &self.files))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/**
+ * parses `source`, inlines its imports, evaluates it and prints it as css
+ * `options.filename` names the source in messages and source maps, relative imports start from its directory
+ */
+pub fn compile(source: &str, options: &CompileOptions) -> Result<CompileOutput, Diagnostics> {
+    compile_with(source, options, Arc::new(Disk))
+}
+
+/// like `compile`, imports are read from `file_system` instead of the disk
+pub fn compile_with(
+    source: &str,
+    options: &CompileOptions,
+    file_system: Arc<dyn FileSystem>,
+) -> Result<CompileOutput, Diagnostics> {
+    let name = match &options.filename {
+        Some(filename) => filename.display().to_string(),
+        None => "input".to_string(),
+    };
+    let mut importer = Importer::new(options.include_paths.clone())
+        .with_file_system(file_system)
+        .with_plugins(options.plugins.clone());
+    let loaded = importer.load(&name, source.to_string());
+    finish(importer, loaded, options)
+}
+
+/// like `compile`, reading the source from `path`
+pub fn compile_file(path: &Path, options: &CompileOptions) -> Result<CompileOutput, Diagnostics> {
+    let mut importer =
+        Importer::new(options.include_paths.clone()).with_plugins(options.plugins.clone());
+    let loaded = importer.load_file(path);
+    finish(importer, loaded, options)
+}
+
+fn finish(
+    mut importer: Importer,
+    loaded: Result<Stylesheets, ImportError>,
+    options: &CompileOptions,
+) -> Result<CompileOutput, Diagnostics> {
+    let loaded = loaded.and_then(|stylesheets| importer.apply_variables(stylesheets, options));
+    let imports = importer.dependencies().to_vec();
+    let missing = importer.missing().to_vec();
+    let file_system = importer.file_system();
+    let files = importer.into_files();
+    let mut stylesheets = match loaded {
+        Ok(stylesheets) => stylesheets,
+        Err(error) => {
+            return Err(Diagnostics {
+                diagnostics: vec![error.to_diagnostic(&files)],
+                imports,
+                missing,
+                files,
+            })
+        }
+    };
+    plugin::pre_eval(&options.plugins, &mut stylesheets);
+    match render_from(&stylesheets, &files, options, file_system) {
+        Ok(output) => Ok(CompileOutput {
+            css: output.css,
+            map: output.map,
+            imports,
+            missing,
+            warnings: output.warnings,
+            files,
+        }),
+        Err(error) => Err(Diagnostics {
+            diagnostics: vec![error.to_diagnostic()],
+            imports,
+            missing,
+            files,
+        }),
+    }
+}
+
+#[cfg(test)]
+fn compile_css(source: &str) -> String {
+    let stylesheets = less_parser::Parser::new(source).parse().unwrap();
+    to_css(&stylesheets, &Options::default()).unwrap()
+}
+
+// `source` with every part in `skipped` cut out, a part that isn't found fails
+// so a changed fixture can't be skipped silently
+#[cfg(test)]
+fn without(source: &str, skipped: &[&str]) -> String {
+    skipped.iter().fold(source.to_string(), |source, part| {
+        assert!(source.contains(part), "not in the fixture: {:?}", part);
+        source.replacen(part, "", 1)
+    })
+}
+
+#[test]
+fn strings() {
+    let less = include_str!("../../less-test-data/test-data/less/_main/strings.less");
+    let css = include_str!("../../less-test-data/test-data/css/_main/strings.css");
+    assert_eq!(compile_css(less), css);
+}
+
+#[test]
+fn escaped_values() {
+    assert_eq!(
+        compile_css(".a { filter: ~\"ms:alwaysHasItsOwnSyntax.For.Stuff()\"; }"),
+        ".a {\n  filter: ms:alwaysHasItsOwnSyntax.For.Stuff();\n}\n"
+    );
+    assert_eq!(
+        compile_css("@r: 32; .a { color: %(\"rgb(%d, %d, %d)\", @r, 128, 64); b: e(\"%\"); }"),
+        ".a {\n  color: \"rgb(32, 128, 64)\";\n  b: %;\n}\n"
+    );
+}
+
+#[test]
+fn css_escapes() {
+    let less = include_str!("../../less-test-data/test-data/less/_main/css-escapes.less");
+    let css = include_str!("../../less-test-data/test-data/css/_main/css-escapes.css");
+    // parts of the fixture that don't compile yet, the less and the css it gives
+    const SKIPPED: &[(&str, &str)] = &[
+        // a bare e() call at the root is not supported
+        ("e('anything to unquote');", "anything to unquote\n"),
+    ];
+    let (skipped_less, skipped_css): (Vec<_>, Vec<_>) = SKIPPED.iter().copied().unzip();
+    assert_eq!(
+        compile_css(&without(less, &skipped_less)),
+        without(css, &skipped_css)
+    );
+}
+
+#[test]
+fn compression() {
+    let less = include_str!("../../less-test-data/test-data/less/compression/compression.less");
+    let css = include_str!("../../less-test-data/test-data/css/compression/compression.css");
+    let stylesheets = less_parser::Parser::new(less).parse().unwrap();
+    let options = Options {
+        compress: true,
+        ..Default::default()
+    };
+    assert_eq!(to_css(&stylesheets, &options).unwrap(), css);
+}
+
+#[test]
+fn comments() {
+    let less = include_str!("../../less-test-data/test-data/less/_main/comments.less");
+    let css = include_str!("../../less-test-data/test-data/css/_main/comments.css");
+    // parts of the fixture that don't compile yet, the less and the css it gives
+    const SKIPPED: &[(&str, &str)] = &[
+        // mixin guards are not supported
+        (
+            concat!(
+                ".mixin_def_with_colors(@a: white, // in\n",
+                "       @b: 1px //put in @b - causes problems! --->\n",
+                "       ) // the\n",
+                "       when (@a = white) {\n",
+                "    .test-rule {\n",
+                "        color: @b;\n",
+                "    }\n",
+                "}\n",
+                ".mixin_def_with_colors();",
+            ),
+            ".test-rule {\n  color: 1px;\n}\n",
+        ),
+        // comments in keyframe selectors are not supported
+        (
+            concat!(
+                "@-webkit-keyframes /* Safari */ hover /* and Chrome */ {\n",
+                "  0% {\n",
+                "    color: red;\n",
+                "  }\n",
+                "}\n",
+            ),
+            concat!(
+                "@-webkit-keyframes hover {\n",
+                "  /* and Chrome */\n",
+                "  0% {\n",
+                "    color: red;\n",
+                "  }\n",
+                "}\n",
+            ),
+        ),
+    ];
+    let (skipped_less, skipped_css): (Vec<_>, Vec<_>) = SKIPPED.iter().copied().unzip();
+    assert_eq!(
+        compile_css(&without(less, &skipped_less)),
+        without(css, &skipped_css)
+    );
+
+    let less = include_str!("../../less-test-data/test-data/less/_main/comments2.less");
+    let css = include_str!("../../less-test-data/test-data/css/_main/comments2.css");
+    assert_eq!(compile_css(less), css);
+}
+
+#[test]
+fn diagnostics() {
+    use less_error::diagnostic::Renderer;
+
+    let mut files = SourceFiles::new();
+    let id = files.add("a.less", "@a: @b;\n@b: @a;\n.a { c: @a; }\n");
+    let stylesheets = less_parser::Parser::with_file(&files.get(id).unwrap().src, id)
+        .parse()
+        .unwrap();
+    let error = to_css(&stylesheets, &Options::default()).unwrap_err();
+    assert_eq!(
+        Renderer::plain().render(&error.to_diagnostic(), &files),
+        "\
+error[L0007]: Recursive variable definition for @a
+ --> a.less:2:5
+  |
+1 | @a: @b;
+  | ------ variable defined here
+2 | @b: @a;
+  |     ^^ used while it is evaluated
+"
+    );
+}
+
+#[test]
+fn warnings() {
+    use less_error::codes::Code;
+    use options::Math;
+
+    let source = "@import (optional) \"missing.less\";\n.a {\n  b: 4px / 2;\n  c: (4px / 2);\n  d: e(\"x\");\n  e: darken(#fff, 10%);\n  f: calc(1px);\n}\n";
+    let codes = |sources: &[(&str, &str)], options: &Options| {
+        render_files(sources, options)
+            .warnings
+            .iter()
+            .map(|warning| warning.code.clone().unwrap())
+            .collect::<Vec<_>>()
+    };
+    // division outside parentheses is only evaluated with --math=always
+    let always = Options {
+        math: Math::Always,
+        ..Default::default()
+    };
+    assert_eq!(
+        codes(&[("a.less", source)], &always),
+        [
+            Code::MissingOptionalImport,
+            Code::DivisionOutsideParens,
+            Code::DeprecatedSyntax,
+            Code::UnknownFunction,
+        ]
+        .map(String::from)
+    );
+    // silenced for the file with the comment only
+    let disabled = "/* less-disable L0012, L0015 */\n// less-disable L0013\n";
+    assert_eq!(
+        codes(
+            &[
+                ("a.less", &format!("{}{}", disabled, source)),
+                ("b.less", ".b { c: e(\"x\"); }")
+            ],
+            &always
+        ),
+        [Code::DivisionOutsideParens, Code::DeprecatedSyntax].map(String::from)
+    );
+
+    let mut files = SourceFiles::new();
+    let id = files.add("a.less", source);
+    let stylesheets = less_parser::Parser::with_file(source, id).parse().unwrap();
+    let options = Options {
+        warnings_as_errors: true,
+        ..always
+    };
+    let error = render(&stylesheets, &files, &options).unwrap_err();
+    assert_eq!(error.code(), Code::MissingOptionalImport);
+    let diagnostic = error.to_diagnostic();
+    assert_eq!(diagnostic.severity, less_error::diagnostic::Severity::Error);
+    assert_eq!(diagnostic.notes, ["warnings are treated as errors"]);
+}
+
+#[test]
+fn math() {
+    use options::Math;
+
+    let source = ".a {\n  b: 12px/1.5 (12px/2) 4px * 2;\n  c: 10px / 2 1px + 1em;\n}\n";
+    let compile = |math: Math| {
+        let options = Options {
+            math,
+            ..Default::default()
+        };
+        let stylesheets = less_parser::Parser::new(source).parse().unwrap();
+        to_css(&stylesheets, &options).unwrap()
+    };
+    assert_eq!(
+        compile(Math::Always),
+        ".a {\n  b: 8px 6px 8px;\n  c: 5px 2px;\n}\n"
+    );
+    assert_eq!(
+        compile(Math::ParensDivision),
+        ".a {\n  b: 12px/1.5 6px 8px;\n  c: 10px / 2 2px;\n}\n"
+    );
+    assert_eq!(
+        compile(Math::Parens),
+        ".a {\n  b: 12px/1.5 6px 4px * 2;\n  c: 10px / 2 1px + 1em;\n}\n"
+    );
+
+    let stylesheets = less_parser::Parser::new(source).parse().unwrap();
+    let options = Options {
+        strict_units: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        to_css(&stylesheets, &options).unwrap_err().to_string(),
+        "Incompatible units. Change the units or use the unit function. Bad units: 'px' and 'em'."
+    );
+}
+
+#[cfg(test)]
+fn render_files(sources: &[(&str, &str)], options: &Options) -> Output {
+    use less_ast::ast::StyleContent;
+
+    let mut files = SourceFiles::new();
+    let mut content: Vec<StyleContent> = Vec::new();
+    for (name, source) in sources {
+        let file = files.add(*name, *source);
+        let stylesheets = less_parser::Parser::with_file(source, file)
+            .parse()
+            .unwrap();
+        content.extend(stylesheets.content);
+    }
+    let stylesheets = Stylesheets {
+        span: Default::default(),
+        content,
+    };
+    render(&stylesheets, &files, options).unwrap()
+}
+
+#[test]
+fn source_map() {
+    use options::SourceMapOptions;
+
+    let options = Options {
+        source_map: Some(SourceMapOptions {
+            filename: Some("out.css.map".to_string()),
+            output_filename: Some("out.css".to_string()),
+            include_sources: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let main = "@var: black;\n\n.b {\n  color: green;\n  .c, .d {\n    background: @var;\n  }\n}\n";
+    let imported = ".imported { color: black; }";
+    let output = render_files(
+        &[("main.less", main), ("imported.less", imported)],
+        &options,
+    );
+    assert_eq!(
+        output.css,
+        ".b {\n  color: green;\n}\n.b .c,\n.b .d {\n  background: black;\n}\n.imported {\n  color: black;\n}\n/*# sourceMappingURL=out.css.map */"
+    );
+    let map = output.map.unwrap();
+    assert_eq!(
+        map.to_json(),
+        format!(
+            r#"{{"version":3,"file":"out.css","sources":["main.less","imported.less"],"sourcesContent":[{:?},{:?}],"names":[],"mappings":"AAEA;EACE;;AACA;AAAA;EACE;;ACLJ;EAAY"}}"#,
+            main, imported
+        )
+    );
+}
+
+#[test]
+fn source_map_annotation() {
+    use options::SourceMapOptions;
+
+    let source = ".a { color: red; }";
+    let with = |source_map: SourceMapOptions| {
+        let options = Options {
+            source_map: Some(source_map),
+            ..Default::default()
+        };
+        render_files(&[("a.less", source)], &options)
+    };
+
+    let output = with(SourceMapOptions {
+        inline: true,
+        ..Default::default()
+    });
+    let map = output.map.unwrap();
+    assert_eq!(map.sources_content, None);
+    assert!(output
+        .css
+        .ends_with(&format!("/*# sourceMappingURL={} */", map.to_data_uri())));
+
+    let output = with(SourceMapOptions {
+        filename: Some("a.css.map".to_string()),
+        url: Some("http://example.com/a.css.map".to_string()),
+        rootpath: Some("less/".to_string()),
+        ..Default::default()
+    });
+    assert!(output
+        .css
+        .ends_with("/*# sourceMappingURL=http://example.com/a.css.map */"));
+    assert_eq!(output.map.unwrap().sources, ["less/a.less"]);
+
+    let output = with(SourceMapOptions {
+        url: Some("a.css.map".to_string()),
+        disable_annotation: true,
+        ..Default::default()
+    });
+    assert_eq!(output.css, ".a {\n  color: red;\n}\n");
+
+    let output = with(SourceMapOptions::default());
+    assert_eq!(output.css, ".a {\n  color: red;\n}\n");
+    assert_eq!(output.map.unwrap().mappings, "AAAA;EAAK");
+}
+
+#[test]
+fn source_map_empty() {
+    let options = Options {
+        source_map: Some(Default::default()),
+        ..Default::default()
+    };
+    let empty = include_str!("../../less-test-data/test-data/less/sourcemaps-empty/empty.less");
+    let var_defs =
+        include_str!("../../less-test-data/test-data/less/sourcemaps-empty/var-defs.less");
+    for source in [empty, var_defs] {
+        let output = render_files(&[("empty.less", source)], &options);
+        assert_eq!(output.css, "");
+        assert_eq!(
+            output.map.unwrap().to_json(),
+            r#"{"version":3,"sources":[],"names":[],"mappings":""}"#
+        );
+    }
+}
+
+// every errors/**/*.less is compiled and its error compared to the .txt next to it,
+// every mismatch or panic fails, unless the fixture is in `SKIPPED` with a reason
+#[test]
+fn error_fixtures() {
+    use std::{fs, panic, path::Path};
+
+    // fixtures that don't match yet, each with the missing feature or difference
+    const SKIPPED: &[(&str, &str)] = &[
+        // errors are reported at the operation, not at the declaration
+        ("eval/add-mixed-units.less", "reported at the operation"),
+        (
+            "eval/add-mixed-units2.less",
+            "reported at the operation, units not multiplied",
+        ),
+        ("eval/divide-mixed-units.less", "no multiple units error"),
+        ("eval/multiply-mixed-units.less", "no multiple units error"),
+        // the parser has no detached rulesets, guards or mixin arguments with values
+        ("eval/css-guard-default-func.less", "no css guards"),
+        ("eval/detached-ruleset-1.less", "no detached rulesets"),
+        ("eval/detached-ruleset-2.less", "no detached rulesets"),
+        ("eval/detached-ruleset-3.less", "no detached rulesets"),
+        ("eval/detached-ruleset-5.less", "no detached rulesets"),
+        ("eval/mixin-not-defined-2.less", "no mixin arguments"),
+        ("eval/mixin-not-matched.less", "no mixin arguments"),
+        ("eval/mixin-not-matched2.less", "no mixin arguments"),
+        ("eval/mixins-guards-default-func-1.less", "no mixin guards"),
+        ("eval/mixins-guards-default-func-2.less", "no mixin guards"),
+        ("eval/mixins-guards-default-func-3.less", "no mixin guards"),
+        (
+            "eval/multiple-guards-on-css-selectors.less",
+            "no css guards",
+        ),
+        (
+            "eval/multiple-guards-on-css-selectors2.less",
+            "no css guards",
+        ),
+        ("eval/namespacing-2.less", "no detached rulesets"),
+        ("eval/namespacing-3.less", "no namespace lookups"),
+        ("eval/namespacing-4.less", "no detached rulesets"),
+        (
+            "parse/mixed-mixin-definition-args-1.less",
+            "no mixin arguments",
+        ),
+        (
+            "parse/mixed-mixin-definition-args-2.less",
+            "no mixin arguments",
+        ),
+        ("parse/mixins-guards-cond-expected.less", "no mixin guards"),
+        // the parser has no calls or declarations in the root
+        ("eval/functions-5-color-2.less", "no root function calls"),
+        ("eval/property-in-root.less", "no root mixin calls"),
+        ("eval/property-in-root2.less", "no root mixin calls"),
+        ("eval/property-in-root3.less", "no root declarations"),
+        ("eval/root-func-undefined-1.less", "no root function calls"),
+        ("eval/mixin-not-defined.less", "no root mixin calls"),
+        ("eval/import-subfolder1.less", "no root mixin calls"),
+        // the javascript plugins of the less.js test data
+        ("eval/functions-1.less", "javascript plugin"),
+        ("eval/functions-3-assignment.less", "javascript plugin"),
+        ("eval/functions-4-call.less", "javascript plugin"),
+        ("eval/functions-5-color.less", "javascript plugin"),
+        ("eval/functions-6-condition.less", "javascript plugin"),
+        ("eval/functions-7-dimension.less", "javascript plugin"),
+        ("eval/functions-8-element.less", "javascript plugin"),
+        ("eval/functions-9-expression.less", "javascript plugin"),
+        ("eval/functions-10-keyword.less", "javascript plugin"),
+        ("eval/functions-11-operation.less", "javascript plugin"),
+        ("eval/functions-12-quoted.less", "javascript plugin"),
+        ("eval/functions-13-selector.less", "javascript plugin"),
+        ("eval/functions-14-url.less", "javascript plugin"),
+        ("eval/functions-15-value.less", "javascript plugin"),
+        ("eval/plugin-1.less", "javascript plugin"),
+        ("eval/plugin-2.less", "javascript plugin"),
+        ("eval/plugin-3.less", "javascript plugin"),
+        ("eval/root-func-undefined-2.less", "javascript plugin"),
+        ("eval/javascript-undefined-var.less", "no inline javascript"),
+        // builtin functions that aren't implemented
+        ("eval/color-func-invalid-color.less", "no color function"),
+        ("eval/color-func-invalid-color-2.less", "no darken function"),
+        (
+            "eval/percentage-non-number-argument.less",
+            "no percentage function",
+        ),
+        ("eval/recursive-variable.less", "no darken function"),
+        ("eval/svg-gradient1.less", "no svg-gradient function"),
+        ("eval/svg-gradient2.less", "no svg-gradient function"),
+        ("eval/svg-gradient3.less", "no svg-gradient function"),
+        ("eval/svg-gradient4.less", "no svg-gradient function"),
+        ("eval/svg-gradient5.less", "no svg-gradient function"),
+        ("eval/svg-gradient6.less", "no svg-gradient function"),
+        ("eval/unit-function.less", "no unit function"),
+        // variables and interpolation outside of values
+        (
+            "eval/at-rules-undefined-var.less",
+            "no variables in at-rule names",
+        ),
+        (
+            "eval/property-interp-not-defined.less",
+            "no property interpolation",
+        ),
+        // scoping differences
+        (
+            "eval/extend-no-selector.less",
+            "extend without a selector is accepted",
+        ),
+        (
+            "eval/mixin-not-visible-in-scope-1.less",
+            "nested `&` blocks don't parse",
+        ),
+        // imports aren't resolved without the importer
+        ("eval/import-missing.less", "parsed without the importer"),
+        (
+            "parse/import-subfolder2.less",
+            "parsed without the importer",
+        ),
+        // the parser reports a generic error where less.js has a specific one
+        (
+            "parse/at-rules-unmatching-block.less",
+            "generic parse error",
+        ),
+        (
+            "parse/custom-property-unmatched-block-1.less",
+            "generic parse error",
+        ),
+        (
+            "parse/custom-property-unmatched-block-2.less",
+            "generic parse error",
+        ),
+        (
+            "parse/custom-property-unmatched-block-3.less",
+            "generic parse error",
+        ),
+        (
+            "parse/extend-not-at-end.less",
+            "extend in the middle is accepted",
+        ),
+        (
+            "parse/import-malformed.less",
+            "malformed import is accepted",
+        ),
+        ("parse/import-no-semi.less", "generic parse error"),
+        (
+            "parse/invalid-color-with-comment.less",
+            "invalid color is an eval error",
+        ),
+        ("parse/parens-error-1.less", "generic parse error"),
+        ("parse/parens-error-2.less", "generic parse error"),
+        ("parse/parens-error-3.less", "generic parse error"),
+        (
+            "parse/parse-error-media-no-block-1.less",
+            "extra `)` is accepted",
+        ),
+        (
+            "parse/parse-error-media-no-block-2.less",
+            "generic parse error",
+        ),
+        (
+            "parse/parse-error-media-no-block-3.less",
+            "generic parse error",
+        ),
+        (
+            "parse/parse-error-missing-parens.less",
+            "missing `)` is accepted",
+        ),
+        ("parse/percentage-missing-space.less", "generic parse error"),
+        (
+            "parse/property-asterisk-only-name.less",
+            "reported before the `:`",
+        ),
+    ];
+
+    fn collect(dir: &Path, paths: &mut Vec<std::path::PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                collect(&path, paths);
+            } else if path.extension().is_some_and(|ext| ext == "less") {
+                paths.push(path);
+            }
+        }
+    }
+
+    fn render_error(path: &Path) -> Option<String> {
+        let source = fs::read_to_string(path).unwrap();
+        let mut files = SourceFiles::new();
+        let id = files.add(path.to_str().unwrap(), source);
+        let file = files.get(id).unwrap();
+        let stylesheets = match less_parser::Parser::with_file(&file.src, id).parse() {
+            Ok(stylesheets) => stylesheets,
+            Err(error) => return Some(error.to_less_error(file).to_string()),
+        };
+        // the options less.js runs its error fixtures with
+        let options = Options {
+            strict_units: true,
+            math: options::Math::Parens,
+            ..Default::default()
+        };
+        let error = to_css(&stylesheets, &options).err()?;
+        Some(error.to_less_error(&files).to_string())
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../less-test-data/test-data/errors");
+    let mut paths = Vec::new();
+    collect(&root, &mut paths);
+    paths.sort();
+
+    let mut failed = Vec::new();
+    let mut fixed = Vec::new();
+    for path in &paths {
+        let Ok(expected) = fs::read_to_string(path.with_extension("txt")) else {
+            continue;
+        };
+        let dir = format!("{}/", path.parent().unwrap().to_str().unwrap());
+        // the placeholders of the less.js test runner, as it fills them in for node
+        let expected = expected
+            .replace("{path}", &dir)
+            .replace("{pathhref}", "")
+            .replace("{404status}", "")
+            .replace("{node}", "")
+            .replace("{/node}", "");
+        let name = path
+            .strip_prefix(&root)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .replace('\\', "/");
+        // a panic fails the fixture even when it is skipped
+        let actual = match panic::catch_unwind(|| render_error(path)) {
+            Ok(actual) => actual,
+            Err(_) => {
+                failed.push(format!("{name}: panicked"));
+                continue;
+            }
+        };
+        let matches = actual.as_deref() == Some(expected.trim_end_matches('\n'));
+        let skipped = SKIPPED.iter().any(|(skipped, _)| *skipped == name);
+        if matches && skipped {
+            fixed.push(name);
+        } else if !matches && !skipped {
+            failed.push(format!(
+                "{}\n  expected: {}\n  actual: {:?}",
+                name,
+                expected.trim_end_matches('\n'),
+                actual
+            ));
+        }
+    }
+    assert!(
+        failed.is_empty(),
+        "error fixtures failed:\n{}",
+        failed.join("\n")
+    );
+    assert!(
+        fixed.is_empty(),
+        "error fixtures pass, remove them from SKIPPED: {:?}",
+        fixed
+    );
+}
+
+#[test]
+fn compile_api() {
+    use less_error::codes::Code;
+
+    let directory = std::env::temp_dir().join(format!("less-compile-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("vars.less"), "@size: 1px;\n").unwrap();
+    let options = CompileOptions {
+        filename: Some(directory.join("main.less")),
+        global_vars: [("color".to_string(), "red".to_string())].into(),
+        ..Default::default()
+    };
+
+    let output = compile(
+        "@import 'vars';\n.a { b: @color @size; c: foo(1); }\n",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(output.css, ".a {\n  b: red 1px;\n  c: foo(1);\n}\n");
+    assert_eq!(output.imports, [directory.join("vars.less")]);
+    assert_eq!(output.warnings.len(), 1);
+    assert_eq!(output.warnings[0].code, Some(Code::UnknownFunction.into()));
+    assert!(output.map.is_none());
+
+    std::fs::write(directory.join("main.less"), ".a { b: @nope; }\n").unwrap();
+    let error = compile_file(&directory.join("main.less"), &options).unwrap_err();
+    assert_eq!(error.diagnostics.len(), 1);
+    assert!(error
+        .to_string()
+        .starts_with("error[L0006]: variable @nope is undefined\n"));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn compile_in_memory() {
+    let mut file_system = import::MemoryFileSystem::default();
+    file_system.insert("styles/vars.less", "@color: red;\n");
+    file_system.insert(
+        "styles/theme/dark.less",
+        "@import '../vars';\n.dark { color: @color; }\n",
+    );
+    let options = CompileOptions {
+        filename: Some("styles/main.less".into()),
+        ..Default::default()
+    };
+    let output = compile_with("@import './theme/dark';", &options, Arc::new(file_system)).unwrap();
+    assert_eq!(output.css, ".dark {\n  color: red;\n}\n");
+    assert_eq!(
+        output.imports,
+        [
+            PathBuf::from("styles/theme/dark.less"),
+            PathBuf::from("styles/vars.less")
+        ]
+    );
+}
+
+#[test]
+fn optional_imports() {
+    use less_error::codes::Code;
+
+    let mut file_system = import::MemoryFileSystem::default();
+    file_system.insert("lib/mixins.less", ".m { a: b; }\n");
+    let options = CompileOptions {
+        include_paths: vec!["lib".into()],
+        ..Default::default()
+    };
+    // found in an include path, and missing from the file system while src/lib.rs is on the disk
+    let source = "@import (optional) \"mixins\";\n@import (optional, less) \"src/lib.rs\";\n";
+    let output = compile_with(source, &options, Arc::new(file_system)).unwrap();
+    assert_eq!(output.css, ".m {\n  a: b;\n}\n");
+    let codes: Vec<_> = output
+        .warnings
+        .iter()
+        .map(|warning| warning.code.clone().unwrap())
+        .collect();
+    assert_eq!(codes, [String::from(Code::MissingOptionalImport)]);
+}
+
+#[test]
+fn built_stylesheet() {
+    use less_ast::builder::StylesheetBuilder;
+
+    let sheet = StylesheetBuilder::new()
+        .variable("@brand", "#00aaff")
+        .rule(".btn, .link", |rule| {
+            rule.decl("color", "@brand")
+                .decl("border", "1px solid rgba(0, 0, 0, 0.5)")
+                .nest("&:hover", |rule| rule.decl("margin", "0 auto"))
+        })
+        .at_rule("@media", "print", |block| {
+            block.rule(".btn", |rule| rule.decl("display", "none !important"))
+        })
+        .build();
+    assert_eq!(
+        to_css(&sheet, &Options::default()).unwrap(),
+        ".btn,\n.link {\n  color: #00aaff;\n  border: 1px solid rgba(0, 0, 0, 0.5);\n}\n.btn:hover,\n.link:hover {\n  margin: 0 auto;\n}\n@media print {\n  .btn {\n    display: none !important;\n  }\n}\n"
+    );
+}
+
+#[test]
+fn custom_functions() {
+    use functions::{FunctionError, Functions};
+    use value::{Color, Number, Value};
+
+    let mut functions = Functions::new();
+    functions
+        .register("rem", |px: Number| {
+            Ok(Number {
+                value: px.value / 16.0,
+                unit: Some("rem".to_string()),
+            })
+        })
+        .register("brand-color", |name: String| match name.as_str() {
+            "primary" => Ok(Color::from_hex("#0055ff").unwrap()),
+            _ => Err(FunctionError::Message(format!("no brand color {}", name))),
+        })
+        // wins over the builtin
+        .register("rgb", |_: f64, _: f64, _: f64| {
+            Ok(Value::Keyword("rgb".to_string()))
+        });
+    let mut scope1 = Functions::new();
+    scope1.register("foo", || Ok(Value::Keyword("foo".to_string())));
+    let mut scope2 = Functions::new();
+    scope2.register("foo", || Ok(Value::Keyword("bar".to_string())));
+    let options = CompileOptions {
+        options: Options {
+            functions,
+            function_plugins: [
+                ("plugin-scope1".to_string(), scope1),
+                ("plugin-scope2".to_string(), scope2),
+            ]
+            .into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let compile = |source: &str| compile(source, &options).map(|output| output.css);
+
+    assert_eq!(
+        compile(".a { b: rem(24px) brand-color(primary); c: rgb(1, 2, 3); }").unwrap(),
+        ".a {\n  b: 1.5rem #0055ff;\n  c: rgb;\n}\n"
+    );
+    // each block sees the plugins of its own and its parents' blocks
+    assert_eq!(
+        compile(
+            "@plugin \"plugin-scope1\";\n.a { value: foo(); }\n.b { @plugin \"../../plugin/plugin-scope2\"; value: foo(); .c { d: foo(); } }\n.a { value: foo(); }\n"
+        )
+        .unwrap(),
+        ".a {\n  value: foo;\n}\n.b {\n  value: bar;\n}\n.b .c {\n  d: bar;\n}\n.a {\n  value: foo;\n}\n"
+    );
+    assert_eq!(
+        compile(".a { @plugin \"plugin-scope2\"; } .b { c: foo(); }").unwrap(),
+        ".b {\n  c: foo();\n}\n"
+    );
+
+    assert_eq!(
+        compile(".a {\n  b: brand-color(secondary);\n}\n")
+            .unwrap_err()
+            .to_string(),
+        "\
+error[L0010]: Error evaluating function `brand-color`: no brand color secondary
+ --> input:2:6
+  |
+2 |   b: brand-color(secondary);
+  |      ^^^^^^^^^^^^^^^^^^^^^^ no brand color secondary
+"
+    );
+    assert_eq!(
+        compile(".a {\n  b: rem(red);\n}\n")
+            .unwrap_err()
+            .diagnostics[0]
+            .code
+            .as_deref(),
+        Some("L0010")
+    );
+    assert_eq!(
+        compile("@plugin \"missing\";\n").unwrap_err().diagnostics[0].message,
+        "plugin missing is not registered"
+    );
+}
+
+#[test]
+fn url_fixtures() {
+    use options::{Math, RewriteUrls};
+
+    // the rules from the last `from` up to `to` are cut from both files
+    let fixture = |name: &str, options: Options, cut: Option<(&str, &str)>| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../less-test-data/test-data");
+        let path = root.join("less").join(name).with_extension("less");
+        let read = |path: &Path| {
+            let text = std::fs::read_to_string(path).unwrap();
+            match cut {
+                Some((from, to)) => {
+                    let start = text.rfind(from).unwrap();
+                    let end = text.find(to).unwrap();
+                    format!("{}{}", &text[..start], &text[end..])
+                }
+                None => text,
+            }
+        };
+        let options = CompileOptions {
+            filename: Some(path.clone()),
+            options,
+            ..Default::default()
+        };
+        let css = compile(&read(&path), &options).unwrap().css;
+        let expected = read(&root.join("css").join(name).with_extension("css"));
+        assert_eq!(css.trim(), expected.trim(), "{}", name);
+    };
+    fixture(
+        "rewrite-urls-all/rewrite-urls-all",
+        Options {
+            rewrite_urls: RewriteUrls::All,
+            ..Default::default()
+        },
+        None,
+    );
+    fixture(
+        "static-urls/urls",
+        Options {
+            rootpath: Some("folder (1)/".to_string()),
+            math: Math::Parens,
+            ..Default::default()
+        },
+        None,
+    );
+    // data-uri() and svg-gradient() are not implemented
+    fixture(
+        "url-args/urls",
+        Options {
+            url_args: Some("424242".to_string()),
+            math: Math::Parens,
+            ..Default::default()
+        },
+        Some(("#data-uri {", "#data-uri-with-spaces")),
+    );
+}
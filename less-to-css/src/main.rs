@@ -1,46 +1,74 @@
-use std::{collections::HashMap, fmt::Write, path::Display};
-
-use less_ast::{
-    ast::{AtKeyword, Atom, ComponentValueList, Stylesheets},
-    visitor::Visitor,
+use std::{
+    env, fs,
+    io::{stderr, IsTerminal},
+    process,
 };
 
-struct ToCss<T: Write> {
-    result: T,
-}
+use less_ast::source::SourceFiles;
+use less_error::diagnostic::{to_json_lines, Diagnostic, Renderer};
+use less_parser::Parser;
+use less_to_css::{options::Options, render};
 
-impl<T: Write> ToCss<T> {
-    fn fmt_ident(&mut self, ident: Atom) {
-        self.result.write_str(&ident).unwrap();
+fn main() {
+    let mut options = Options::default();
+    let mut path = None;
+    let mut renderer = Renderer::new(stderr().is_terminal());
+    let mut json = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-x" | "--compress" => options.compress = true,
+            "--warnings-as-errors" => options.warnings_as_errors = true,
+            "--color" => renderer = Renderer::ansi(),
+            "--no-color" => renderer = Renderer::plain(),
+            "--error-format=json" => json = true,
+            "--error-format=human" => json = false,
+            _ => path = Some(arg),
+        }
     }
-}
-
-impl<T: Write> Visitor for ToCss<T> {
-    fn visit_stylesheets(&mut self, stylesheets: &mut Stylesheets) {
-        self.fmt_ident("stylesheets".to_string());
+    let Some(path) = path else {
+        eprintln!(
+            "usage: less-to-css [-x|--compress] [--warnings-as-errors] [--color|--no-color] [--error-format=human|json] <file.less>"
+        );
+        process::exit(1);
+    };
+    let source = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    });
+    let mut files = SourceFiles::new();
+    let id = files.add(path, source);
+    let file = files.get(id).unwrap();
+    let output = Output { renderer, json };
+    let mut parser = Parser::with_file(&file.src, id);
+    let stylesheets = parser
+        .parse()
+        .unwrap_or_else(|e| exit(&output, &e.to_diagnostic(file), &files));
+    match render(&stylesheets, &files, &options) {
+        Ok(result) => {
+            report(&output, &result.warnings, &files);
+            print!("{}", result.css);
+        }
+        Err(e) => exit(&output, &e.to_diagnostic(), &files),
     }
 }
 
-struct Context {
-    // collect current level all variables defined
-    // @a: 123;
-    // @a: {};
-    // #a(){}
-    // #namespace {}
-    symbol_table: HashMap<String, String>,
-    parent: Option<Box<Context>>
+// how diagnostics are written to stderr
+struct Output {
+    renderer: Renderer,
+    json: bool,
 }
 
-fn main() {
-    // let source = r#"
-    // @color : 2 *;
+fn report(output: &Output, diagnostics: &[Diagnostic], files: &SourceFiles) {
+    if output.json {
+        eprint!("{}", to_json_lines(diagnostics, files));
+        return;
+    }
+    for diagnostic in diagnostics {
+        eprint!("{}", output.renderer.render(diagnostic, files));
+    }
+}
 
-    // "#;
-    // let mut parser = Parser::new(source);
-    // let result = parser.parse();
-    let mut to_css = ToCss {
-        result: String::new(),
-    };
-    to_css.fmt_ident("hello".to_string());
-    println!("{:#?}", to_css.result);
+fn exit(output: &Output, diagnostic: &Diagnostic, files: &SourceFiles) -> ! {
+    report(output, std::slice::from_ref(diagnostic), files);
+    process::exit(1);
 }
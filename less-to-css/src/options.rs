@@ -0,0 +1,117 @@
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr, sync::Arc};
+
+use crate::{functions::Functions, plugin::Plugin, wasm::WasmLimits};
+
+/// which urls are rewritten relative to the entry file
+/// --rewrite-urls=off|local|all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RewriteUrls {
+    #[default]
+    Off,
+    // only urls starting with `.`
+    Local,
+    // every url that is not absolute
+    All,
+}
+
+impl FromStr for RewriteUrls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(RewriteUrls::Off),
+            "local" => Ok(RewriteUrls::Local),
+            "all" => Ok(RewriteUrls::All),
+            _ => Err(format!("invalid rewrite-urls value: {}", s)),
+        }
+    }
+}
+
+/// which operations are evaluated, --math=always|parens-division|parens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Math {
+    // everywhere, as less 3 did
+    Always,
+    // `/` only inside parentheses, `font: 12px/1.5` is left as is
+    #[default]
+    ParensDivision,
+    // every operation only inside parentheses
+    Parens,
+}
+
+impl FromStr for Math {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" | "0" => Ok(Math::Always),
+            "parens-division" | "1" => Ok(Math::ParensDivision),
+            "parens" | "strict" | "2" => Ok(Math::Parens),
+            _ => Err(format!("invalid math value: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub rewrite_urls: RewriteUrls,
+    // prepended to every relative url, e.g. `http://example.com/assets/css/`
+    pub rootpath: Option<String>,
+    // appended to every url as a query string, e.g. `424242`
+    pub url_args: Option<String>,
+    // generate a source map when set
+    pub source_map: Option<SourceMapOptions>,
+    // --compress, minified output
+    pub compress: bool,
+    // --warnings-as-errors, fail on the first warning that is not silenced
+    pub warnings_as_errors: bool,
+    pub math: Math,
+    // --strict-units, `1px + 1em` is an error instead of `2px`
+    pub strict_units: bool,
+    // native functions callable everywhere, before the builtins
+    pub functions: Functions,
+    // functions that `@plugin "name";` adds to its block, matched by the file stem of the name
+    pub function_plugins: BTreeMap<String, Functions>,
+    // the sandbox of `.wasm` modules loaded by `@plugin` when no function plugin matches
+    pub wasm_limits: WasmLimits,
+}
+
+/// --source-map and the --source-map-* flags
+#[derive(Debug, Clone, Default)]
+pub struct SourceMapOptions {
+    // path of the written map, used for the annotation when there is no `url`
+    pub filename: Option<String>,
+    // name of the generated css file, written to the map's `file`
+    pub output_filename: Option<String>,
+    // the url in `/*# sourceMappingURL=... */`
+    pub url: Option<String>,
+    // prepended to every path in `sources`
+    pub rootpath: Option<String>,
+    // removed from the start of every path in `sources`
+    pub basepath: Option<String>,
+    // put the whole map in the annotation as a base64 data uri
+    pub inline: bool,
+    // include the less sources as `sourcesContent`
+    pub include_sources: bool,
+    // do not append the `/*# sourceMappingURL=... */` comment
+    pub disable_annotation: bool,
+}
+
+/// variable name, with or without `@`, to the source of its value, e.g. `"1px solid red"`
+pub type Variables = BTreeMap<String, String>;
+
+/// `Options` and what is needed to load the file and its imports
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    pub options: Options,
+    // the source in messages and source maps, relative imports start from its directory
+    pub filename: Option<PathBuf>,
+    // searched for imports not found next to the importing file
+    pub include_paths: Vec<PathBuf>,
+    // --global-var, declared before the file so that it can override them
+    pub global_vars: Variables,
+    // --modify-var, declared after the file so that they win
+    pub modify_vars: Variables,
+    // run in order at every step of the compilation
+    pub plugins: Vec<Arc<dyn Plugin>>,
+}
@@ -0,0 +1,152 @@
+use std::{fmt, sync::Arc};
+
+use less_ast::ast::Stylesheets;
+
+use crate::css::Stylesheet;
+
+/**
+ * hooks into `compile`, every hook defaults to leaving its input alone
+ * and plugins run in the order they are registered in `CompileOptions::plugins`
+ *
+ * `pre_eval` gets the parsed ast, walk it with `less_ast::visitor::{VisitMut, Fold}`
+ * `post_eval` gets the evaluated `css::Stylesheet` instead, which is not a `less_ast` tree:
+ * its selectors are plain strings and the visitors don't apply, walk it with
+ * `Stylesheet::walk_mut` or match on `css::Node` directly
+ */
+pub trait Plugin: Send + Sync {
+    /// shown when the options are printed
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// the source of every file before it is parsed, `file` as named in messages
+    fn pre_process(&self, source: String, _file: &str) -> String {
+        source
+    }
+
+    /// the stylesheet with its imports inlined and the variables of the options applied
+    fn pre_eval(&self, _stylesheets: &mut Stylesheets) {}
+
+    /// the evaluated css tree, before it is printed
+    fn post_eval(&self, _stylesheet: &mut Stylesheet) {}
+
+    /// the css before the source map annotation is added, the mappings are not moved
+    fn post_process(&self, css: String) -> String {
+        css
+    }
+}
+
+impl fmt::Debug for dyn Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+pub fn pre_process(plugins: &[Arc<dyn Plugin>], source: String, file: &str) -> String {
+    plugins
+        .iter()
+        .fold(source, |source, plugin| plugin.pre_process(source, file))
+}
+
+pub fn pre_eval(plugins: &[Arc<dyn Plugin>], stylesheets: &mut Stylesheets) {
+    for plugin in plugins {
+        plugin.pre_eval(stylesheets);
+    }
+}
+
+pub fn post_eval(plugins: &[Arc<dyn Plugin>], stylesheet: &mut Stylesheet) {
+    for plugin in plugins {
+        plugin.post_eval(stylesheet);
+    }
+}
+
+pub fn post_process(plugins: &[Arc<dyn Plugin>], css: String) -> String {
+    plugins
+        .iter()
+        .fold(css, |css, plugin| plugin.post_process(css))
+}
+
+#[cfg(test)]
+fn compile_with_plugins(less: &str, plugins: Vec<Arc<dyn Plugin>>) -> String {
+    let options = crate::options::CompileOptions {
+        plugins,
+        ..Default::default()
+    };
+    crate::compile(less, &options).unwrap().css
+}
+
+#[test]
+fn plugins() {
+    use less_ast::{ast::*, visitor::*};
+
+    use crate::css::Node;
+
+    struct PreProcessor;
+    impl Plugin for PreProcessor {
+        fn pre_process(&self, source: String, _: &str) -> String {
+            format!("@color: red;\n{}", source)
+        }
+    }
+    let less =
+        include_str!("../../less-test-data/test-data/less/preProcessorPlugin/preProcessor.less");
+    let css =
+        include_str!("../../less-test-data/test-data/css/preProcessorPlugin/preProcessor.css");
+    assert_eq!(
+        compile_with_plugins(less, vec![Arc::new(PreProcessor)]),
+        css
+    );
+
+    // removes the declarations less doesn't know, on the ast
+    struct Visitor;
+    impl Fold for Visitor {
+        fn fold_declaration_list(&mut self, list: DeclarationList) -> DeclarationList {
+            list.into_iter()
+                .filter(|declaration| !declaration.name.starts_with("-some-"))
+                .collect()
+        }
+    }
+    impl Plugin for Visitor {
+        fn pre_eval(&self, stylesheets: &mut Stylesheets) {
+            stylesheets.content = std::mem::take(&mut stylesheets.content).fold_with(&mut Visitor);
+        }
+    }
+    let less = include_str!("../../less-test-data/test-data/less/visitorPlugin/visitor.less");
+    let css = include_str!("../../less-test-data/test-data/css/visitorPlugin/visitor.css");
+    assert_eq!(compile_with_plugins(less, vec![Arc::new(Visitor)]), css);
+
+    struct PostProcessor;
+    impl Plugin for PostProcessor {
+        fn post_process(&self, css: String) -> String {
+            format!("hr {{height:50px;}}\n{}", css)
+        }
+    }
+    let less =
+        include_str!("../../less-test-data/test-data/less/postProcessorPlugin/postProcessor.less");
+    let css =
+        include_str!("../../less-test-data/test-data/css/postProcessorPlugin/postProcessor.css");
+    assert_eq!(
+        compile_with_plugins(less, vec![Arc::new(PostProcessor)]),
+        css
+    );
+
+    // runs after evaluation, the selectors are strings by then
+    struct Prefix;
+    impl Plugin for Prefix {
+        fn post_eval(&self, stylesheet: &mut Stylesheet) {
+            stylesheet.walk_mut(|node| {
+                if let Node::Rule(rule) = node {
+                    for selector in &mut rule.selectors {
+                        selector.insert_str(0, ".theme ");
+                    }
+                }
+            });
+        }
+    }
+    assert_eq!(
+        compile_with_plugins(
+            "@a: 1px; .a { b: @a; } @media print { .b { c: d; } }",
+            vec![Arc::new(Prefix), Arc::new(PostProcessor)]
+        ),
+        "hr {height:50px;}\n.theme .a {\n  b: 1px;\n}\n@media print {\n  .theme .b {\n    c: d;\n  }\n}\n"
+    );
+}
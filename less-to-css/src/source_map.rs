@@ -0,0 +1,220 @@
+use less_ast::{
+    ast::Span,
+    source::{FileId, SourceFiles},
+};
+use serde::Serialize;
+
+use crate::options::SourceMapOptions;
+
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// a generated position, 0-based, and the less node it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub generated_line: usize,
+    pub generated_column: usize,
+    pub span: Span,
+}
+
+/// https://sourcemaps.info/spec.html
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMap {
+    pub version: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub sources: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources_content: Option<Vec<String>>,
+    pub names: Vec<String>,
+    pub mappings: String,
+}
+
+impl SourceMap {
+    pub fn new(mappings: &[Mapping], files: &SourceFiles, options: &SourceMapOptions) -> Self {
+        // sources are listed in the order they are first used
+        let mut used: Vec<FileId> = Vec::new();
+        let mut encoded = String::new();
+        let mut line = 0;
+        let mut previous_column = 0;
+        let mut previous_source = 0;
+        let mut previous_original_line = 0;
+        let mut previous_original_column = 0;
+
+        for mapping in mappings {
+            let Some(file) = files.get(mapping.span.file) else {
+                continue;
+            };
+            let source = match used.iter().position(|id| *id == file.id) {
+                Some(index) => index,
+                None => {
+                    used.push(file.id);
+                    used.len() - 1
+                }
+            };
+            let original = file.position(mapping.span.start);
+            let original_line = original.line - 1;
+
+            if mapping.generated_line != line {
+                for _ in line..mapping.generated_line {
+                    encoded.push(';');
+                }
+                line = mapping.generated_line;
+                previous_column = 0;
+            } else if !encoded.is_empty() && !encoded.ends_with(';') {
+                encoded.push(',');
+            }
+            encode_vlq(&mut encoded, mapping.generated_column, previous_column);
+            encode_vlq(&mut encoded, source, previous_source);
+            encode_vlq(&mut encoded, original_line, previous_original_line);
+            encode_vlq(
+                &mut encoded,
+                original.utf16_column,
+                previous_original_column,
+            );
+
+            previous_column = mapping.generated_column;
+            previous_source = source;
+            previous_original_line = original_line;
+            previous_original_column = original.utf16_column;
+        }
+
+        let used_files = used.iter().filter_map(|id| files.get(*id));
+        Self {
+            version: 3,
+            file: options.output_filename.clone(),
+            sources: used_files
+                .clone()
+                .map(|file| source_name(&file.name, options))
+                .collect(),
+            sources_content: options
+                .include_sources
+                .then(|| used_files.map(|file| file.src.clone()).collect()),
+            names: Vec::new(),
+            mappings: encoded,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn to_data_uri(&self) -> String {
+        format!(
+            "data:application/json;base64,{}",
+            base64_encode(self.to_json().as_bytes())
+        )
+    }
+}
+
+/// the annotation appended to the css, None when there is no url to point at
+pub fn annotation(map: &SourceMap, options: &SourceMapOptions) -> Option<String> {
+    if options.disable_annotation {
+        return None;
+    }
+    let url = if options.inline {
+        map.to_data_uri()
+    } else {
+        options.url.clone().or_else(|| options.filename.clone())?
+    };
+    Some(format!("/*# sourceMappingURL={} */", url))
+}
+
+// a/b.less -> {rootpath}{path without basepath}
+fn source_name(name: &str, options: &SourceMapOptions) -> String {
+    let mut name = name.replace('\\', "/");
+    if let Some(basepath) = &options.basepath {
+        let basepath = basepath.replace('\\', "/");
+        let basepath = basepath.trim_end_matches('/');
+        // only a whole directory is stripped, /a/b is no prefix of /a/bc.less
+        if let Some(stripped) = name.strip_prefix(&format!("{}/", basepath)) {
+            name = stripped.trim_start_matches('/').to_string();
+        }
+    }
+    match &options.rootpath {
+        Some(rootpath) => format!("{}{}", rootpath, name),
+        None => name,
+    }
+}
+
+// base64 vlq of the difference to the previous value
+fn encode_vlq(result: &mut String, value: usize, previous: usize) {
+    let delta = value as i64 - previous as i64;
+    let mut vlq = if delta < 0 {
+        ((-delta) << 1) | 1
+    } else {
+        delta << 1
+    };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        result.push(BASE64_CHARS[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let triple = (buffer[0] as u32) << 16 | (buffer[1] as u32) << 8 | buffer[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (triple >> (18 - index * 6)) & 0b111111;
+                result.push(BASE64_CHARS[sextet as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[test]
+fn vlq() {
+    let mut result = String::new();
+    for (value, previous) in [(0, 0), (1, 0), (0, 1), (16, 0), (0, 16), (1000, 0)] {
+        encode_vlq(&mut result, value, previous);
+        result.push(',');
+    }
+    assert_eq!(result, "A,C,D,gB,hB,w+B,");
+}
+
+#[test]
+fn base64() {
+    assert_eq!(base64_encode(b""), "");
+    assert_eq!(base64_encode(b"f"), "Zg==");
+    assert_eq!(base64_encode(b"fo"), "Zm8=");
+    assert_eq!(base64_encode(b"foo"), "Zm9v");
+    assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+}
+
+#[test]
+fn source_names() {
+    let options = SourceMapOptions {
+        basepath: Some("C:\\less".to_string()),
+        rootpath: Some("http://example.com/".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        source_name("C:\\less\\a\\b.less", &options),
+        "http://example.com/a/b.less"
+    );
+    assert_eq!(
+        source_name("C:\\lessons\\b.less", &options),
+        "http://example.com/C:/lessons/b.less"
+    );
+    assert_eq!(
+        source_name("b.less", &SourceMapOptions::default()),
+        "b.less"
+    );
+}
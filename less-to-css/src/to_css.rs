@@ -0,0 +1,236 @@
+use std::fmt::{self, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+use less_ast::ast::Span;
+
+use crate::css::{AtRule, Comment, Declaration, Node, Rule, Stylesheet};
+use crate::source_map::Mapping;
+
+pub struct ToCss<T: Write> {
+    pub result: T,
+    // where each selector, declaration and at-rule starts in the output
+    pub mappings: Vec<Mapping>,
+    // no optional whitespace, semicolons or comments
+    compress: bool,
+    indent: usize,
+    // 0-based, the column counts utf-16 code units as source maps do
+    line: usize,
+    column: usize,
+}
+
+impl<T: Write> ToCss<T> {
+    pub fn new(result: T, compress: bool) -> Self {
+        Self {
+            result,
+            mappings: Vec::new(),
+            compress,
+            indent: 0,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    fn write_str(&mut self, value: &str) -> fmt::Result {
+        match value.rfind('\n') {
+            Some(pos) => {
+                self.line += value.matches('\n').count();
+                self.column = value[pos + 1..].encode_utf16().count();
+            }
+            None => self.column += value.encode_utf16().count(),
+        }
+        self.result.write_str(value)
+    }
+
+    fn add_mapping(&mut self, span: &Span) {
+        self.mappings.push(Mapping {
+            generated_line: self.line,
+            generated_column: self.column,
+            span: span.clone(),
+        });
+    }
+
+    fn write_indent(&mut self) -> fmt::Result {
+        if self.compress {
+            return Ok(());
+        }
+        for _ in 0..self.indent {
+            self.write_str("  ")?;
+        }
+        Ok(())
+    }
+
+    pub fn write_stylesheet(&mut self, stylesheet: &Stylesheet) -> fmt::Result {
+        self.write_nodes(&stylesheet.nodes)
+    }
+
+    // the newline after a block or declaration
+    fn write_newline(&mut self) -> fmt::Result {
+        if self.compress {
+            return Ok(());
+        }
+        self.write_str("\n")
+    }
+
+    fn write_nodes(&mut self, nodes: &[Node]) -> fmt::Result {
+        let compress = self.compress;
+        let nodes: Vec<&Node> = nodes
+            .iter()
+            .filter(|node| !matches!(node, Node::Comment(comment) if comment.is_silent(compress)))
+            .collect();
+        for (index, node) in nodes.iter().enumerate() {
+            match node {
+                Node::Rule(rule) => self.write_rule(rule)?,
+                Node::AtRule(at_rule) => self.write_at_rule(at_rule)?,
+                Node::Declaration(declaration) => {
+                    self.write_declaration(declaration, index + 1 == nodes.len())?
+                }
+                Node::Comment(comment) => self.write_comment(comment)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_rule(&mut self, rule: &Rule) -> fmt::Result {
+        for (index, selector) in rule.selectors.iter().enumerate() {
+            if index > 0 {
+                self.write_str(",")?;
+                self.write_newline()?;
+            }
+            self.write_indent()?;
+            self.add_mapping(&rule.span);
+            if self.compress {
+                self.write_str(&compress_selector(selector))?;
+            } else {
+                self.write_str(selector)?;
+            }
+        }
+        self.write_block_start()?;
+        self.indent += 1;
+        self.write_nodes(&rule.declarations)?;
+        self.indent -= 1;
+        self.write_block_end()
+    }
+
+    fn write_block_start(&mut self) -> fmt::Result {
+        self.write_str(if self.compress { "{" } else { " {" })?;
+        self.write_newline()
+    }
+
+    fn write_block_end(&mut self) -> fmt::Result {
+        self.write_indent()?;
+        self.write_str("}")?;
+        self.write_newline()
+    }
+
+    // the semicolon of the last declaration in a block is dropped when compressing
+    fn write_declaration(&mut self, declaration: &Declaration, last: bool) -> fmt::Result {
+        self.write_indent()?;
+        self.add_mapping(&declaration.span);
+        let separator = if self.compress { ":" } else { ": " };
+        let value = declaration.value.to_css(self.compress);
+        self.write_str(&format!("{}{}{}", declaration.name, separator, value))?;
+        if declaration.important {
+            self.write_str(" !important")?;
+        }
+        if !(self.compress && last) {
+            self.write_str(";")?;
+        }
+        self.write_newline()
+    }
+
+    fn write_comment(&mut self, comment: &Comment) -> fmt::Result {
+        self.write_indent()?;
+        self.add_mapping(&comment.span);
+        self.write_str(&comment.value)?;
+        self.write_newline()
+    }
+
+    fn write_at_rule(&mut self, at_rule: &AtRule) -> fmt::Result {
+        self.write_indent()?;
+        self.add_mapping(&at_rule.span);
+        self.write_str(&at_rule.name)?;
+        if let Some(prelude) = &at_rule.prelude {
+            self.write_str(&format!(" {}", prelude.to_css(self.compress)))?;
+        }
+        match &at_rule.block {
+            Some(nodes) => {
+                self.write_block_start()?;
+                self.indent += 1;
+                self.write_nodes(nodes)?;
+                self.indent -= 1;
+                self.write_block_end()
+            }
+            None => {
+                self.write_str(";")?;
+                self.write_newline()
+            }
+        }
+    }
+}
+
+// `.a > .b ~ .c` -> `.a>.b~.c`, whitespace inside [], () and strings is kept
+fn compress_selector(selector: &str) -> String {
+    let mut result = String::with_capacity(selector.len());
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = selector.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(q), _) if ch == q => quote = None,
+            (_, '\\') => {
+                push_escape(&mut result, &mut chars);
+                continue;
+            }
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth -= 1,
+            (None, _) if depth == 0 && ch.is_whitespace() => {
+                let next_is_combinator = matches!(chars.peek(), Some('>' | '+' | '~' | '^' | ' '));
+                let after_combinator = matches!(result.chars().last(), Some('>' | '+' | '~' | '^'));
+                if next_is_combinator || after_combinator {
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        result.push(ch);
+    }
+    result
+}
+
+// \\34 keeps its hex digits and the whitespace ending it
+fn push_escape(result: &mut String, chars: &mut Peekable<Chars>) {
+    result.push('\\');
+    let Some(ch) = chars.next() else {
+        return;
+    };
+    result.push(ch);
+    if !ch.is_ascii_hexdigit() {
+        return;
+    }
+    for _ in 1..6 {
+        match chars.next_if(|ch| ch.is_ascii_hexdigit()) {
+            Some(ch) => result.push(ch),
+            None => break,
+        }
+    }
+    if let Some(ch) = chars.next_if(|ch| ch.is_whitespace()) {
+        result.push(ch);
+    }
+}
+
+#[test]
+fn compress_selectors() {
+    assert_eq!(compress_selector(".a > .b"), ".a>.b");
+    assert_eq!(compress_selector(".shadow ^ .dom"), ".shadow^.dom");
+    assert_eq!(compress_selector("body ^^ .shadow"), "body^^.shadow");
+    assert_eq!(compress_selector(".a  .b + .c"), ".a .b+.c");
+    assert_eq!(
+        compress_selector("[title=\"a > b\"] ~ .c"),
+        "[title=\"a > b\"]~.c"
+    );
+    assert_eq!(compress_selector(":not(.a > .b)"), ":not(.a > .b)");
+    assert_eq!(compress_selector(".\\34  .b"), ".\\34  .b");
+}
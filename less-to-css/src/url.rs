@@ -0,0 +1,286 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::options::{Options, RewriteUrls};
+
+// ^(?:[a-z-]+:|\/|#)
+fn is_path_relative(path: &str) -> bool {
+    if path.starts_with('/') || path.starts_with('#') {
+        return false;
+    }
+    match path.find(':') {
+        Some(pos) => !path[..pos]
+            .chars()
+            .all(|ch| ch.is_ascii_alphabetic() || ch == '-'),
+        None => true,
+    }
+}
+
+fn is_path_local_relative(path: &str) -> bool {
+    path.starts_with('.')
+}
+
+// an unquoted url needs `(`, `)`, quotes and whitespace escaped
+fn escape_path(path: &str) -> String {
+    let mut result = String::with_capacity(path.len());
+    for ch in path.chars() {
+        if matches!(ch, '(' | ')' | '\'' | '"') || ch.is_whitespace() {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// resolve `.` and `..` segments, keeping leading `..` that can not be resolved
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                if segments.is_empty() || segments.last() == Some(&"..") {
+                    segments.push(segment);
+                } else {
+                    segments.pop();
+                }
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+fn rewrite_path(path: &str, rootpath: &str) -> String {
+    let new_path = normalize_path(&format!("{}{}", rootpath, path));
+    // an explicit relative path must stay explicit relative
+    if is_path_local_relative(path)
+        && is_path_relative(rootpath)
+        && !is_path_local_relative(&new_path)
+    {
+        return format!("./{}", new_path);
+    }
+    new_path
+}
+
+/// the rootpath of a file, `file_dir` is the directory of the file relative to the entry file
+/// ```
+/// use less_to_css::{options::{Options, RewriteUrls}, url::file_rootpath};
+/// let options = Options { rewrite_urls: RewriteUrls::All, ..Default::default() };
+/// assert_eq!(file_rootpath(&options, "folder".as_ref()), "folder/");
+/// ```
+pub fn file_rootpath(options: &Options, file_dir: &Path) -> String {
+    let rootpath = options.rootpath.clone().unwrap_or_default();
+    if options.rewrite_urls == RewriteUrls::Off {
+        return rootpath;
+    }
+    let mut diff = String::new();
+    for component in file_dir.components() {
+        match component {
+            Component::ParentDir => diff.push_str("../"),
+            Component::Normal(name) => {
+                diff.push_str(&name.to_string_lossy());
+                diff.push('/');
+            }
+            _ => {}
+        }
+    }
+    rootpath + &diff
+}
+
+/// the directory of `file` relative to the directory of `entry`, without looking at the disk
+/// ```
+/// use less_to_css::url::relative_dir;
+/// assert_eq!(relative_dir("a/main.less".as_ref(), "a/./b/../c/d.less".as_ref()), std::path::Path::new("c"));
+/// assert_eq!(relative_dir("a/main.less".as_ref(), "e.less".as_ref()), std::path::Path::new(".."));
+/// ```
+pub fn relative_dir(entry: &Path, file: &Path) -> PathBuf {
+    fn normalize(path: &Path) -> Vec<Component<'_>> {
+        let mut components: Vec<Component> = Vec::new();
+        for component in path.parent().unwrap_or(Path::new("")).components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir if matches!(components.last(), Some(Component::Normal(_))) => {
+                    components.pop();
+                }
+                component => components.push(component),
+            }
+        }
+        components
+    }
+    let entry = normalize(entry);
+    let file = normalize(file);
+    let common = entry
+        .iter()
+        .zip(&file)
+        .take_while(|(entry, file)| entry == file)
+        .count();
+    let mut result = PathBuf::new();
+    for _ in common..entry.len() {
+        result.push("..");
+    }
+    for component in &file[common..] {
+        result.push(component);
+    }
+    result
+}
+
+/// rewrite the quoted path of a css `@import` found in a file with the given rootpath,
+/// unlike urls the result isn't kept explicit relative
+pub fn rewrite_import(path: &str, rootpath: &str) -> String {
+    if is_path_relative(path) {
+        normalize_path(&format!("{}{}", rootpath, path))
+    } else {
+        normalize_path(path)
+    }
+}
+
+/// rewrite the value of a `url()` found in a file with the given rootpath
+pub fn rewrite_url(url: &str, quoted: bool, rootpath: &str, options: &Options) -> String {
+    let requires_rewrite = match options.rewrite_urls {
+        RewriteUrls::Local => is_path_local_relative(url),
+        _ => is_path_relative(url),
+    };
+    let mut value = if requires_rewrite && quoted {
+        rewrite_path(url, rootpath)
+    } else if requires_rewrite {
+        rewrite_path(url, &escape_path(rootpath))
+    } else {
+        normalize_path(url)
+    };
+
+    if let Some(url_args) = &options.url_args {
+        if !value.trim_start().starts_with("data:") {
+            let delimiter = if value.contains('?') { '&' } else { '?' };
+            let url_args = format!("{}{}", delimiter, url_args);
+            match value.find('#') {
+                Some(pos) => value.insert_str(pos, &url_args),
+                None => value.push_str(&url_args),
+            }
+        }
+    }
+    value
+}
+
+#[test]
+fn rewrite_urls_all() {
+    let options = Options {
+        rewrite_urls: RewriteUrls::All,
+        ..Default::default()
+    };
+    let rewrite = |url| rewrite_url(url, true, "", &options);
+    assert_eq!(rewrite("./relative/path"), "./relative/path");
+    assert_eq!(rewrite("../relative/path"), "../relative/path");
+    assert_eq!(rewrite("./relative/../path"), "./path");
+    assert_eq!(rewrite("./relative/../path/.."), "./");
+    assert_eq!(
+        rewrite("module/path/../relative/path"),
+        "module/relative/path"
+    );
+
+    let rootpath = file_rootpath(&options, Path::new("folder"));
+    let rewrite = |url| rewrite_url(url, true, &rootpath, &options);
+    assert_eq!(rewrite("./relative/path"), "./folder/relative/path");
+    assert_eq!(rewrite("../relative/path"), "./relative/path");
+    assert_eq!(rewrite("../../relative/path"), "../relative/path");
+    assert_eq!(rewrite("module"), "folder/module");
+    assert_eq!(
+        rewrite("module/path/../relative/path"),
+        "folder/module/relative/path"
+    );
+}
+
+#[test]
+fn rewrite_urls_local() {
+    let options = Options {
+        rewrite_urls: RewriteUrls::Local,
+        ..Default::default()
+    };
+    let rootpath = file_rootpath(&options, Path::new("folder"));
+    let rewrite = |url| rewrite_url(url, true, &rootpath, &options);
+    assert_eq!(rewrite("./relative/path"), "./folder/relative/path");
+    assert_eq!(rewrite("../relative/path"), "./relative/path");
+    assert_eq!(rewrite("module"), "module");
+    assert_eq!(
+        rewrite("module/path/../relative/path"),
+        "module/relative/path"
+    );
+}
+
+#[test]
+fn rootpath_rewrite_urls_all() {
+    let options = Options {
+        rewrite_urls: RewriteUrls::All,
+        rootpath: Some("http://example.com/assets/css/".to_string()),
+        ..Default::default()
+    };
+    let rootpath = file_rootpath(&options, Path::new(""));
+    let rewrite = |url| rewrite_url(url, true, &rootpath, &options);
+    assert_eq!(
+        rewrite("./relative/path"),
+        "http://example.com/assets/css/relative/path"
+    );
+    assert_eq!(
+        rewrite("../relative/path"),
+        "http://example.com/assets/relative/path"
+    );
+    assert_eq!(
+        rewrite("./relative/../path/.."),
+        "http://example.com/assets/css"
+    );
+
+    let rootpath = file_rootpath(&options, Path::new("folder"));
+    let rewrite = |url| rewrite_url(url, true, &rootpath, &options);
+    assert_eq!(
+        rewrite("../../relative/path"),
+        "http://example.com/assets/relative/path"
+    );
+    assert_eq!(
+        rewrite("module"),
+        "http://example.com/assets/css/folder/module"
+    );
+}
+
+#[test]
+fn static_urls() {
+    let options = Options {
+        rootpath: Some("folder (1)/".to_string()),
+        ..Default::default()
+    };
+    let rootpath = file_rootpath(&options, Path::new("imports"));
+    assert_eq!(rootpath, "folder (1)/");
+    assert_eq!(
+        rewrite_url("images/image.jpg", false, &rootpath, &options),
+        "folder\\ \\(1\\)/images/image.jpg"
+    );
+    assert_eq!(
+        rewrite_url("/fonts/garamond-pro.ttf", true, &rootpath, &options),
+        "/fonts/garamond-pro.ttf"
+    );
+    assert_eq!(
+        rewrite_url(
+            "http://www.lesscss.org/spec.html",
+            true,
+            &rootpath,
+            &options
+        ),
+        "http://www.lesscss.org/spec.html"
+    );
+}
+
+#[test]
+fn url_args() {
+    let options = Options {
+        url_args: Some("424242".to_string()),
+        ..Default::default()
+    };
+    let rewrite = |url| rewrite_url(url, false, "", &options);
+    assert_eq!(
+        rewrite("fonts.svg#MyGeometricModern"),
+        "fonts.svg?424242#MyGeometricModern"
+    );
+    assert_eq!(rewrite("css?family=a"), "css?family=a&424242");
+    assert_eq!(
+        rewrite("data:image/x-png,f9difSSFIIGFIFJD1f982FSDKAA9=="),
+        "data:image/x-png,f9difSSFIIGFIFJD1f982FSDKAA9=="
+    );
+}
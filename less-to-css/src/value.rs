@@ -0,0 +1,418 @@
+use std::fmt::{self, Display, Write};
+
+use less_ast::ast::Atom;
+
+/// an evaluated less value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(Number),
+    Color(Color),
+    Quoted(Quoted),
+    // idents and any token that is passed through as is
+    Keyword(Atom),
+    Url(Url),
+    // unknown function, printed as is: local(Futura-Medium)
+    Function(Function),
+    List(List),
+    // a: yes /* comment */;
+    Comment(Atom),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Number {
+    pub value: f64,
+    pub unit: Option<Atom>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Color {
+    pub rgb: [f64; 3],
+    pub alpha: f64,
+    // the original spelling, kept until the color is modified
+    pub raw: Option<Atom>,
+}
+
+/// "a" 'a' ~"a"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quoted {
+    // the content between the quotes, escapes are kept as written
+    pub value: String,
+    pub quote: Option<char>,
+    // ~"a" and e("a") are printed without quotes
+    pub escaped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    pub value: String,
+    pub quote: Option<char>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: Atom,
+    pub args: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    Space,
+    Comma,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct List {
+    pub items: Vec<Value>,
+    pub separator: Separator,
+}
+
+impl Quoted {
+    /// parse a string token, `raw` includes the quotes
+    pub fn from_raw(raw: &str, escaped: bool) -> Self {
+        let quote = raw.chars().next().filter(|ch| matches!(ch, '"' | '\''));
+        let value = match quote {
+            Some(quote) => raw[1..].strip_suffix(quote).unwrap_or(&raw[1..]),
+            None => raw,
+        };
+        Quoted {
+            value: value.to_string(),
+            quote,
+            escaped,
+        }
+    }
+
+    /// the string with css escapes decoded: "\"a\" \31 " -> "\"a\" 1"
+    pub fn decoded(&self) -> String {
+        decode_escapes(&self.value)
+    }
+}
+
+/// decode css escape sequences as described in css-syntax-3 4.3.7
+pub fn decode_escapes(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.peek() {
+            None => result.push('\u{fffd}'),
+            // escaped newline is a line continuation
+            Some('\n') => {
+                chars.next();
+            }
+            Some(ch) if ch.is_ascii_hexdigit() => {
+                let mut hex = String::new();
+                while hex.len() < 6 {
+                    match chars.peek() {
+                        Some(ch) if ch.is_ascii_hexdigit() => {
+                            hex.push(*ch);
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                // a single whitespace after the hex digits belongs to the escape
+                if matches!(chars.peek(), Some(ch) if ch.is_whitespace()) {
+                    chars.next();
+                }
+                let code = u32::from_str_radix(&hex, 16).unwrap_or(0xfffd);
+                match char::from_u32(code) {
+                    Some(ch) if code != 0 => result.push(ch),
+                    _ => result.push('\u{fffd}'),
+                }
+            }
+            Some(_) => result.push(chars.next().unwrap()),
+        }
+    }
+    result
+}
+
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let mut result = format!("{:.8}", value);
+    while result.ends_with('0') {
+        result.pop();
+    }
+    if result.ends_with('.') {
+        result.pop();
+    }
+    result
+}
+
+impl Color {
+    pub fn from_hex(raw: &str) -> Option<Self> {
+        let hex = raw.strip_prefix('#')?;
+        let digits: Vec<u32> = hex
+            .chars()
+            .map(|ch| ch.to_digit(16))
+            .collect::<Option<_>>()?;
+        let channels: Vec<f64> = match digits.len() {
+            3 | 4 => digits.iter().map(|d| (d * 17) as f64).collect(),
+            6 | 8 => digits
+                .chunks(2)
+                .map(|pair| (pair[0] * 16 + pair[1]) as f64)
+                .collect(),
+            _ => return None,
+        };
+        Some(Color {
+            rgb: [channels[0], channels[1], channels[2]],
+            alpha: channels.get(3).map(|a| a / 255.0).unwrap_or(1.0),
+            raw: Some(raw.to_string()),
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        let mut result = String::from("#");
+        for channel in self.rgb {
+            write!(result, "{:02x}", channel.round().clamp(0.0, 255.0) as u8).unwrap();
+        }
+        result
+    }
+}
+
+impl Color {
+    // #aabbcc -> #abc
+    fn to_short_hex(&self) -> String {
+        let hex = self.to_hex();
+        let bytes = hex.as_bytes();
+        if bytes[1] == bytes[2] && bytes[3] == bytes[4] && bytes[5] == bytes[6] {
+            return format!(
+                "#{}{}{}",
+                bytes[1] as char, bytes[3] as char, bytes[5] as char
+            );
+        }
+        hex
+    }
+
+    fn write_css(&self, f: &mut dyn Write, compress: bool) -> fmt::Result {
+        if let Some(raw) = &self.raw {
+            return f.write_str(raw);
+        }
+        if self.alpha < 1.0 {
+            let [r, g, b] = self.rgb.map(|c| c.round().clamp(0.0, 255.0));
+            let separator = if compress { "," } else { ", " };
+            return write!(
+                f,
+                "rgba({r}{separator}{g}{separator}{b}{separator}{})",
+                format_number(self.alpha)
+            );
+        }
+        if compress {
+            return f.write_str(&self.to_short_hex());
+        }
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl Number {
+    fn write_css(&self, f: &mut dyn Write, compress: bool) -> fmt::Result {
+        let value = format_number(self.value);
+        // 0.5 -> .5
+        match value.strip_prefix('0') {
+            Some(value) if compress && value.starts_with('.') => f.write_str(value)?,
+            _ => f.write_str(&value)?,
+        }
+        if let Some(unit) = &self.unit {
+            f.write_str(unit)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Quoted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quote {
+            Some(quote) if !self.escaped => write!(f, "{}{}{}", quote, self.value, quote),
+            _ => f.write_str(&self.value),
+        }
+    }
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quote {
+            Some(quote) => write!(f, "url({}{}{})", quote, self.value, quote),
+            None => write!(f, "url({})", self.value),
+        }
+    }
+}
+
+impl Function {
+    fn write_css(&self, f: &mut dyn Write, compress: bool) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        for (index, arg) in self.args.iter().enumerate() {
+            if index > 0 {
+                f.write_str(if compress { "," } else { ", " })?;
+            }
+            arg.write_css(f, compress)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl List {
+    fn write_css(&self, f: &mut dyn Write, compress: bool) -> fmt::Result {
+        let separator = match self.separator {
+            Separator::Space => " ",
+            Separator::Comma if compress => ",",
+            Separator::Comma => ", ",
+        };
+        let items = self.items.iter().filter(|item| !item.is_silent(compress));
+        for (index, item) in items.enumerate() {
+            if index > 0 {
+                f.write_str(separator)?;
+            }
+            item.write_css(f, compress)?;
+        }
+        Ok(())
+    }
+}
+
+impl Value {
+    /// print the value, `compress` drops optional whitespace and zeros
+    pub fn write_css(&self, f: &mut dyn Write, compress: bool) -> fmt::Result {
+        match self {
+            Value::Number(number) => number.write_css(f, compress),
+            Value::Color(color) => color.write_css(f, compress),
+            Value::Quoted(quoted) => write!(f, "{}", quoted),
+            Value::Keyword(keyword) => f.write_str(keyword),
+            Value::Url(url) => write!(f, "{}", url),
+            Value::Function(function) => function.write_css(f, compress),
+            Value::List(list) => list.write_css(f, compress),
+            Value::Comment(_) if self.is_silent(compress) => Ok(()),
+            Value::Comment(comment) => f.write_str(comment),
+        }
+    }
+
+    // comments are stripped when compressing, except `/*! */`
+    pub fn is_silent(&self, compress: bool) -> bool {
+        compress && matches!(self, Value::Comment(comment) if !comment.starts_with("/*!"))
+    }
+
+    pub fn to_css(&self, compress: bool) -> String {
+        let mut result = String::new();
+        self.write_css(&mut result, compress).unwrap();
+        result
+    }
+}
+
+macro_rules! from_variant {
+    ($($ty:ident),*) => {
+        $(impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::$ty(value)
+            }
+        })*
+    };
+}
+
+from_variant!(Number, Color, Quoted, Url, Function, List);
+
+impl Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_css(f, false)
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_css(f, false)
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_css(f, false)
+    }
+}
+
+impl Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_css(f, false)
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_css(f, false)
+    }
+}
+
+#[test]
+fn quoted() {
+    let quoted = Quoted::from_raw(r#""\"hello\" \\world""#, false);
+    assert_eq!(quoted.quote, Some('"'));
+    assert_eq!(quoted.value, r#"\"hello\" \\world"#);
+    assert_eq!(quoted.to_string(), r#""\"hello\" \\world""#);
+    assert_eq!(quoted.decoded(), r#""hello" \world"#);
+
+    let quoted = Quoted::from_raw("'\"\"#!&\"\"'", false);
+    assert_eq!(quoted.to_string(), "'\"\"#!&\"\"'");
+
+    let quoted = Quoted::from_raw("\"DX.Transform.MS.BS.filter(opacity=50)\"", true);
+    assert_eq!(quoted.to_string(), "DX.Transform.MS.BS.filter(opacity=50)");
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(decode_escapes(r"\34 04"), "404");
+    assert_eq!(
+        decode_escapes(r"\62\6c\6f \63 \6B \0071 \000075o\74 e"),
+        "blockquote"
+    );
+    assert_eq!(decode_escapes(r"ng\:cloak"), "ng:cloak");
+    assert_eq!(decode_escapes(r"\5FAE\8F6F"), "微软");
+    assert_eq!(decode_escapes("a\\\nb"), "ab");
+    assert_eq!(decode_escapes(r"\0"), "\u{fffd}");
+}
+
+#[test]
+fn number() {
+    assert_eq!(format_number(1.0), "1");
+    assert_eq!(format_number(54.4), "54.4");
+    assert_eq!(format_number(-0.5), "-0.5");
+    assert_eq!(format_number(1.0 / 3.0), "0.33333333");
+}
+
+#[test]
+fn compress() {
+    let number = |value, unit: Option<&str>| {
+        Value::Number(Number {
+            value,
+            unit: unit.map(|unit| unit.to_string()),
+        })
+    };
+    assert_eq!(number(0.1, Some("px")).to_css(true), ".1px");
+    assert_eq!(number(0.0, Some("px")).to_css(true), "0px");
+    assert_eq!(number(-0.5, None).to_css(true), "-0.5");
+    assert_eq!(number(0.1, Some("px")).to_css(false), "0.1px");
+
+    let white = Color {
+        rgb: [255.0, 255.0, 255.0],
+        alpha: 1.0,
+        raw: None,
+    };
+    assert_eq!(Value::Color(white.clone()).to_css(true), "#fff");
+    assert_eq!(Value::Color(white.clone()).to_css(false), "#ffffff");
+    let translucent = Color {
+        alpha: 0.5,
+        ..white
+    };
+    assert_eq!(
+        Value::Color(translucent).to_css(true),
+        "rgba(255,255,255,0.5)"
+    );
+    let raw = Color::from_hex("#ffeeaa").unwrap();
+    assert_eq!(Value::Color(raw).to_css(true), "#ffeeaa");
+
+    let list = Value::List(List {
+        items: vec![number(1.0, None), number(0.5, Some("em"))],
+        separator: Separator::Comma,
+    });
+    assert_eq!(list.to_css(true), "1,.5em");
+    assert_eq!(list.to_css(false), "1, 0.5em");
+}
@@ -0,0 +1,630 @@
+/*!
+ * `@plugin "brand.wasm";` loads a webassembly module in a sandbox: it can import nothing
+ * but the functions of module `less` below, every call gets `WasmLimits::fuel`, all the calls
+ * of one compile share `WasmLimits::total_fuel` and its memory can't grow past `WasmLimits::memory`
+ *
+ * the module exports `memory`, `alloc(len: i32) -> i32` and `install()`, which registers
+ * what the plugin adds with
+ *
+ * - `register_function(name_ptr, name_len, export_ptr, export_len)`, the export is called
+ *   for the less function `name` in the blocks that load the plugin
+ * - `register_visitor(export_ptr, export_len)`, the export is called with every declaration
+ *   of the evaluated css
+ * - `error(ptr, len)`, fails the current call with the message
+ *
+ * exports take `(ptr: i32, len: i32)` of json written to memory returned by `alloc`
+ * and return `(ptr << 32) | len` of their json result, values are
+ *
+ * ```json
+ * {"type": "number", "value": 16, "unit": "px"}
+ * {"type": "color", "rgb": [255, 0, 0], "alpha": 1}
+ * {"type": "string", "value": "a", "quote": "\"", "escaped": false}
+ * {"type": "keyword", "value": "auto"}
+ * {"type": "list", "separator": ",", "items": []}
+ * ```
+ *
+ * urls, css functions and comments are passed as keywords, a json string or number
+ * returned is a keyword or a unitless number, as less.js does with plain js values
+ *
+ * functions get the array of their arguments, visitors `{"name", "value", "important"}`
+ * and return it changed, or `null` to remove the declaration
+ */
+
+use std::{
+    mem::take,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use thiserror::Error;
+use wasmi::{
+    core::TrapCode, Caller, Config, Engine, Extern, Instance, Linker, Memory, Module, Store,
+    StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::{
+    css::{AtRule, Declaration, Node, Stylesheet},
+    functions::{FunctionError, Functions},
+    value::{Color, List, Number, Quoted, Separator, Value},
+};
+
+/// the sandbox of a module
+#[derive(Debug, Clone, Copy)]
+pub struct WasmLimits {
+    // instructions a call may run, roughly one unit each
+    pub fuel: u64,
+    // fuel of every call of every module in one compile together
+    pub total_fuel: u64,
+    // bytes of linear memory
+    pub memory: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 100_000_000,
+            total_fuel: 1_000_000_000,
+            memory: 16 << 20,
+        }
+    }
+}
+
+/// the fuel left for the modules of one compile, clones share it
+#[derive(Debug, Clone)]
+pub struct FuelBudget(Arc<AtomicU64>);
+
+impl FuelBudget {
+    pub fn new(fuel: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(fuel)))
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn spend(&self, fuel: u64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                Some(left.saturating_sub(fuel))
+            });
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum WasmError {
+    #[error("{0}")]
+    Wasm(#[from] wasmi::Error),
+    #[error("the module does not export `{0}`")]
+    MissingExport(&'static str),
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid value: {0}")]
+    InvalidValue(serde_json::Value),
+    #[error("out of bounds memory access")]
+    OutOfBounds,
+    #[error("all fuel for plugins in this compile is used up")]
+    OutOfTotalFuel,
+    // passed to `error`
+    #[error("{0}")]
+    Message(String),
+}
+
+impl From<wasmi::errors::LinkerError> for WasmError {
+    fn from(error: wasmi::errors::LinkerError) -> Self {
+        WasmError::Wasm(error.into())
+    }
+}
+
+impl From<wasmi::errors::FuelError> for WasmError {
+    fn from(error: wasmi::errors::FuelError) -> Self {
+        WasmError::Wasm(error.into())
+    }
+}
+
+#[derive(Default)]
+struct Host {
+    limits: StoreLimits,
+    // less name and export
+    functions: Vec<(String, String)>,
+    visitors: Vec<String>,
+    error: Option<String>,
+}
+
+struct Sandbox {
+    store: Store<Host>,
+    instance: Instance,
+    fuel: u64,
+    budget: FuelBudget,
+}
+
+/// an instantiated module and what its `install` registered
+pub struct WasmPlugin {
+    pub functions: Functions,
+    visitors: Vec<String>,
+    sandbox: Arc<Mutex<Sandbox>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeclarationJson {
+    name: String,
+    value: serde_json::Value,
+    important: bool,
+}
+
+impl WasmPlugin {
+    /// `budget` is shared by the modules of one compile, `install` doesn't use it
+    pub fn load(wasm: &[u8], limits: &WasmLimits, budget: &FuelBudget) -> Result<Self, WasmError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+        let host = Host {
+            limits: StoreLimitsBuilder::new()
+                .memory_size(limits.memory)
+                .instances(1)
+                .build(),
+            ..Default::default()
+        };
+        let mut store = Store::new(&engine, host);
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(limits.fuel)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(
+                "less",
+                "register_function",
+                |mut caller: Caller<'_, Host>,
+                 name_ptr: i32,
+                 name_len: i32,
+                 export_ptr: i32,
+                 export_len: i32|
+                 -> Result<(), wasmi::Error> {
+                    let name = read_string(&caller, name_ptr, name_len)?;
+                    let export = read_string(&caller, export_ptr, export_len)?;
+                    caller.data_mut().functions.push((name, export));
+                    Ok(())
+                },
+            )?
+            .func_wrap(
+                "less",
+                "register_visitor",
+                |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                    let export = read_string(&caller, ptr, len)?;
+                    caller.data_mut().visitors.push(export);
+                    Ok(())
+                },
+            )?
+            .func_wrap(
+                "less",
+                "error",
+                |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+                    let message = read_string(&caller, ptr, len)?;
+                    caller.data_mut().error = Some(message.clone());
+                    Err(wasmi::Error::new(message))
+                },
+            )?;
+        let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+        let install = instance
+            .get_typed_func::<(), ()>(&store, "install")
+            .map_err(|_| WasmError::MissingExport("install"))?;
+        let installed = install.call(&mut store, ());
+        if let Some(message) = store.data_mut().error.take() {
+            return Err(WasmError::Message(message));
+        }
+        installed?;
+
+        let registered = take(&mut store.data_mut().functions);
+        let visitors = take(&mut store.data_mut().visitors);
+        let sandbox = Arc::new(Mutex::new(Sandbox {
+            store,
+            instance,
+            fuel: limits.fuel,
+            budget: budget.clone(),
+        }));
+        let mut functions = Functions::new();
+        for (name, export) in registered {
+            let sandbox = Arc::clone(&sandbox);
+            functions.register_variadic(&name, move |args| {
+                let args = json!(args.iter().map(to_json).collect::<Vec<_>>());
+                let result = sandbox.lock().unwrap().call_json(&export, &args);
+                result
+                    .and_then(|result| from_json(&result))
+                    .map_err(|error| FunctionError::Message(error.to_string()))
+            });
+        }
+        Ok(Self {
+            functions,
+            visitors,
+            sandbox,
+        })
+    }
+
+    /// runs the visitors on every declaration of `stylesheet`
+    pub fn visit(&self, stylesheet: &mut Stylesheet) -> Result<(), WasmError> {
+        if self.visitors.is_empty() {
+            return Ok(());
+        }
+        let mut sandbox = self.sandbox.lock().unwrap();
+        for visitor in &self.visitors {
+            visit_nodes(&mut sandbox, visitor, &mut stylesheet.nodes)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sandbox {
+    fn memory(&self) -> Result<Memory, WasmError> {
+        self.instance
+            .get_memory(&self.store, "memory")
+            .ok_or(WasmError::MissingExport("memory"))
+    }
+
+    // every call starts with the whole fuel, or what is left of the budget
+    fn call(&mut self, export: &str, input: &[u8]) -> Result<Vec<u8>, WasmError> {
+        let fuel = self.fuel.min(self.budget.remaining());
+        if fuel == 0 {
+            return Err(WasmError::OutOfTotalFuel);
+        }
+        self.store.set_fuel(fuel)?;
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "alloc")
+            .map_err(|_| WasmError::MissingExport("alloc"))?;
+        let function = self
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&self.store, export)?;
+        let len = i32::try_from(input.len()).map_err(|_| WasmError::OutOfBounds)?;
+        let ptr = alloc.call(&mut self.store, len)?;
+        self.memory()?
+            .write(&mut self.store, ptr as u32 as usize, input)
+            .map_err(|_| WasmError::OutOfBounds)?;
+        let result = function.call(&mut self.store, (ptr, len));
+        self.budget.spend(fuel - self.store.get_fuel()?);
+        if let Some(message) = self.store.data_mut().error.take() {
+            return Err(WasmError::Message(message));
+        }
+        let out_of_fuel = |error: &wasmi::Error| error.as_trap_code() == Some(TrapCode::OutOfFuel);
+        if fuel < self.fuel && result.as_ref().is_err_and(out_of_fuel) {
+            return Err(WasmError::OutOfTotalFuel);
+        }
+        let result = result? as u64;
+        let (ptr, len) = ((result >> 32) as usize, (result & 0xffff_ffff) as usize);
+        self.memory()?
+            .data(&self.store)
+            .get(ptr..ptr + len)
+            .map(<[u8]>::to_vec)
+            .ok_or(WasmError::OutOfBounds)
+    }
+
+    // structs keep the order of their fields, `{"name": ...}` comes first for visitors
+    fn call_json(
+        &mut self,
+        export: &str,
+        input: &impl Serialize,
+    ) -> Result<serde_json::Value, WasmError> {
+        let output = self.call(export, &serde_json::to_vec(input)?)?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+
+    fn visit_declaration(
+        &mut self,
+        export: &str,
+        declaration: &Declaration,
+    ) -> Result<Option<Declaration>, WasmError> {
+        let input = DeclarationJson {
+            name: declaration.name.clone(),
+            value: to_json(&declaration.value),
+            important: declaration.important,
+        };
+        match self.call_json(export, &input)? {
+            serde_json::Value::Null => Ok(None),
+            output => {
+                let output: DeclarationJson = serde_json::from_value(output)?;
+                Ok(Some(Declaration {
+                    span: declaration.span.clone(),
+                    name: output.name,
+                    value: from_json(&output.value)?,
+                    important: output.important,
+                }))
+            }
+        }
+    }
+}
+
+fn read_string(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("the module does not export `memory`"))?;
+    let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+    let bytes = memory
+        .data(caller)
+        .get(ptr..ptr + len)
+        .ok_or_else(|| wasmi::Error::new("out of bounds memory access"))?;
+    String::from_utf8(bytes.to_vec()).map_err(|error| wasmi::Error::new(error.to_string()))
+}
+
+// rules left without declarations are removed
+fn visit_nodes(
+    sandbox: &mut Sandbox,
+    export: &str,
+    nodes: &mut Vec<Node>,
+) -> Result<(), WasmError> {
+    let mut visited = Vec::with_capacity(nodes.len());
+    for mut node in take(nodes) {
+        match &mut node {
+            Node::Rule(rule) => {
+                visit_nodes(sandbox, export, &mut rule.declarations)?;
+                if rule.declarations.is_empty() {
+                    continue;
+                }
+            }
+            Node::AtRule(AtRule {
+                block: Some(block), ..
+            }) => visit_nodes(sandbox, export, block)?,
+            Node::Declaration(declaration) => {
+                match sandbox.visit_declaration(export, declaration)? {
+                    Some(changed) => *declaration = changed,
+                    None => continue,
+                }
+            }
+            _ => {}
+        }
+        visited.push(node);
+    }
+    *nodes = visited;
+    Ok(())
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Number(number) => json!({
+            "type": "number",
+            "value": number.value,
+            "unit": number.unit,
+        }),
+        Value::Color(color) => json!({
+            "type": "color",
+            "rgb": color.rgb,
+            "alpha": color.alpha,
+        }),
+        Value::Quoted(quoted) => json!({
+            "type": "string",
+            "value": quoted.value,
+            "quote": quoted.quote,
+            "escaped": quoted.escaped,
+        }),
+        Value::List(list) => json!({
+            "type": "list",
+            "separator": match list.separator {
+                Separator::Space => " ",
+                Separator::Comma => ",",
+            },
+            "items": list.items.iter().map(to_json).collect::<Vec<_>>(),
+        }),
+        _ => json!({
+            "type": "keyword",
+            "value": value.to_string(),
+        }),
+    }
+}
+
+fn from_json(json: &serde_json::Value) -> Result<Value, WasmError> {
+    let invalid = || WasmError::InvalidValue(json.clone());
+    let string = |key: &str| json.get(key).and_then(serde_json::Value::as_str);
+    let number = |key: &str| json.get(key).and_then(serde_json::Value::as_f64);
+    let value = match json {
+        serde_json::Value::String(keyword) => Value::Keyword(keyword.clone()),
+        serde_json::Value::Number(value) => Value::Number(Number {
+            value: value.as_f64().ok_or_else(invalid)?,
+            unit: None,
+        }),
+        _ => match string("type").ok_or_else(invalid)? {
+            "number" => Value::Number(Number {
+                value: number("value").ok_or_else(invalid)?,
+                unit: string("unit").map(str::to_string),
+            }),
+            "color" => {
+                let rgb: [f64; 3] = json
+                    .get("rgb")
+                    .and_then(|rgb| serde_json::from_value(rgb.clone()).ok())
+                    .ok_or_else(invalid)?;
+                Value::Color(Color {
+                    rgb,
+                    alpha: number("alpha").unwrap_or(1.0),
+                    raw: None,
+                })
+            }
+            "string" => Value::Quoted(Quoted {
+                value: string("value").ok_or_else(invalid)?.to_string(),
+                quote: string("quote").and_then(|quote| quote.chars().next()),
+                escaped: json
+                    .get("escaped")
+                    .and_then(serde_json::Value::as_bool)
+                    .unwrap_or(false),
+            }),
+            "keyword" => Value::Keyword(string("value").ok_or_else(invalid)?.to_string()),
+            "list" => Value::List(List {
+                items: json
+                    .get("items")
+                    .and_then(serde_json::Value::as_array)
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .map(from_json)
+                    .collect::<Result<_, _>>()?,
+                separator: match string("separator") {
+                    Some(",") => Separator::Comma,
+                    _ => Separator::Space,
+                },
+            }),
+            _ => return Err(invalid()),
+        },
+    };
+    Ok(value)
+}
+
+#[test]
+fn wasm_plugins() {
+    use crate::{
+        compile,
+        import::MemoryFileSystem,
+        options::{CompileOptions, Options},
+    };
+
+    let module = r#"
+(module
+  (import "less" "register_function" (func $register_function (param i32 i32 i32 i32)))
+  (import "less" "register_visitor" (func $register_visitor (param i32 i32)))
+  (import "less" "error" (func $error (param i32 i32)))
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 4096))
+  (data (i32.const 0) "foo")
+  (data (i32.const 16) "\"bar\"")
+  (data (i32.const 32) "same")
+  (data (i32.const 48) "spin")
+  (data (i32.const 64) "fail")
+  (data (i32.const 80) "no foo today")
+  (data (i32.const 96) "visit")
+  (data (i32.const 112) "null")
+  (func (export "alloc") (param $len i32) (result i32)
+    (global.get $heap)
+    (global.set $heap (i32.add (global.get $heap) (local.get $len))))
+  (func (export "install")
+    (call $register_function (i32.const 0) (i32.const 3) (i32.const 0) (i32.const 3))
+    (call $register_function (i32.const 32) (i32.const 4) (i32.const 32) (i32.const 4))
+    (call $register_function (i32.const 48) (i32.const 4) (i32.const 48) (i32.const 4))
+    (call $register_function (i32.const 64) (i32.const 4) (i32.const 64) (i32.const 4))
+    (call $register_visitor (i32.const 96) (i32.const 5)))
+  (func $result (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+  ;; foo() -> bar
+  (func (export "foo") (param i32 i32) (result i64)
+    (call $result (i32.const 16) (i32.const 5)))
+  ;; same(x) -> x, the array of one argument without its brackets
+  (func (export "same") (param $ptr i32) (param $len i32) (result i64)
+    (call $result
+      (i32.add (local.get $ptr) (i32.const 1))
+      (i32.sub (local.get $len) (i32.const 2))))
+  (func (export "spin") (param i32 i32) (result i64)
+    (loop $forever (br $forever))
+    (i64.const 0))
+  (func (export "fail") (param i32 i32) (result i64)
+    (call $error (i32.const 80) (i32.const 12))
+    (i64.const 0))
+  ;; removes the declarations starting with `-`, {"name":"-
+  (func (export "visit") (param $ptr i32) (param $len i32) (result i64)
+    (if (result i64) (i32.eq (i32.load8_u offset=9 (local.get $ptr)) (i32.const 45))
+      (then (call $result (i32.const 112) (i32.const 4)))
+      (else (call $result (local.get $ptr) (local.get $len))))))
+"#;
+    let directory = std::env::temp_dir().join(format!("less-wasm-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("sandbox.wasm"),
+        wat::parse_str(module).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(directory.join("broken.wasm"), "not wasm").unwrap();
+    let compile_with = |source: &str, options: Options| {
+        let options = CompileOptions {
+            filename: Some(directory.join("main.less")),
+            options,
+            ..Default::default()
+        };
+        compile(source, &options)
+    };
+    let compile = |source: &str| compile_with(source, Options::default());
+
+    let output = compile(
+        ".a {\n  @plugin \"sandbox\";\n  b: foo();\n  c: same(1px \"x\" #f00);\n  -some-d: 1;\n}\n.e {\n  f: foo();\n  -some-g: 1;\n}\n",
+    )
+    .unwrap();
+    assert_eq!(
+        output.css,
+        ".a {\n  b: bar;\n  c: 1px \"x\" #ff0000;\n}\n.e {\n  f: foo();\n}\n"
+    );
+
+    let message = |source: &str, options: Options| {
+        let error = compile_with(source, options).unwrap_err();
+        let diagnostic = &error.diagnostics[0];
+        (diagnostic.code.clone().unwrap(), diagnostic.message.clone())
+    };
+    assert_eq!(
+        message(
+            "@plugin \"sandbox.wasm\";\n.a { b: fail(); }",
+            Options::default()
+        ),
+        (
+            "L0010".to_string(),
+            "Error evaluating function `fail`: no foo today".to_string()
+        )
+    );
+    let limited = |limits: WasmLimits| Options {
+        wasm_limits: limits,
+        ..Default::default()
+    };
+    let (code, spin) = message(
+        "@plugin \"sandbox\";\n.a { b: spin(); }",
+        limited(WasmLimits {
+            fuel: 100_000,
+            ..Default::default()
+        }),
+    );
+    assert_eq!(code, "L0010");
+    assert!(spin.contains("fuel"), "{}", spin);
+    // every call may run, the compile as a whole may not
+    let (code, spin) = message(
+        "@plugin \"sandbox\";\n.a { b: spin(); }",
+        limited(WasmLimits {
+            total_fuel: 100_000,
+            ..Default::default()
+        }),
+    );
+    assert_eq!(code, "L0010");
+    assert!(spin.contains("all fuel for plugins"), "{}", spin);
+    assert_eq!(
+        message("@plugin \"missing\";", Options::default()).0,
+        "L0018"
+    );
+    assert_eq!(
+        message("@plugin \"broken\";", Options::default()).0,
+        "L0019"
+    );
+    // the module wants a page of 64KiB
+    let (code, _) = message(
+        "@plugin \"sandbox\";",
+        limited(WasmLimits {
+            memory: 1024,
+            ..Default::default()
+        }),
+    );
+    assert_eq!(code, "L0019");
+
+    // read from the file system of the compile, found in an include path as imports are
+    let mut file_system = MemoryFileSystem::default();
+    file_system.insert(
+        "plugins/sandbox.wasm",
+        std::fs::read(directory.join("sandbox.wasm")).unwrap(),
+    );
+    let options = CompileOptions {
+        filename: Some("styles/main.less".into()),
+        include_paths: vec!["plugins".into()],
+        ..Default::default()
+    };
+    let output = crate::compile_with(
+        "@plugin \"sandbox\";\n.a { b: foo(); }",
+        &options,
+        std::sync::Arc::new(file_system),
+    )
+    .unwrap();
+    assert_eq!(output.css, ".a {\n  b: bar;\n}\n");
+    std::fs::remove_dir_all(&directory).unwrap();
+}
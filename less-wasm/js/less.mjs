@@ -0,0 +1,54 @@
+// less.render on top of the raw exports of less_wasm.wasm
+//
+//   cargo build -p less-wasm --target wasm32-unknown-unknown --release
+//   const less = await load(await fetch("less_wasm.wasm").then((r) => r.arrayBuffer()));
+//   less.fs.writeFile("theme/vars.less", "@color: red;");
+//   const { css, map, imports } = await less.render("@import 'theme/vars'; a { color: @color }");
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+export async function load(bytes) {
+  const { instance } = await WebAssembly.instantiate(bytes, {});
+  const wasm = instance.exports;
+
+  // copies a string into wasm memory, freed by the caller
+  const write = (text) => {
+    const data = encoder.encode(text);
+    const ptr = wasm.less_alloc(data.length);
+    new Uint8Array(wasm.memory.buffer, ptr, data.length).set(data);
+    return [ptr, data.length];
+  };
+  const call = (name, ...texts) => {
+    const args = texts.map(write);
+    try {
+      return wasm[name](...args.flat());
+    } finally {
+      args.forEach(([ptr, len]) => wasm.less_free(ptr, len));
+    }
+  };
+
+  return {
+    fs: {
+      writeFile: (path, source) => call("less_write_file", path, source),
+      removeFile: (path) => call("less_remove_file", path),
+    },
+    // resolves to { css, map, imports, warnings }, rejects with { message, filename, line, column, ... }
+    render(input, options = {}) {
+      call("less_render", JSON.stringify({ input, options }));
+      const result = JSON.parse(
+        decoder.decode(
+          new Uint8Array(wasm.memory.buffer, wasm.less_result_ptr(), wasm.less_result_len()),
+        ),
+      );
+      if (result.error) {
+        const error = Object.assign(new Error(result.error.message), result.error);
+        return Promise.reject(error);
+      }
+      if (result.map === null) {
+        delete result.map;
+      }
+      return Promise.resolve(result);
+    },
+  };
+}
@@ -0,0 +1,28 @@
+// node less-wasm/js/test.mjs [path to less_wasm.wasm]
+// after cargo build -p less-wasm --target wasm32-unknown-unknown --release
+
+import assert from "node:assert/strict";
+import { readFile } from "node:fs/promises";
+import { load } from "./less.mjs";
+
+const path =
+  process.argv[2] ??
+  new URL("../../target/wasm32-unknown-unknown/release/less_wasm.wasm", import.meta.url);
+const less = await load(await readFile(path));
+
+less.fs.writeFile("theme/vars.less", "@color: red;\n");
+const output = await less.render("@import 'theme/vars';\n.a { color: @color; }\n", {
+  filename: "main.less",
+  sourceMap: {},
+});
+assert.ok(output.css.startsWith(".a {\n  color: red;\n}\n"));
+assert.deepEqual(output.imports, ["theme/vars.less"]);
+assert.ok(JSON.parse(output.map).sources.includes("theme/vars.less"));
+
+less.fs.removeFile("theme/vars.less");
+await assert.rejects(less.render("@import 'theme/vars';"), {
+  message: "'theme/vars.less' wasn't found",
+  line: 1,
+  column: 0,
+});
+console.log("less-wasm: ok");
@@ -0,0 +1,359 @@
+/*!
+ * `less.render` for `wasm32-unknown-unknown`, without wasm-bindgen
+ *
+ * the host copies utf-8 strings in with `less_alloc`, calls an export and reads the json result
+ * at `less_result_ptr` / `less_result_len`, `js/less.mjs` wraps this in the less.js api
+ */
+
+use std::{cell::RefCell, path::PathBuf, sync::Arc};
+
+use less_error::diagnostic::{Diagnostic, JsonDiagnostic};
+use less_to_css::{
+    compile_with,
+    import::{FileSystem, MemoryFileSystem},
+    options::{CompileOptions, Math, RewriteUrls, SourceMapOptions, Variables},
+    Diagnostics,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// the options of `less.render`, unknown ones are ignored as less.js does
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RenderOptions {
+    pub filename: Option<String>,
+    // include paths, searched in the virtual file system
+    pub paths: Vec<String>,
+    pub compress: bool,
+    // `"parens-division"` or `1`
+    pub math: Option<Value>,
+    pub strict_units: bool,
+    pub rewrite_urls: Option<String>,
+    pub rootpath: Option<String>,
+    pub url_args: Option<String>,
+    pub global_vars: Variables,
+    pub modify_vars: Variables,
+    // `true` or the source map options
+    pub source_map: Option<Value>,
+}
+
+/// `sourceMap: {...}`, named as in less.js
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct SourceMapJs {
+    #[serde(rename = "sourceMapURL")]
+    source_map_url: Option<String>,
+    source_map_rootpath: Option<String>,
+    source_map_basepath: Option<String>,
+    output_filename: Option<String>,
+    output_source_files: bool,
+    source_map_file_inline: bool,
+}
+
+impl RenderOptions {
+    pub fn to_compile_options(&self) -> Result<CompileOptions, String> {
+        let mut options = CompileOptions {
+            filename: self.filename.as_ref().map(PathBuf::from),
+            include_paths: self.paths.iter().map(PathBuf::from).collect(),
+            global_vars: self.global_vars.clone(),
+            modify_vars: self.modify_vars.clone(),
+            ..Default::default()
+        };
+        options.options.compress = self.compress;
+        options.options.strict_units = self.strict_units;
+        options.options.rootpath = self.rootpath.clone();
+        options.options.url_args = self.url_args.clone();
+        options.options.math = match &self.math {
+            None => Math::default(),
+            Some(Value::String(math)) => math.parse()?,
+            Some(Value::Number(math)) => math.to_string().parse()?,
+            Some(math) => return Err(format!("invalid math value: {}", math)),
+        };
+        if let Some(rewrite_urls) = &self.rewrite_urls {
+            options.options.rewrite_urls = rewrite_urls.parse::<RewriteUrls>()?;
+        }
+        options.options.source_map = match &self.source_map {
+            None | Some(Value::Bool(false)) | Some(Value::Null) => None,
+            Some(Value::Bool(true)) => Some(SourceMapOptions::default()),
+            Some(source_map) => {
+                let source_map = SourceMapJs::deserialize(source_map)
+                    .map_err(|error| format!("invalid sourceMap option: {}", error))?;
+                Some(SourceMapOptions {
+                    url: source_map.source_map_url,
+                    rootpath: source_map.source_map_rootpath,
+                    basepath: source_map.source_map_basepath,
+                    output_filename: source_map.output_filename,
+                    include_sources: source_map.output_source_files,
+                    inline: source_map.source_map_file_inline,
+                    ..Default::default()
+                })
+            }
+        };
+        Ok(options)
+    }
+}
+
+/// what `less.render` resolves to, `map` is the json of the source map unless it is inline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderOutput {
+    pub css: String,
+    pub map: Option<String>,
+    pub imports: Vec<String>,
+    pub warnings: Vec<JsonDiagnostic>,
+}
+
+/// what `less.render` rejects with, `line` is 1-based and `column` 0-based as in less.js
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderError {
+    pub message: String,
+    pub code: Option<String>,
+    pub filename: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub diagnostics: Vec<JsonDiagnostic>,
+}
+
+impl RenderError {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            code: None,
+            filename: None,
+            line: None,
+            column: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn from_diagnostics(error: &Diagnostics) -> Self {
+        let diagnostics: Vec<_> = error
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_json(&error.files))
+            .collect();
+        let Some(first) = diagnostics.first() else {
+            return Self::new(error.to_string());
+        };
+        let start = first.range.as_ref().map(|range| &range.start);
+        Self {
+            message: first.message.clone(),
+            code: first.code.clone(),
+            filename: first.file.clone(),
+            line: start.map(|start| start.line),
+            column: start.map(|start| start.column - 1),
+            diagnostics,
+        }
+    }
+}
+
+/// the virtual file system `@import` and `@plugin` read from, it is kept between renders
+#[derive(Debug, Clone, Default)]
+pub struct Compiler {
+    file_system: MemoryFileSystem,
+}
+
+impl Compiler {
+    /// a stylesheet, or the bytes of a `.wasm` plugin
+    pub fn write_file(&mut self, path: &str, contents: impl AsRef<[u8]>) {
+        self.file_system.insert(path, contents.as_ref());
+    }
+
+    pub fn remove_file(&mut self, path: &str) {
+        self.file_system.remove(path);
+    }
+
+    pub fn render(
+        &self,
+        input: &str,
+        options: &RenderOptions,
+    ) -> Result<RenderOutput, Box<RenderError>> {
+        let options = options
+            .to_compile_options()
+            .map_err(|error| Box::new(RenderError::new(error)))?;
+        let output = compile_with(input, &options, Arc::new(self.file_system.clone()))
+            .map_err(|error| Box::new(RenderError::from_diagnostics(&error)))?;
+        let inline = options
+            .options
+            .source_map
+            .as_ref()
+            .is_some_and(|source_map| source_map.inline);
+        let json = |diagnostic: &Diagnostic| diagnostic.to_json(&output.files);
+        Ok(RenderOutput {
+            css: output.css,
+            map: output.map.filter(|_| !inline).map(|map| map.to_json()),
+            imports: output
+                .imports
+                .iter()
+                .map(|path| self.file_system.canonicalize(path).display().to_string())
+                .collect(),
+            warnings: output.warnings.iter().map(json).collect(),
+        })
+    }
+
+    /**
+     * `{"input": "...", "options": {...}}` to `{"css": ..., "map": ..., "imports": [...], "warnings": [...]}`
+     * or `{"error": {...}}`
+     */
+    pub fn render_json(&self, request: &str) -> String {
+        #[derive(Deserialize)]
+        struct Request {
+            input: String,
+            #[serde(default)]
+            options: RenderOptions,
+        }
+        let result = serde_json::from_str::<Request>(request)
+            .map_err(|error| {
+                Box::new(RenderError::new(format!(
+                    "invalid render request: {}",
+                    error
+                )))
+            })
+            .and_then(|request| self.render(&request.input, &request.options));
+        match result {
+            Ok(output) => serde_json::to_string(&output),
+            Err(error) => serde_json::to_string(&serde_json::json!({ "error": error })),
+        }
+        .unwrap()
+    }
+}
+
+thread_local! {
+    static COMPILER: RefCell<Compiler> = RefCell::default();
+    // the json written by the last call, read by the host
+    static RESULT: RefCell<String> = RefCell::default();
+}
+
+/// # Safety
+/// `ptr` must come from `less_alloc` with `len` bytes of utf-8
+unsafe fn read_str<'a>(ptr: *const u8, len: usize) -> &'a str {
+    if len == 0 {
+        return "";
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(ptr, len)).unwrap_or_default()
+}
+
+/// room for `len` bytes, written by the host and freed with `less_free`
+#[no_mangle]
+pub extern "C" fn less_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// # Safety
+/// `ptr` and `len` must come from the same `less_alloc` call
+#[no_mangle]
+pub unsafe extern "C" fn less_free(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// # Safety
+/// both buffers must come from `less_alloc`, the contents are written as they are
+#[no_mangle]
+pub unsafe extern "C" fn less_write_file(
+    path: *const u8,
+    path_len: usize,
+    contents: *const u8,
+    contents_len: usize,
+) {
+    let path = read_str(path, path_len);
+    let contents = match contents_len {
+        0 => &[],
+        len => std::slice::from_raw_parts(contents, len),
+    };
+    COMPILER.with(|compiler| compiler.borrow_mut().write_file(path, contents));
+}
+
+/// # Safety
+/// `path` must come from `less_alloc`
+#[no_mangle]
+pub unsafe extern "C" fn less_remove_file(path: *const u8, path_len: usize) {
+    let path = read_str(path, path_len);
+    COMPILER.with(|compiler| compiler.borrow_mut().remove_file(path));
+}
+
+/// # Safety
+/// `request` must come from `less_alloc`, the result is read with `less_result_ptr` and `less_result_len`
+#[no_mangle]
+pub unsafe extern "C" fn less_render(request: *const u8, request_len: usize) {
+    let request = read_str(request, request_len);
+    let result = COMPILER.with(|compiler| compiler.borrow().render_json(request));
+    RESULT.with(|cell| *cell.borrow_mut() = result);
+}
+
+#[no_mangle]
+pub extern "C" fn less_result_ptr() -> *const u8 {
+    RESULT.with(|cell| cell.borrow().as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn less_result_len() -> usize {
+    RESULT.with(|cell| cell.borrow().len())
+}
+
+#[test]
+fn render() {
+    let mut compiler = Compiler::default();
+    compiler.write_file("theme/vars.less", "@color: red;\n");
+    compiler.write_file(
+        "theme/button.less",
+        "@import 'vars';\n.button { color: @color; }\n",
+    );
+    let output = compiler
+        .render(
+            "@import 'theme/button';\n.a { width: (@w * 2); }",
+            &serde_json::from_str(
+                r#"{ "filename": "main.less", "globalVars": { "w": "5px" }, "sourceMap": {} }"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        output.css,
+        ".button {\n  color: red;\n}\n.a {\n  width: 10px;\n}\n"
+    );
+    assert_eq!(output.imports, ["theme/button.less", "theme/vars.less"]);
+    assert!(output.map.unwrap().contains("theme/button.less"));
+
+    let error: Value =
+        serde_json::from_str(&compiler.render_json(r#"{ "input": ".a { b: @c; }" }"#)).unwrap();
+    assert_eq!(error["error"]["message"], "variable @c is undefined");
+    assert_eq!(error["error"]["code"], "L0006");
+    assert_eq!(error["error"]["filename"], "input");
+    assert_eq!(error["error"]["line"], 1);
+    assert_eq!(error["error"]["column"], 8);
+
+    compiler.remove_file("theme/vars.less");
+    let error = compiler.render("@import 'theme/button';", &RenderOptions::default());
+    assert_eq!(error.unwrap_err().message, "'vars.less' wasn't found");
+}
+
+#[test]
+fn exports() {
+    let write = |text: &str| {
+        let ptr = less_alloc(text.len());
+        unsafe { std::ptr::copy_nonoverlapping(text.as_ptr(), ptr, text.len()) };
+        (ptr, text.len())
+    };
+    let (path, path_len) = write("a.less");
+    let (source, source_len) = write(".a { b: c; }");
+    let (request, request_len) =
+        write(r#"{ "input": "@import 'a';", "options": { "compress": true } }"#);
+    let result = unsafe {
+        less_write_file(path, path_len, source, source_len);
+        less_render(request, request_len);
+        for (ptr, len) in [
+            (path, path_len),
+            (source, source_len),
+            (request, request_len),
+        ] {
+            less_free(ptr, len);
+        }
+        read_str(less_result_ptr(), less_result_len()).to_string()
+    };
+    assert_eq!(
+        result,
+        r#"{"css":".a{b:c}","map":null,"imports":["a.less"],"warnings":[]}"#
+    );
+}
@@ -0,0 +1,267 @@
+use std::{env, path::PathBuf};
+
+use less_to_css::options::{CompileOptions, Options, SourceMapOptions, Variables};
+
+pub const USAGE: &str = "\
+usage: lessc [option option=parameter ...] <source> [destination]
+       lessc --batch [option ...] <directory|glob> <output directory>
+
+If source is set to `-' (dash or hyphen-minus), input is read from stdin.
+
+options:
+  -h, --help                   Prints help (this message) and exit.
+  -v, --version                Prints version number and exit.
+  -s, --silent                 Suppresses output of error and warning messages.
+  -l, --lint                   Syntax check only (lint).
+  -w, --watch                  Recompiles when the source or a file it imports changes.
+                               With --batch only the entry points reading the file.
+  -b, --batch                  Compiles every file of a directory or glob except `_' partials.
+  -j=N, --jobs=N               Compiles N files at a time in batch mode, defaults to the cores.
+  -M, --depends                Outputs a makefile import dependency list to stdout.
+  -x, --compress               Compresses output by removing some whitespaces.
+  --no-color                   Disables colorized output.
+  --color                      Colorizes output even when stderr is not a terminal.
+  --error-format=human|json    Prints diagnostics as code frames or JSON lines.
+  --warnings-as-errors         Fails on the first warning.
+  --include-path=PATHS         Sets include paths. Separated by `:'. `;' also supported on windows.
+  --global-var='VAR=VALUE'     Defines a variable that can be referenced by the file.
+  --modify-var='VAR=VALUE'     Modifies a variable already declared in the file.
+  -m=, --math=                 always, parens-division (default) or parens.
+  -su=on|off, --strict-units=on|off
+                               Fails on operations mixing units, e.g. 1px+1em.
+  -rp, --rootpath=URL          Sets rootpath for url rewriting in relative imports and urls.
+  -ru=, --rewrite-urls=        Rewrites relative urls: off (default), local or all.
+                               -ru alone is the same as all.
+  --url-args='QUERYSTRING'     Adds params into url tokens (e.g. 42, cb=42 or 'a=1&b=2').
+  --source-map[=FILENAME]      Outputs a v3 sourcemap to the filename (or output filename.map).
+  --source-map-rootpath=X      Adds this path onto the sourcemap filename and less file paths.
+  --source-map-basepath=X      Sets sourcemap base path, defaults to current working directory.
+  --source-map-include-source  Puts the less files into the map instead of referencing them.
+  --source-map-inline          Puts the map (and any less files) as a base64 data uri into the output css file.
+  --source-map-url=URL         Sets a custom URL to map file, for sourceMappingURL comment
+                               in generated CSS file.
+";
+
+/// the parsed command line of `lessc`
+#[derive(Debug, Default)]
+pub struct Args {
+    // `-` reads stdin
+    pub input: Option<String>,
+    // stdout when not set
+    pub output: Option<String>,
+    pub options: Options,
+    pub include_paths: Vec<PathBuf>,
+    pub global_vars: Variables,
+    pub modify_vars: Variables,
+    // --source-map without a filename writes next to the output
+    pub source_map_filename: Option<String>,
+    pub help: bool,
+    pub version: bool,
+    pub silent: bool,
+    pub lint: bool,
+    pub depends: bool,
+    pub watch: bool,
+    pub batch: bool,
+    // threads in batch mode, the number of cores when not set
+    pub jobs: Option<usize>,
+    // None detects a terminal
+    pub color: Option<bool>,
+    pub json: bool,
+}
+
+// --global-var="color=red" -> ("color", "red")
+fn parse_var(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value.split_once('=').ok_or_else(|| {
+        format!(
+            "invalid variable definition: {}, expected NAME=VALUE",
+            value
+        )
+    })?;
+    let name = name.trim().trim_start_matches('@');
+    if name.is_empty() {
+        return Err(format!("invalid variable definition: {}", value));
+    }
+    Ok((name.to_string(), value.trim().to_string()))
+}
+
+fn parse_switch(name: &str, value: Option<&str>) -> Result<bool, String> {
+    match value {
+        None | Some("on") | Some("true") => Ok(true),
+        Some("off") | Some("false") => Ok(false),
+        Some(value) => Err(format!("invalid value for {}: {}", name, value)),
+    }
+}
+
+impl Args {
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut result = Args::default();
+        let mut files = Vec::new();
+        for arg in args {
+            if arg == "-" || !arg.starts_with('-') {
+                files.push(arg);
+                continue;
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+            let required = || value.ok_or_else(|| format!("{} requires a value", name));
+            match name {
+                "-h" | "--help" => result.help = true,
+                "-v" | "--version" => result.version = true,
+                "-s" | "--silent" => result.silent = true,
+                "-l" | "--lint" => result.lint = true,
+                "-M" | "--depends" => result.depends = true,
+                "-w" | "--watch" => result.watch = true,
+                "-b" | "--batch" => result.batch = true,
+                "-j" | "--jobs" => {
+                    let jobs = required()?;
+                    match jobs.parse() {
+                        Ok(jobs) if jobs > 0 => result.jobs = Some(jobs),
+                        _ => return Err(format!("invalid number of jobs: {}", jobs)),
+                    }
+                }
+                "-x" | "--compress" => result.options.compress = true,
+                "--no-color" => result.color = Some(false),
+                "--color" => result.color = Some(true),
+                "--error-format" => match required()? {
+                    "human" => result.json = false,
+                    "json" => result.json = true,
+                    value => return Err(format!("invalid error format: {}", value)),
+                },
+                "--warnings-as-errors" => result.options.warnings_as_errors = true,
+                "--include-path" => {
+                    result.include_paths.extend(
+                        env::split_paths(required()?).filter(|path| !path.as_os_str().is_empty()),
+                    );
+                }
+                "--global-var" => {
+                    let (name, value) = parse_var(required()?)?;
+                    result.global_vars.insert(name, value);
+                }
+                "--modify-var" => {
+                    let (name, value) = parse_var(required()?)?;
+                    result.modify_vars.insert(name, value);
+                }
+                "-m" | "--math" => result.options.math = required()?.parse()?,
+                "-su" | "--strict-units" => {
+                    result.options.strict_units = parse_switch(name, value)?
+                }
+                "-rp" | "--rootpath" => result.options.rootpath = Some(required()?.to_string()),
+                "-ru" | "--rewrite-urls" => {
+                    result.options.rewrite_urls = value.unwrap_or("all").parse()?
+                }
+                "--url-args" => result.options.url_args = Some(required()?.to_string()),
+                "--source-map" => {
+                    result.source_map();
+                    result.source_map_filename = value.map(str::to_string);
+                }
+                "--source-map-rootpath" => {
+                    result.source_map().rootpath = Some(required()?.to_string())
+                }
+                "--source-map-basepath" => {
+                    result.source_map().basepath = Some(required()?.to_string())
+                }
+                "--source-map-include-source" | "--source-map-less-inline" => {
+                    result.source_map().include_sources = true
+                }
+                "--source-map-inline" | "--source-map-map-inline" => {
+                    result.source_map().inline = true
+                }
+                "--source-map-url" => result.source_map().url = Some(required()?.to_string()),
+                _ => return Err(format!("unrecognised option: {}", name)),
+            }
+        }
+        let mut files = files.into_iter();
+        result.input = files.next();
+        result.output = files.next();
+        if let Some(extra) = files.next() {
+            return Err(format!("unexpected argument: {}", extra));
+        }
+        Ok(result)
+    }
+
+    pub fn compile_options(&self) -> CompileOptions {
+        let mut options = self.options.clone();
+        // sources are listed relative to the working directory unless told otherwise
+        if let Some(source_map) = &mut options.source_map {
+            if source_map.basepath.is_none() {
+                source_map.basepath = std::env::current_dir()
+                    .ok()
+                    .map(|directory| directory.display().to_string());
+            }
+        }
+        CompileOptions {
+            options,
+            filename: None,
+            include_paths: self.include_paths.clone(),
+            global_vars: self.global_vars.clone(),
+            modify_vars: self.modify_vars.clone(),
+            plugins: Vec::new(),
+        }
+    }
+
+    // any --source-map-* flag turns source maps on
+    fn source_map(&mut self) -> &mut SourceMapOptions {
+        self.options.source_map.get_or_insert_with(Default::default)
+    }
+}
+
+#[test]
+fn parse() {
+    use less_to_css::options::{Math, RewriteUrls};
+
+    let args = |args: &[&str]| Args::parse(args.iter().map(|arg| arg.to_string()));
+    let parsed = args(&[
+        "-x",
+        "--include-path=a:b",
+        "--global-var=@color=red",
+        "--modify-var=size = 1px",
+        "--math=parens",
+        "--strict-units=on",
+        "-ru",
+        "--source-map=out.map",
+        "--source-map-include-source",
+        "in.less",
+        "out.css",
+    ])
+    .unwrap();
+    assert_eq!(parsed.input.as_deref(), Some("in.less"));
+    assert_eq!(parsed.output.as_deref(), Some("out.css"));
+    assert!(parsed.options.compress && parsed.options.strict_units);
+    assert_eq!(parsed.options.math, Math::Parens);
+    assert_eq!(parsed.options.rewrite_urls, RewriteUrls::All);
+    assert_eq!(
+        parsed.include_paths,
+        [PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert_eq!(
+        parsed.global_vars,
+        Variables::from([("color".to_string(), "red".to_string())])
+    );
+    assert_eq!(
+        parsed.modify_vars,
+        Variables::from([("size".to_string(), "1px".to_string())])
+    );
+    assert_eq!(parsed.source_map_filename.as_deref(), Some("out.map"));
+    assert!(parsed.options.source_map.unwrap().include_sources);
+
+    assert_eq!(args(&["-"]).unwrap().input.as_deref(), Some("-"));
+    let batch = args(&["--batch", "--jobs=4", "src", "dist"]).unwrap();
+    assert!(batch.batch);
+    assert_eq!(batch.jobs, Some(4));
+    assert_eq!(args(&["-j=0"]).unwrap_err(), "invalid number of jobs: 0");
+    assert_eq!(args(&["--math"]).unwrap_err(), "--math requires a value");
+    assert_eq!(
+        args(&["--nope"]).unwrap_err(),
+        "unrecognised option: --nope"
+    );
+    assert_eq!(
+        args(&["--global-var=red"]).unwrap_err(),
+        "invalid variable definition: red, expected NAME=VALUE"
+    );
+}
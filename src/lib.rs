@@ -0,0 +1,26 @@
+/*!
+ * compiles less to css, for build scripts and servers that embed the compiler
+ *
+ * ```no_run
+ * let options = less::CompileOptions::default();
+ * let output = less::compile_file("styles/main.less".as_ref(), &options)?;
+ * std::fs::write("main.css", output.css)?;
+ * # Ok::<(), Box<dyn std::error::Error>>(())
+ * ```
+ */
+
+pub use less_error::{
+    codes::Code,
+    diagnostic::{Diagnostic, Renderer, Severity},
+};
+pub use less_to_css::{
+    batch::{self, Batch},
+    compile, compile_file, css,
+    functions::{FromValue, FunctionError, Functions},
+    options::{CompileOptions, Math, Options, RewriteUrls, SourceMapOptions, Variables},
+    plugin::Plugin,
+    source_map::SourceMap,
+    value::{self, Value},
+    wasm::WasmLimits,
+    CompileOutput, Diagnostics,
+};
@@ -1,29 +1,341 @@
-use std::{clone, vec};
+mod args;
+mod watch;
 
-use rand::Rng;
+use std::{
+    fs,
+    io::{self, stderr, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process,
+};
 
-fn l<'a>(parent: Vec<Vec<&'a str>>, current: Vec<&'a str>) -> Vec<Vec<&'a str>> {
-    let mut r = vec![];
-    for l3 in current {
-        for l1 in &parent {
-            let v: Vec<_> = l1.clone().into_iter().collect();
-            let mut rI = v.clone();
-            rI.push(l3);
-            r.push(rI);
+use args::{Args, USAGE};
+use less_ast::source::SourceFiles;
+use less_error::diagnostic::{to_json_lines, Diagnostic, Renderer};
+use less_to_css::batch::{self, Batch};
+
+// how diagnostics are written to stderr
+struct Output {
+    renderer: Renderer,
+    json: bool,
+    silent: bool,
+}
+
+impl Output {
+    fn report(&self, diagnostics: &[Diagnostic], files: &SourceFiles) {
+        if self.silent || diagnostics.is_empty() {
+            return;
+        }
+        if self.json {
+            eprint!("{}", to_json_lines(diagnostics, files));
+            return;
+        }
+        for diagnostic in diagnostics {
+            eprint!("{}", self.renderer.render(diagnostic, files));
         }
     }
-
-    r
 }
 
 fn main() {
-    let a = [1,2,3];
-    let mut ai = a.iter();
-    ai.next();
-    let mut a2 = ai.cycle();
-    dbg!(a2.next());
-    dbg!(a2.next());
-    dbg!(a2.next());
-    dbg!(a2.next());
-    dbg!(a2.next());
+    let args = Args::from_env().unwrap_or_else(|message| {
+        eprintln!("lessc: {}\n\n{}", message, USAGE);
+        process::exit(1);
+    });
+    process::exit(run(args));
+}
+
+/// an input file and where its css is written, stdout when `output` is None
+pub struct Entry {
+    pub input: String,
+    pub output: Option<String>,
+}
+
+fn run(args: Args) -> i32 {
+    if args.help {
+        print!("{}", USAGE);
+        return 0;
+    }
+    if args.version {
+        println!("lessc {} (Less Compiler) [Rust]", env!("CARGO_PKG_VERSION"));
+        return 0;
+    }
+    let Some(input) = args.input.clone() else {
+        eprintln!("lessc: no input files\n\n{}", USAGE);
+        return 1;
+    };
+    let output = Output {
+        renderer: Renderer::new(args.color.unwrap_or_else(|| stderr().is_terminal())),
+        json: args.json,
+        silent: args.silent,
+    };
+    if args.batch {
+        return batch(&args, &output, input);
+    }
+    if args.depends && args.output.is_none() {
+        eprintln!("lessc: option --depends requires an output path to be specified");
+        return 1;
+    }
+    let entry = Entry {
+        input,
+        output: args.output.clone(),
+    };
+    if !args.watch {
+        return match compile(&args, &output, &entry) {
+            Compiled {
+                succeeded: true, ..
+            } => 0,
+            Compiled {
+                succeeded: false, ..
+            } => 1,
+        };
+    }
+    if entry.input == "-" {
+        eprintln!("lessc: --watch can't read from stdin");
+        return 1;
+    }
+    let error = watch::watch(1, |_| compile(&args, &output, &entry).files);
+    eprintln!("lessc: {}", error);
+    1
+}
+
+// every entry point of a directory or glob, in parallel
+fn batch(args: &Args, output: &Output, input: String) -> i32 {
+    let unsupported = [
+        (args.depends, "--depends"),
+        (args.lint, "--lint"),
+        (args.source_map_filename.is_some(), "--source-map=FILENAME"),
+    ];
+    if let Some((_, name)) = unsupported.iter().find(|(set, _)| *set) {
+        eprintln!("lessc: option {} can't be used with --batch", name);
+        return 1;
+    }
+    let Some(destination) = &args.output else {
+        eprintln!("lessc: --batch requires an output directory");
+        return 1;
+    };
+    if args.watch {
+        return watch_batch(args, output, &input, Path::new(destination));
+    }
+    let summary = match batch::compile(&Batch {
+        input,
+        output: PathBuf::from(destination),
+        options: args.compile_options(),
+        threads: args.jobs,
+    }) {
+        Ok(summary) => summary,
+        Err(error) => {
+            eprintln!("lessc: {}", error);
+            return 1;
+        }
+    };
+    for compiled in &summary.compiled {
+        match &compiled.result {
+            Ok(warnings) => output.report(warnings, &compiled.files),
+            Err(error) => output.report(std::slice::from_ref(&**error), &compiled.files),
+        }
+    }
+    if !output.silent {
+        for compiled in &summary.compiled {
+            let result = match compiled.result {
+                Ok(_) => format!("-> {}", compiled.output.display()),
+                Err(_) => "failed".to_string(),
+            };
+            eprintln!(
+                "{:>9.1}ms  {} {}",
+                compiled.duration.as_secs_f64() * 1000.0,
+                compiled.input.display(),
+                result
+            );
+        }
+        eprintln!(
+            "lessc: compiled {} files ({} failed) in {:.1}ms on {} thread{}",
+            summary.compiled.len(),
+            summary.failed(),
+            summary.duration.as_secs_f64() * 1000.0,
+            summary.threads,
+            if summary.threads == 1 { "" } else { "s" }
+        );
+    }
+    i32::from(summary.failed() > 0)
+}
+
+// every entry point is compiled on its own, a change recompiles the entries reading the file
+fn watch_batch(args: &Args, output: &Output, input: &str, destination: &Path) -> i32 {
+    let (base, inputs) = match batch::entries(input) {
+        Ok(entries) => entries,
+        Err(error) => {
+            eprintln!("lessc: {}", error);
+            return 1;
+        }
+    };
+    let entries: Vec<Entry> = inputs
+        .iter()
+        .map(|input| {
+            let relative = input.strip_prefix(&base).unwrap_or(input);
+            Entry {
+                input: input.display().to_string(),
+                output: Some(
+                    destination
+                        .join(relative)
+                        .with_extension("css")
+                        .display()
+                        .to_string(),
+                ),
+            }
+        })
+        .collect();
+    let error = watch::watch(entries.len(), |entry| {
+        compile(args, output, &entries[entry]).files
+    });
+    eprintln!("lessc: {}", error);
+    1
+}
+
+// what one compilation of an entry read, diagnostics are already printed
+struct Compiled {
+    succeeded: bool,
+    // the input, every file it imports and where its missing imports were looked for
+    files: Vec<PathBuf>,
+}
+
+fn compile(args: &Args, output: &Output, entry: &Entry) -> Compiled {
+    let mut compiled = Compiled {
+        succeeded: false,
+        files: Vec::new(),
+    };
+    let mut options = args.compile_options();
+    if let Some(source_map) = &mut options.options.source_map {
+        let filename = match (&args.source_map_filename, &entry.output) {
+            (Some(filename), _) => Some(filename.clone()),
+            (None, Some(output)) => Some(format!("{}.map", output)),
+            (None, None) => None,
+        };
+        if filename.is_none() && !source_map.inline {
+            eprintln!("lessc: the sourcemap option only has an optional filename if the css filename is given");
+            return compiled;
+        }
+        // the annotation points at the map relative to the css file
+        if let (None, Some(filename)) = (&source_map.url, &filename) {
+            let css_directory = entry
+                .output
+                .as_deref()
+                .map(|output| Path::new(output).parent());
+            let map_path = Path::new(filename);
+            if css_directory == Some(map_path.parent()) {
+                source_map.url = map_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into());
+            }
+        }
+        source_map.output_filename = entry.output.as_deref().and_then(|output| {
+            Path::new(output)
+                .file_name()
+                .map(|name| name.to_string_lossy().into())
+        });
+        source_map.filename = filename;
+    }
+
+    let result = if entry.input == "-" {
+        let mut source = String::new();
+        if let Err(error) = io::stdin().read_to_string(&mut source) {
+            eprintln!("lessc: stdin: {}", error);
+            return compiled;
+        }
+        // imports start from the working directory
+        options.filename = Some(PathBuf::from("stdin"));
+        less_to_css::compile(&source, &options)
+    } else {
+        compiled.files.push(PathBuf::from(&entry.input));
+        less_to_css::compile_file(Path::new(&entry.input), &options)
+    };
+    let result = match result {
+        Ok(result) => result,
+        Err(error) => {
+            compiled.files.extend(error.imports);
+            compiled.files.extend(error.missing);
+            output.report(&error.diagnostics, &error.files);
+            return compiled;
+        }
+    };
+    compiled.files.extend(result.imports.iter().cloned());
+    compiled.files.extend(result.missing.iter().cloned());
+    output.report(&result.warnings, &result.files);
+    if args.lint {
+        compiled.succeeded = true;
+        return compiled;
+    }
+    if args.depends {
+        let mut line = format!("{}:", entry.output.as_deref().unwrap_or_default());
+        for dependency in &result.imports {
+            line.push(' ');
+            line.push_str(&dependency.display().to_string());
+        }
+        println!("{}", line);
+        compiled.succeeded = true;
+        return compiled;
+    }
+
+    let written = match &entry.output {
+        Some(path) => write_file(path, &result.css),
+        None => io::stdout().write_all(result.css.as_bytes()),
+    };
+    if let Err(error) = written {
+        eprintln!(
+            "lessc: {}: {}",
+            entry.output.as_deref().unwrap_or("stdout"),
+            error
+        );
+        return compiled;
+    }
+    let map = options.options.source_map.as_ref().zip(result.map.as_ref());
+    if let Some((options, map)) = map {
+        if let (false, Some(filename)) = (options.inline, &options.filename) {
+            if let Err(error) = write_file(filename, &map.to_json()) {
+                eprintln!("lessc: {}: {}", filename, error);
+                return compiled;
+            }
+        }
+    }
+    compiled.succeeded = true;
+    compiled
+}
+
+// creates missing directories as lessc does
+fn write_file(path: &str, contents: &str) -> io::Result<()> {
+    if let Some(directory) = Path::new(path).parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, contents)
+}
+
+#[test]
+fn relative_sources() {
+    let directory = std::env::current_dir()
+        .unwrap()
+        .join("target")
+        .join(format!("less-source-map-{}", std::process::id()));
+    fs::create_dir_all(directory.join("sub")).unwrap();
+    fs::write(directory.join("a.less"), "@import 'sub/b';\n.a { b: c; }\n").unwrap();
+    fs::write(directory.join("sub/b.less"), ".b { c: d; }\n").unwrap();
+    let input = directory.join("a.less").display().to_string();
+    let css = directory.join("a.css").display().to_string();
+    let args = Args::parse(["--source-map".to_string(), input.clone(), css.clone()]).unwrap();
+    let output = Output {
+        renderer: Renderer::new(false),
+        json: false,
+        silent: false,
+    };
+    let entry = Entry {
+        input,
+        output: Some(css.clone()),
+    };
+    let compiled = compile(&args, &output, &entry);
+    let map = fs::read_to_string(format!("{}.map", css));
+    fs::remove_dir_all(&directory).unwrap();
+    assert!(compiled.succeeded);
+    // the absolute input is listed relative to the working directory
+    let prefix = format!("target/less-source-map-{}", std::process::id());
+    assert!(map.unwrap().contains(&format!(
+        "\"sources\":[\"{}/sub/b.less\",\"{}/a.less\"]",
+        prefix, prefix
+    )));
 }
@@ -0,0 +1,238 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+// a save usually fires several events, they are collected until it is quiet for this long
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+// canonical so that `a/../b.less` and `b.less` are the same file, a missing file by its directory
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|error| match (path.parent(), path.file_name()) {
+            (Some(directory), Some(name)) => directory.canonicalize().map(|dir| dir.join(name)),
+            _ => Err(error),
+        })
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// which entry points read every file, to recompile only the ones a change affects
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    dependents: HashMap<PathBuf, HashSet<usize>>,
+}
+
+impl DependencyGraph {
+    /// replaces the files read by `entry`, its own input included
+    pub fn update(&mut self, entry: usize, files: impl IntoIterator<Item = PathBuf>) {
+        for dependents in self.dependents.values_mut() {
+            dependents.remove(&entry);
+        }
+        for file in files {
+            self.dependents
+                .entry(normalize(&file))
+                .or_default()
+                .insert(entry);
+        }
+        self.dependents
+            .retain(|_, dependents| !dependents.is_empty());
+    }
+
+    /// the entries reading any of `paths`, sorted
+    pub fn affected<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> Vec<usize> {
+        let mut entries: Vec<usize> = paths
+            .into_iter()
+            .filter_map(|path| self.dependents.get(&normalize(path)))
+            .flatten()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        entries.sort_unstable();
+        entries
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.dependents.len()
+    }
+
+    /// directories are watched instead of files, editors often replace a file on save
+    pub fn directories(&self) -> HashSet<PathBuf> {
+        self.dependents
+            .keys()
+            .filter_map(|file| file.parent())
+            .map(Path::to_path_buf)
+            .collect()
+    }
+}
+
+/**
+ * compiles every entry, then waits for changes and recompiles the entries they affect
+ * `compile` prints its own diagnostics and returns the files the entry read, even when it failed,
+ * and where its missing imports were looked for, so that creating one recompiles it
+ * returns only when watching fails
+ */
+pub fn watch(entries: usize, compile: impl FnMut(usize) -> Vec<PathBuf>) -> notify::Error {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(error) => return error,
+    };
+    run(entries, compile, receiver, |directory| {
+        watcher.watch(directory, RecursiveMode::NonRecursive)
+    })
+}
+
+// `watch` with the events of `receiver`, `watch_directory` is called once per directory to watch
+fn run(
+    entries: usize,
+    mut compile: impl FnMut(usize) -> Vec<PathBuf>,
+    receiver: mpsc::Receiver<notify::Result<Event>>,
+    mut watch_directory: impl FnMut(&Path) -> notify::Result<()>,
+) -> notify::Error {
+    let mut graph = DependencyGraph::default();
+    let mut watched = HashSet::new();
+    let mut pending: Vec<usize> = (0..entries).collect();
+    loop {
+        for &entry in &pending {
+            graph.update(entry, compile(entry));
+        }
+        for directory in graph.directories() {
+            // the directory of a missing import may not exist yet
+            if watched.contains(&directory) || !directory.is_dir() {
+                continue;
+            }
+            if let Err(error) = watch_directory(&directory) {
+                return error;
+            }
+            watched.insert(directory);
+        }
+        eprintln!(
+            "lessc: compiled {} of {} entry points, watching {} files",
+            pending.len(),
+            entries,
+            graph.file_count()
+        );
+
+        pending.clear();
+        while pending.is_empty() {
+            let Ok(event) = receiver.recv() else {
+                return notify::Error::generic("the watcher stopped");
+            };
+            let mut changed = Vec::new();
+            for event in std::iter::once(event)
+                .chain(std::iter::from_fn(|| receiver.recv_timeout(DEBOUNCE).ok()))
+            {
+                match event {
+                    Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                        changed.extend(event.paths)
+                    }
+                    Ok(_) => {}
+                    Err(error) => eprintln!("lessc: {}", error),
+                }
+            }
+            pending = graph.affected(changed.iter().map(PathBuf::as_path));
+        }
+    }
+}
+
+#[test]
+fn dependency_graph() {
+    let directory = std::env::temp_dir().join(format!("less-watch-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("shared")).unwrap();
+    let file = |name: &str| {
+        let path = directory.join(name);
+        std::fs::write(&path, "").unwrap();
+        path
+    };
+    let (a, b, vars) = (file("a.less"), file("b.less"), file("shared/vars.less"));
+
+    let mut graph = DependencyGraph::default();
+    graph.update(0, [a.clone(), vars.clone()]);
+    graph.update(1, [b.clone(), directory.join("shared/../shared/vars.less")]);
+    assert_eq!(graph.file_count(), 3);
+    assert_eq!(graph.affected([vars.as_path()]), [0, 1]);
+    assert_eq!(graph.affected([a.as_path(), directory.as_path()]), [0]);
+    assert_eq!(
+        graph.directories(),
+        HashSet::from([normalize(&directory), normalize(&directory.join("shared"))])
+    );
+
+    // a no longer imports vars.less
+    graph.update(0, [a.clone()]);
+    assert_eq!(graph.affected([vars.as_path()]), [1]);
+    graph.update(1, [b.clone()]);
+    assert_eq!(graph.affected([vars.as_path()]), Vec::<usize>::new());
+    assert_eq!(graph.file_count(), 2);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn rebuilds_dependents() {
+    use notify::event::{CreateKind, ModifyKind};
+    use std::fs;
+
+    let directory = std::env::temp_dir().join(format!("less-rebuild-{}", std::process::id()));
+    fs::create_dir_all(directory.join("shared")).unwrap();
+    fs::write(directory.join("shared/vars.less"), "@size: 1px;\n").unwrap();
+    let entry = |name: &str, source: &str| {
+        let path = directory.join(name);
+        fs::write(&path, source).unwrap();
+        path
+    };
+    let entries = [
+        entry("a.less", "@import 'shared/vars';\n.a { b: @size; }\n"),
+        entry("b.less", "@import 'shared/vars';\n.b { c: @size; }\n"),
+        entry(
+            "c.less",
+            "@import (optional) 'later/theme';\n.c { d: 1px; }\n",
+        ),
+    ];
+    fs::create_dir_all(directory.join("later")).unwrap();
+
+    // the event is queued before, the loop ends once it is read and the sender is gone
+    let rebuilt = |kind, path: &str| {
+        let (sender, receiver) = mpsc::channel();
+        sender
+            .send(Ok(Event::new(kind).add_path(directory.join(path))))
+            .unwrap();
+        drop(sender);
+        let mut compiled = Vec::new();
+        let mut watched = HashSet::new();
+        run(
+            entries.len(),
+            |entry| {
+                compiled.push(entry);
+                let options = less_to_css::options::CompileOptions::default();
+                let output = less_to_css::compile_file(&entries[entry], &options).unwrap();
+                std::iter::once(entries[entry].clone())
+                    .chain(output.imports)
+                    .chain(output.missing)
+                    .collect()
+            },
+            receiver,
+            |directory| {
+                watched.insert(directory.to_path_buf());
+                Ok(())
+            },
+        );
+        assert!(watched.contains(&normalize(&directory.join("later"))));
+        compiled.split_off(entries.len())
+    };
+    // c.less doesn't import vars.less
+    assert_eq!(
+        rebuilt(EventKind::Modify(ModifyKind::Any), "shared/vars.less"),
+        [0, 1]
+    );
+    // only c.less waits for the missing later/theme.less
+    assert_eq!(
+        rebuilt(EventKind::Create(CreateKind::File), "later/theme.less"),
+        [2]
+    );
+    fs::remove_dir_all(&directory).unwrap();
+}